@@ -1,7 +1,7 @@
 import ctypes as ct
 from pathlib import Path
 from enum import IntEnum
-from typing import Callable, Optional, Dict, Union
+from typing import Callable, Optional, Dict, Union, Tuple
 
 import numpy as np
 import matplotlib.pyplot as plt
@@ -25,6 +25,8 @@ FANCY_PARAM_NAMES = {
     'lambda_val': 'λ',
     'epsilon': 'ε',
     'step_size': 'α',
+    'discount': 'γ',
+    'temperature': 'τ',
 }
 
 e21 = ct.CDLL(str(CDLL_FILE))
@@ -47,28 +49,107 @@ class CGpiCb:
             return None
         return cls.CALLBACK(obj)
 
-e21.run_monte_carlo.argtypes = [ct.c_int, ct.POINTER(OUTPUT_ARRAY),
-                                CGpiCb]
+e21.run_monte_carlo.argtypes = [ct.c_int, ct.c_float,
+                                ct.POINTER(OUTPUT_ARRAY), CGpiCb]
 e21.run_monte_carlo.restype = ct.c_int
 
-e21.run_sarsa.argtypes = [ct.c_int, ct.c_float, ct.POINTER(OUTPUT_ARRAY),
-                          CGpiCb]
+e21.run_off_policy_monte_carlo.argtypes = [ct.c_int, ct.c_int, ct.c_float,
+                                           ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+e21.run_off_policy_monte_carlo.restype = ct.c_int
+
+e21.run_sarsa.argtypes = [ct.c_int, ct.c_float, ct.c_int, ct.c_float,
+                          ct.POINTER(OUTPUT_ARRAY), CGpiCb]
 e21.run_sarsa.restype = ct.c_int
 
-e21.run_q_learning.argtypes = [ct.c_int, ct.c_float, ct.POINTER(OUTPUT_ARRAY),
-                               CGpiCb]
+e21.run_sarsa_boltzmann.argtypes = [ct.c_int, ct.c_float, ct.c_float,
+                                    ct.c_float, ct.c_float,
+                                    ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+e21.run_sarsa_boltzmann.restype = ct.c_int
+
+e21.run_sarsa_ucb.argtypes = [ct.c_int, ct.c_float, ct.c_float, ct.c_float,
+                              ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+e21.run_sarsa_ucb.restype = ct.c_int
+
+e21.run_q_learning.argtypes = [ct.c_int, ct.c_float, ct.c_int, ct.c_int,
+                               ct.c_float, ct.POINTER(OUTPUT_ARRAY), CGpiCb]
 e21.run_q_learning.restype = ct.c_int
 
+e21.run_expected_sarsa.argtypes = [ct.c_int, ct.c_float, ct.c_int,
+                                   ct.c_float, ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+e21.run_expected_sarsa.restype = ct.c_int
+
+e21.run_n_step_sarsa.argtypes = [ct.c_int, ct.c_int, ct.c_float,
+                                 ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+e21.run_n_step_sarsa.restype = ct.c_int
+
+e21.run_n_step_tree_backup.argtypes = [ct.c_int, ct.c_int, ct.c_float,
+                                       ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+e21.run_n_step_tree_backup.restype = ct.c_int
+
+e21.run_double_q_learning.argtypes = [ct.c_int, ct.c_float,
+                                      ct.POINTER(OUTPUT_ARRAY),
+                                      ct.POINTER(OUTPUT_ARRAY),
+                                      ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+e21.run_double_q_learning.restype = ct.c_int
+
 e21.run_lfa.argtypes = [ct.c_int, ct.c_float, ct.c_float, ct.c_float,
-                        ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+                        ct.c_int, ct.c_float, ct.POINTER(OUTPUT_ARRAY), CGpiCb]
 e21.run_lfa.restype = ct.c_int
 
+e21.run_true_online_lfa.argtypes = [ct.c_int, ct.c_float, ct.c_float,
+                                    ct.c_float, ct.c_float,
+                                    ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+e21.run_true_online_lfa.restype = ct.c_int
+
+e21.run_mlp.argtypes = [ct.c_int, ct.c_int, ct.c_int, ct.c_int, ct.c_int,
+                        ct.c_float, ct.c_float, ct.c_float,
+                        ct.POINTER(OUTPUT_ARRAY), ct.POINTER(ct.c_float),
+                        CGpiCb]
+e21.run_mlp.restype = ct.c_int
+
+e21.mlp_weight_count.argtypes = [ct.c_int, ct.c_int]
+e21.mlp_weight_count.restype = ct.c_int
+
+e21.run_dqn.argtypes = [ct.c_int, ct.c_int, ct.c_int, ct.c_int, ct.c_int,
+                        ct.c_int, ct.c_int, ct.c_float, ct.c_float,
+                        ct.c_float, ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+e21.run_dqn.restype = ct.c_int
+
+e21.run_reinforce.argtypes = [ct.c_int, ct.c_float, ct.c_int, ct.c_float,
+                              ct.c_float, ct.POINTER(OUTPUT_ARRAY), CGpiCb]
+e21.run_reinforce.restype = ct.c_int
+
+e21.run_dp.argtypes = [ct.c_float, ct.POINTER(OUTPUT_ARRAY)]
+e21.run_dp.restype = ct.c_int
+
 
 class Action(IntEnum):
     Hit = 0
     Stick = 1
 
 
+class TraceMode(IntEnum):
+    Watkins = 0
+    Naive = 1
+
+
+class Sampling(IntEnum):
+    Ordinary = 0
+    Weighted = 1
+
+
+class TraceType(IntEnum):
+    Accumulating = 0
+    Replacing = 1
+    Dutch = 2
+
+
+class Activation(IntEnum):
+    Tanh = 0
+    Relu = 1
+    Sigmoid = 2
+
+
 class ExpectedRewardMatrix:
     def __init__(self, raw_output: OUTPUT_ARRAY):
         self.array = output_array_to_numpy(raw_output)\
@@ -112,6 +193,43 @@ class ExpectedRewardMatrix:
         return '\n'.join(lines)
 
 
+class ActionProbabilityMatrix:
+    def __init__(self, raw_output: OUTPUT_ARRAY):
+        self.array = output_array_to_numpy(raw_output)\
+          .reshape((len(DEALER_RANGE), len(PLAYER_RANGE), NUM_ACTIONS))
+
+    def get_probability(self, dealer: int, player: int,
+                        action: Action) -> float:
+        return self.array[dealer - 1][player - 1][action]
+
+    def plot_hit_probability(self):
+        x = np.array(DEALER_RANGE)
+        y = np.array(PLAYER_RANGE)
+
+        X, Y = np.meshgrid(x, y)
+        Z = np.vectorize(
+            lambda dealer, player: self.get_probability(dealer, player,
+                                                        Action.Hit)
+        )(X, Y)
+
+        ax = plt.axes(projection='3d')
+        ax.set_xlabel('Dealer showing')
+        ax.set_ylabel('Player sum')
+        ax.set_zlabel('Probability of hitting')
+
+        ax.plot_wireframe(X, Y, Z, color='black')
+
+    def __str__(self):
+        lines = []
+        for player in reversed(range(len(PLAYER_RANGE))):
+            line = []
+            for dealer in range(len(DEALER_RANGE)):
+                probability = int(self.array[dealer][player][Action.Hit] * 100)
+                line.append(f'{probability:-4}')
+            lines.append(' '.join(line))
+        return '\n'.join(lines)
+
+
 def output_array_to_numpy(ct_arr: OUTPUT_ARRAY):
     # I was hoping there would be a way to do this that didn't involve
     # iterating in python-land, but whatever, it's a tiny array. More
@@ -126,9 +244,24 @@ def output_array_to_numpy(ct_arr: OUTPUT_ARRAY):
     return np_arr
 
 
+# Receives the weights and biases of a network, layer by layer, every
+# time its values are written.
+class NetworkWeights:
+    def __init__(self, hidden_layers: int=1, width: int=16):
+        count = e21.mlp_weight_count(hidden_layers, width)
+        if count < 0:
+            raise ValueError("invalid network shape")
+        self.array = (ct.c_float * count)()
+
+    @property
+    def values(self) -> np.ndarray:
+        return output_array_to_numpy(self.array)
+
+
 class OutputReceiver:
-    def __init__(self, cb: GpiCb=None):
+    def __init__(self, cb: GpiCb=None, matrix_class=ExpectedRewardMatrix):
         self.array = OUTPUT_ARRAY()
+        self.matrix_class = matrix_class
         self.array_ref = ct.byref(self.array)
         self._cb = cb
         if self._cb is None:
@@ -136,8 +269,8 @@ class OutputReceiver:
         self.errors = 0
 
     @property
-    def matrix(self) -> ExpectedRewardMatrix:
-        return ExpectedRewardMatrix(self.array)
+    def matrix(self):
+        return self.matrix_class(self.array)
 
     def cb(self):
         if self.errors:
@@ -166,9 +299,10 @@ def alg_name(name: str) -> Callable:
 
 
 @alg_name("Monte Carlo")
-def run_monte_carlo(episodes: int, cb: GpiCb=None) -> ExpectedRewardMatrix:
+def run_monte_carlo(episodes: int, cb: GpiCb=None,
+                    discount: float=1.0) -> ExpectedRewardMatrix:
     out = OutputReceiver(cb)
-    result = e21.run_monte_carlo(episodes, out.array_ref, out.cb)
+    result = e21.run_monte_carlo(episodes, discount, out.array_ref, out.cb)
 
     if result != 0:
         raise ValueError(f"run_monte_carlo failed with result {result}")
@@ -176,11 +310,29 @@ def run_monte_carlo(episodes: int, cb: GpiCb=None) -> ExpectedRewardMatrix:
     return out.matrix
 
 
+@alg_name("Off-Policy Monte Carlo")
+def run_off_policy_monte_carlo(episodes: int, cb: GpiCb=None,
+                               sampling: Sampling=Sampling.Weighted,
+                               discount: float=1.0) -> ExpectedRewardMatrix:
+    out = OutputReceiver(cb)
+    result = e21.run_off_policy_monte_carlo(episodes, sampling, discount,
+                                            out.array_ref, out.cb)
+
+    if result != 0:
+        raise ValueError(
+            f"run_off_policy_monte_carlo failed with result {result}"
+        )
+
+    return out.matrix
+
+
 @alg_name("Sarsa(λ)")
-def run_sarsa(episodes: int, lambda_val: float,
-              cb: GpiCb=None) -> ExpectedRewardMatrix:
+def run_sarsa(episodes: int, lambda_val: float, cb: GpiCb=None,
+              trace_type: TraceType=TraceType.Accumulating,
+              discount: float=1.0) -> ExpectedRewardMatrix:
     out = OutputReceiver(cb)
-    result = e21.run_sarsa(episodes, lambda_val, out.array_ref, out.cb)
+    result = e21.run_sarsa(episodes, lambda_val, trace_type, discount,
+                           out.array_ref, out.cb)
 
     if result != 0:
         raise ValueError(f"run_sarsa failed with result {result}")
@@ -188,11 +340,43 @@ def run_sarsa(episodes: int, lambda_val: float,
     return out.matrix
 
 
+@alg_name("Sarsa(λ) with Boltzmann Exploration")
+def run_sarsa_boltzmann(episodes: int, lambda_val: float, temperature: float,
+                        cb: GpiCb=None, temperature_decay: float=1.0,
+                        discount: float=1.0) -> ExpectedRewardMatrix:
+    out = OutputReceiver(cb)
+    result = e21.run_sarsa_boltzmann(episodes, lambda_val, temperature,
+                                     temperature_decay, discount,
+                                     out.array_ref, out.cb)
+
+    if result != 0:
+        raise ValueError(f"run_sarsa_boltzmann failed with result {result}")
+
+    return out.matrix
+
+
+@alg_name("Sarsa(λ) with UCB Exploration")
+def run_sarsa_ucb(episodes: int, lambda_val: float, ucb_c: float,
+                  cb: GpiCb=None, discount: float=1.0) \
+        -> ExpectedRewardMatrix:
+    out = OutputReceiver(cb)
+    result = e21.run_sarsa_ucb(episodes, lambda_val, ucb_c, discount,
+                               out.array_ref, out.cb)
+
+    if result != 0:
+        raise ValueError(f"run_sarsa_ucb failed with result {result}")
+
+    return out.matrix
+
+
 @alg_name("Q-Learning")
-def run_q_learning(episodes: int, lambda_val: float,
-                   cb: GpiCb=None) -> ExpectedRewardMatrix:
+def run_q_learning(episodes: int, lambda_val: float, cb: GpiCb=None,
+                   trace_type: TraceType=TraceType.Accumulating,
+                   trace_mode: TraceMode=TraceMode.Watkins,
+                   discount: float=1.0) -> ExpectedRewardMatrix:
     out = OutputReceiver(cb)
-    result = e21.run_q_learning(episodes, lambda_val, out.array_ref, out.cb)
+    result = e21.run_q_learning(episodes, lambda_val, trace_type, trace_mode,
+                                discount, out.array_ref, out.cb)
 
     if result != 0:
         raise ValueError(f"run_q_learning failed with result {result}")
@@ -200,12 +384,84 @@ def run_q_learning(episodes: int, lambda_val: float,
     return out.matrix
 
 
+@alg_name("Expected Sarsa(λ)")
+def run_expected_sarsa(episodes: int, lambda_val: float, cb: GpiCb=None,
+                       trace_type: TraceType=TraceType.Accumulating,
+                       discount: float=1.0) -> ExpectedRewardMatrix:
+    out = OutputReceiver(cb)
+    result = e21.run_expected_sarsa(episodes, lambda_val, trace_type,
+                                    discount, out.array_ref, out.cb)
+
+    if result != 0:
+        raise ValueError(f"run_expected_sarsa failed with result {result}")
+
+    return out.matrix
+
+
+@alg_name("n-step Sarsa")
+def run_n_step_sarsa(episodes: int, n: int, cb: GpiCb=None,
+                     discount: float=1.0) -> ExpectedRewardMatrix:
+    out = OutputReceiver(cb)
+    result = e21.run_n_step_sarsa(episodes, n, discount, out.array_ref,
+                                  out.cb)
+
+    if result != 0:
+        raise ValueError(f"run_n_step_sarsa failed with result {result}")
+
+    return out.matrix
+
+
+@alg_name("n-step Tree Backup")
+def run_n_step_tree_backup(episodes: int, n: int, cb: GpiCb=None,
+                           discount: float=1.0) -> ExpectedRewardMatrix:
+    out = OutputReceiver(cb)
+    result = e21.run_n_step_tree_backup(episodes, n, discount, out.array_ref,
+                                        out.cb)
+
+    if result != 0:
+        raise ValueError(
+            f"run_n_step_tree_backup failed with result {result}"
+        )
+
+    return out.matrix
+
+
+@alg_name("Double Q-Learning")
+def run_double_q_learning(episodes: int, cb: GpiCb=None,
+                          discount: float=1.0) -> ExpectedRewardMatrix:
+    return run_double_q_learning_estimates(episodes, cb, discount)[0]
+
+
+def run_double_q_learning_estimates(episodes: int, cb: GpiCb=None,
+                                    discount: float=1.0) \
+        -> Tuple[ExpectedRewardMatrix, ExpectedRewardMatrix,
+                 ExpectedRewardMatrix]:
+    '''
+    Returns the values Double Q-learning acts on, along with each of
+    its two estimates.
+    '''
+
+    out = OutputReceiver(cb)
+    first = OutputReceiver()
+    second = OutputReceiver()
+    result = e21.run_double_q_learning(episodes, discount, out.array_ref,
+                                       first.array_ref, second.array_ref,
+                                       out.cb)
+
+    if result != 0:
+        raise ValueError(f"run_double_q_learning failed with result {result}")
+
+    return out.matrix, first.matrix, second.matrix
+
+
 @alg_name("Linear Function Approximation")
 def run_lfa(episodes: int, lambda_val: float, epsilon: float,
-            step_size: float, cb: GpiCb=None) -> ExpectedRewardMatrix:
+            step_size: float, cb: GpiCb=None,
+            trace_type: TraceType=TraceType.Accumulating,
+            discount: float=1.0) -> ExpectedRewardMatrix:
     out = OutputReceiver(cb)
     result = e21.run_lfa(episodes, lambda_val, epsilon, step_size,
-                         out.array_ref, out.cb)
+                         trace_type, discount, out.array_ref, out.cb)
 
     if result != 0:
         raise ValueError(f"run_lfa failed with result {result}")
@@ -213,6 +469,88 @@ def run_lfa(episodes: int, lambda_val: float, epsilon: float,
     return out.matrix
 
 
+@alg_name("True Online Linear Function Approximation")
+def run_true_online_lfa(episodes: int, lambda_val: float, epsilon: float,
+                        step_size: float, cb: GpiCb=None,
+                        discount: float=1.0) -> ExpectedRewardMatrix:
+    out = OutputReceiver(cb)
+    result = e21.run_true_online_lfa(episodes, lambda_val, epsilon,
+                                     step_size, discount, out.array_ref,
+                                     out.cb)
+
+    if result != 0:
+        raise ValueError(f"run_true_online_lfa failed with result {result}")
+
+    return out.matrix
+
+
+@alg_name("Neural Network Function Approximation")
+def run_mlp(episodes: int, epsilon: float, step_size: float,
+            cb: GpiCb=None, hidden_layers: int=1, width: int=16,
+            activation: Activation=Activation.Tanh, q_learning: bool=False,
+            discount: float=1.0,
+            weights: Optional[NetworkWeights]=None) -> ExpectedRewardMatrix:
+    if weights is not None and \
+            len(weights.array) != e21.mlp_weight_count(hidden_layers, width):
+        raise ValueError("weights don't match the network's shape")
+    out = OutputReceiver(cb)
+    result = e21.run_mlp(episodes, hidden_layers, width, activation,
+                         int(q_learning), epsilon, step_size, discount,
+                         out.array_ref,
+                         None if weights is None else weights.array, out.cb)
+
+    if result != 0:
+        raise ValueError(f"run_mlp failed with result {result}")
+
+    return out.matrix
+
+
+@alg_name("DQN")
+def run_dqn(episodes: int, epsilon: float, step_size: float,
+            cb: GpiCb=None, hidden_layers: int=1, width: int=16,
+            activation: Activation=Activation.Tanh, buffer_size: int=10000,
+            batch_size: int=32, sync_interval: int=500,
+            discount: float=1.0) -> ExpectedRewardMatrix:
+    out = OutputReceiver(cb)
+    result = e21.run_dqn(episodes, hidden_layers, width, activation,
+                         buffer_size, batch_size, sync_interval, epsilon,
+                         step_size, discount, out.array_ref, out.cb)
+
+    if result != 0:
+        raise ValueError(f"run_dqn failed with result {result}")
+
+    return out.matrix
+
+
+# Unlike the other learners, this returns the probability of taking each
+# action at every state.
+@alg_name("REINFORCE")
+def run_reinforce(episodes: int, step_size: float, cb: GpiCb=None,
+                  baseline_step_size: Optional[float]=None,
+                  discount: float=1.0) -> ActionProbabilityMatrix:
+    out = OutputReceiver(cb, ActionProbabilityMatrix)
+    baseline = baseline_step_size is not None
+    result = e21.run_reinforce(episodes, step_size, int(baseline),
+                               baseline_step_size if baseline else 0.0,
+                               discount, out.array_ref, out.cb)
+
+    if result != 0:
+        raise ValueError(f"run_reinforce failed with result {result}")
+
+    return out.matrix
+
+
+@alg_name("Dynamic Programming")
+def run_dp(discount: float=1.0) -> ExpectedRewardMatrix:
+    out = OutputReceiver()
+    result = e21.run_dp(discount, out.array_ref)
+
+    if result != 0:
+        raise ValueError(f"run_dp failed with result {result}")
+
+    return out.matrix
+
+
 if __name__ == '__main__':
     print("Output of monte carlo w/ 30,000 episodes:\n")
     print(run_monte_carlo(30_000))
@@ -3,9 +3,31 @@ from easy21 import *
 
 def test_algorithms_do_not_smoke():
     run_monte_carlo(30)
+    run_off_policy_monte_carlo(30)
+    run_off_policy_monte_carlo(30, sampling=Sampling.Ordinary)
     run_lfa(10, 0.5, 0.05, 0.1)
     run_sarsa(1000, 0.5)
+    run_sarsa(1000, 0.9, trace_type=TraceType.Replacing)
+    run_sarsa(1000, 0.5, discount=0.9)
+    run_monte_carlo(30, discount=0.9)
+    run_sarsa_boltzmann(1000, 0.5, 0.5, temperature_decay=0.999)
+    run_sarsa_ucb(1000, 0.5, 2.0)
+    run_lfa(10, 0.5, 0.05, 0.1, trace_type=TraceType.Dutch)
+    run_true_online_lfa(10, 1.0, 0.05, 0.01)
+    run_mlp(10, 0.05, 0.01)
+    run_mlp(10, 0.05, 0.01, hidden_layers=2, activation=Activation.Relu,
+            q_learning=True)
+    run_dqn(10, 0.05, 0.01, batch_size=4, sync_interval=10)
+    run_reinforce(10, 0.01)
+    run_reinforce(10, 0.01, baseline_step_size=0.01)
     run_q_learning(1000, 0.5)
+    run_q_learning(1000, 0.5, trace_mode=TraceMode.Naive)
+    run_expected_sarsa(1000, 0.5)
+    run_double_q_learning(1000)
+    run_n_step_sarsa(1000, 4)
+    run_n_step_tree_backup(1000, 4)
+    run_dp()
+    run_dp(discount=0.9)
 
 
 def test_diffs_and_mse_work():
@@ -33,6 +55,28 @@ def test_callbacks_work():
     run_lfa(5, 0.5, 0.05, 0.1, callback)
     assert times_called == 5
 
+    def probability_callback(obj):
+        nonlocal times_called
+        assert isinstance(obj, ActionProbabilityMatrix)
+        times_called += 1
+
+    times_called = 0
+    run_reinforce(4, 0.01, probability_callback)
+    assert times_called == 4
+
+
+def test_mlp_weights_work():
+    weights = NetworkWeights(hidden_layers=2, width=8)
+    run_mlp(10, 0.05, 0.01, hidden_layers=2, width=8, weights=weights)
+    assert len(weights.values) == 3 * 8 + 9 * 8 + 9 * 2
+    assert any(weights.values != 0)
+
+
+def test_double_q_learning_estimates_work():
+    combined, first, second = run_double_q_learning_estimates(1000)
+    assert combined.get_max_diff(first) > 0
+    assert combined.get_max_diff(second) > 0
+
 
 def test_alg_names_work():
     assert run_monte_carlo.alg_name == "Monte Carlo"
@@ -0,0 +1,39 @@
+// Measures how many episodes per second each tabular learner can play.
+//
+// Run it with:
+//
+//     cargo run --release --example benchmark
+
+extern crate easy21;
+
+use std::time::Instant;
+
+use easy21::shortcuts::{self, Config};
+
+const EPISODES: i32 = 1_000_000;
+
+fn report<F: FnOnce()>(name: &str, run: F) {
+    let start = Instant::now();
+    run();
+    let elapsed = start.elapsed();
+    let seconds = elapsed.as_secs() as f64 +
+                  elapsed.subsec_nanos() as f64 * 1e-9;
+    println!("{:>10}: {:>10.0} episodes/sec", name,
+             EPISODES as f64 / seconds);
+}
+
+fn main() {
+    let config = Config::default();
+
+    println!("Playing {} episodes with each learner...", EPISODES);
+
+    report("mc", || {
+        shortcuts::run_monte_carlo(EPISODES, &config);
+    });
+    report("sarsa", || {
+        shortcuts::run_sarsa(EPISODES, 0.5, &config);
+    });
+    report("qlearning", || {
+        shortcuts::run_q_learning(EPISODES, 0.5, &config);
+    });
+}
@@ -0,0 +1,403 @@
+// This module implements the casino Blackjack game described in
+// Example 5.1 of Sutton & Barto's "Reinforcement Learning: An
+// Introduction", as a companion environment to Easy21.
+//
+// Cards are drawn from an infinite deck, face cards count as 10 and
+// aces count as either 1 or 11. The player always hits while their sum
+// is below 12, since no decision is needed there, so every state the
+// agent sees has a player sum between 12 and 21. The dealer hits until
+// their sum is at least 17.
+//
+// A natural (an ace and a ten-card dealt as the first two cards) beats
+// any other hand summing to 21, while two naturals are a draw.
+
+use rand::Rng;
+
+use environment::Environment;
+use game::{Action, Reward, ACTIONS, PLAYER_LOSE_REWARD, PLAYER_WIN_REWARD,
+           DRAW_REWARD, NO_REWARD};
+use game::Action::*;
+use gpi::Alg;
+
+pub const MIN_PLAYER_SUM: i32 = 12;
+pub const MAX_SUM: i32 = 21;
+pub const ACE: i32 = 1;
+pub const MAX_CARD: i32 = 10;
+const NUM_RANKS: i32 = 13;
+const DEALER_STICK_MIN: i32 = 17;
+
+// The extra value an ace has when it's counted as 11 instead of 1.
+const USABLE_ACE_BONUS: i32 = 10;
+
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
+pub struct State {
+    // The player's current sum, counting a usable ace as 11.
+    pub player: i32,
+
+    // The dealer's showing card (with an ace as 1) while the game is in
+    // progress, or the dealer's final sum once the player has stuck.
+    pub dealer: i32,
+
+    // Whether the player holds an ace that can be counted as 11
+    // without going bust.
+    pub usable_ace: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Hand {
+    // The sum of the hand's cards, counting every ace as 1.
+    hard_sum: i32,
+    has_ace: bool,
+    cards: i32,
+}
+
+impl Hand {
+    fn new() -> Self {
+        Hand { hard_sum: 0, has_ace: false, cards: 0 }
+    }
+
+    fn from_state(state: State) -> Self {
+        Hand {
+            hard_sum: if state.usable_ace {
+                state.player - USABLE_ACE_BONUS
+            } else {
+                state.player
+            },
+            has_ace: state.usable_ace,
+            cards: 0,
+        }
+    }
+
+    fn add(&mut self, card: i32) {
+        self.hard_sum += card;
+        self.has_ace = self.has_ace || card == ACE;
+        self.cards += 1;
+    }
+
+    fn usable_ace(&self) -> bool {
+        self.has_ace && self.hard_sum + USABLE_ACE_BONUS <= MAX_SUM
+    }
+
+    fn sum(&self) -> i32 {
+        if self.usable_ace() {
+            self.hard_sum + USABLE_ACE_BONUS
+        } else {
+            self.hard_sum
+        }
+    }
+
+    fn is_natural(&self) -> bool {
+        self.cards == 2 && self.sum() == MAX_SUM
+    }
+}
+
+// Returns the player's reward once both hands are final.
+fn outcome(player: i32, player_natural: bool, dealer: i32,
+           dealer_natural: bool) -> Reward {
+    if player > MAX_SUM {
+        PLAYER_LOSE_REWARD
+    } else if player_natural != dealer_natural {
+        if player_natural { PLAYER_WIN_REWARD } else { PLAYER_LOSE_REWARD }
+    } else if dealer > MAX_SUM || player > dealer {
+        PLAYER_WIN_REWARD
+    } else if player == dealer {
+        DRAW_REWARD
+    } else {
+        PLAYER_LOSE_REWARD
+    }
+}
+
+pub struct Blackjack {
+    rng: Box<dyn Rng>,
+
+    // The dealer's hand, including the card that's hidden from the
+    // player.
+    dealer: Hand,
+
+    // Whether the player's hand is still the natural they were dealt.
+    player_natural: bool,
+}
+
+impl Blackjack {
+    pub fn new<T: Rng + 'static>(rng: T) -> Self {
+        Blackjack {
+            rng: Box::new(rng),
+            dealer: Hand::new(),
+            player_natural: false,
+        }
+    }
+
+    fn draw(&mut self) -> i32 {
+        self.rng.gen_range(1, NUM_RANKS + 1).min(MAX_CARD)
+    }
+}
+
+impl Environment for Blackjack {
+    type State = State;
+    type Action = Action;
+
+    fn actions() -> &'static [Action] {
+        ACTIONS
+    }
+
+    fn num_states() -> usize {
+        (2 * (MAX_SUM + 1 - MIN_PLAYER_SUM) * (MAX_CARD + 1 - ACE)) as usize
+    }
+
+    fn state_index(state: State) -> Option<usize> {
+        if (MIN_PLAYER_SUM..=MAX_SUM).contains(&state.player) &&
+           (ACE..=MAX_CARD).contains(&state.dealer) {
+            let player = state.player - MIN_PLAYER_SUM;
+            let dealer = state.dealer - ACE;
+            let ace = if state.usable_ace { 1 } else { 0 };
+            let index = (ace * (MAX_SUM + 1 - MIN_PLAYER_SUM) + player) *
+                        (MAX_CARD + 1 - ACE) + dealer;
+            Some(index as usize)
+        } else {
+            None
+        }
+    }
+
+    fn state_at(index: usize) -> State {
+        let index = index as i32;
+        let dealers = MAX_CARD + 1 - ACE;
+        let players = MAX_SUM + 1 - MIN_PLAYER_SUM;
+        State {
+            player: index / dealers % players + MIN_PLAYER_SUM,
+            dealer: index % dealers + ACE,
+            usable_ace: index / dealers / players == 1,
+        }
+    }
+
+    fn reset(&mut self) -> State {
+        let mut player = Hand::new();
+        let mut dealer = Hand::new();
+
+        player.add(self.draw());
+        player.add(self.draw());
+
+        // The dealer's first card is showing, while the second is hidden.
+        let showing = self.draw();
+        dealer.add(showing);
+        dealer.add(self.draw());
+
+        self.player_natural = player.is_natural();
+        self.dealer = dealer;
+
+        while player.sum() < MIN_PLAYER_SUM {
+            player.add(self.draw());
+        }
+
+        State {
+            player: player.sum(),
+            dealer: showing,
+            usable_ace: player.usable_ace(),
+        }
+    }
+
+    fn reset_to(&mut self, state: State) {
+        let mut dealer = Hand::new();
+        dealer.add(state.dealer);
+        dealer.add(self.draw());
+
+        self.player_natural = false;
+        self.dealer = dealer;
+    }
+
+    fn step(&mut self, state: State, action: Action) -> (State, Reward) {
+        let mut player = Hand::from_state(state);
+
+        match action {
+            Hit => {
+                self.player_natural = false;
+                player.add(self.draw());
+                let next = State {
+                    player: player.sum(),
+                    dealer: state.dealer,
+                    usable_ace: player.usable_ace(),
+                };
+                let reward = if next.player > MAX_SUM {
+                    PLAYER_LOSE_REWARD
+                } else {
+                    NO_REWARD
+                };
+                (next, reward)
+            },
+            Stick => {
+                let mut dealer = self.dealer;
+                let dealer_natural = dealer.is_natural();
+                while dealer.sum() < DEALER_STICK_MIN {
+                    dealer.add(self.draw());
+                }
+                let reward = outcome(state.player, self.player_natural,
+                                     dealer.sum(), dealer_natural);
+                (State { dealer: dealer.sum(), ..state }, reward)
+            }
+        }
+    }
+
+    fn is_terminal(&self, state: State) -> bool {
+        state.player > MAX_SUM || state.dealer >= DEALER_STICK_MIN
+    }
+}
+
+// Print the expected reward for every state given that we take the
+// optimal action at each state, once for hands with a usable ace and
+// once for hands without one.
+pub fn print_optimal_values<T: Alg<Blackjack>>(alg: &T) {
+    for &usable_ace in [true, false].iter() {
+        println!("{} usable ace:", if usable_ace { "With" } else { "No" });
+        for player in (MIN_PLAYER_SUM..MAX_SUM + 1).rev() {
+            for dealer in ACE..MAX_CARD + 1 {
+                let state = State { player, dealer, usable_ace };
+                let action = alg.choose_best_action(state);
+                let value = alg.get_expected_reward(state, action);
+                let marker = if action == Hit { 'H' } else { 'S' };
+                print!("{:4}{} ", (value * 100.0) as i32, marker);
+            }
+            println!("  <- player sum = {}", player);
+        }
+        for _ in ACE..MAX_CARD + 1 {
+            print!("------");
+        }
+        println!();
+        for dealer in ACE..MAX_CARD + 1 {
+            if dealer == ACE {
+                print!("    A ");
+            } else {
+                print!("{:5} ", dealer);
+            }
+        }
+        println!("  <- dealer showing");
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+
+    use blackjack::*;
+
+    #[test]
+    fn hand_counts_usable_aces() {
+        let mut hand = Hand::new();
+        hand.add(ACE);
+        hand.add(6);
+
+        assert_eq!(hand.sum(), 17);
+        assert!(hand.usable_ace());
+
+        hand.add(8);
+
+        assert_eq!(hand.sum(), 15);
+        assert!(!hand.usable_ace());
+    }
+
+    #[test]
+    fn hand_detects_naturals() {
+        let mut hand = Hand::new();
+        hand.add(ACE);
+        hand.add(MAX_CARD);
+
+        assert!(hand.is_natural());
+
+        let mut hand = Hand::new();
+        hand.add(5);
+        hand.add(6);
+        hand.add(MAX_CARD);
+
+        assert_eq!(hand.sum(), MAX_SUM);
+        assert!(!hand.is_natural());
+    }
+
+    #[test]
+    fn hand_from_state_works() {
+        let mut hand = Hand::from_state(State {
+            player: 13,
+            dealer: 5,
+            usable_ace: true,
+        });
+        hand.add(MAX_CARD);
+
+        assert_eq!(hand.sum(), 13);
+        assert!(!hand.usable_ace());
+    }
+
+    #[test]
+    fn outcome_works() {
+        assert_eq!(outcome(22, false, 25, false), PLAYER_LOSE_REWARD);
+        assert_eq!(outcome(20, false, 25, false), PLAYER_WIN_REWARD);
+        assert_eq!(outcome(20, false, 19, false), PLAYER_WIN_REWARD);
+        assert_eq!(outcome(19, false, 19, false), DRAW_REWARD);
+        assert_eq!(outcome(18, false, 19, false), PLAYER_LOSE_REWARD);
+        assert_eq!(outcome(21, true, 21, false), PLAYER_WIN_REWARD);
+        assert_eq!(outcome(21, false, 21, true), PLAYER_LOSE_REWARD);
+        assert_eq!(outcome(21, true, 21, true), DRAW_REWARD);
+    }
+
+    #[test]
+    fn state_index_works() {
+        let mut seen = vec![false; Blackjack::num_states()];
+
+        for &usable_ace in [true, false].iter() {
+            for player in MIN_PLAYER_SUM..MAX_SUM + 1 {
+                for dealer in ACE..MAX_CARD + 1 {
+                    let state = State { player, dealer, usable_ace };
+                    let index = Blackjack::state_index(state).unwrap();
+                    assert!(!seen[index]);
+                    seen[index] = true;
+                    assert_eq!(Blackjack::state_at(index), state);
+                }
+            }
+        }
+
+        assert!(seen.iter().all(|&s| s));
+        assert_eq!(Blackjack::state_index(State {
+            player: 22,
+            dealer: 5,
+            usable_ace: false,
+        }), None);
+    }
+
+    #[test]
+    fn reset_deals_decision_states() {
+        let mut env = Blackjack::new(thread_rng());
+
+        for _ in 0..300 {
+            let state = env.reset();
+
+            assert!(state.player >= MIN_PLAYER_SUM && state.player <= MAX_SUM);
+            assert!(state.dealer >= ACE && state.dealer <= MAX_CARD);
+            assert!(!env.is_terminal(state));
+        }
+    }
+
+    #[test]
+    fn reset_to_deals_the_dealer_a_hidden_card() {
+        let mut env = Blackjack::new(thread_rng());
+        let state = State { player: 20, dealer: 10, usable_ace: false };
+
+        env.reset_to(state);
+
+        assert_eq!(env.dealer.cards, 2);
+        assert!(!env.player_natural);
+        assert!(env.dealer.sum() > 10);
+    }
+
+    #[test]
+    fn episodes_end() {
+        let mut env = Blackjack::new(thread_rng());
+
+        for _ in 0..300 {
+            let mut state = env.reset();
+            while !env.is_terminal(state) {
+                state = env.step(state, Hit).0;
+            }
+
+            let state = env.reset();
+            let (end, _) = env.step(state, Stick);
+            assert!(env.is_terminal(end), "{:?} should be terminal", end);
+        }
+    }
+}
@@ -1,11 +1,20 @@
 use libc::{c_int, c_float};
 use rand::Rng;
 
-use game::{State, Action, Deck, MIN_SUM, MAX_SUM, MIN_CARD, MAX_CARD,
-           NUM_ACTIONS};
-use gpi::{Alg, Gpi, EpsilonGreedyPolicy};
+use game::{State, Action, Easy21, Rules, MIN_SUM, MAX_SUM, MIN_CARD,
+           MAX_CARD, NUM_ACTIONS};
+use gpi::{Alg, AlgPolicy, Policy, Gpi};
+use dp::ValueIteration;
+use double_qlearning::DoubleQLearning;
 use shortcuts;
+use shortcuts::Config;
 use validators;
+use traces::TraceType;
+use qlearning::TraceMode;
+use off_policy_montecarlo::Sampling;
+use mlp::{Activation, NetworkShape, NUM_INPUTS};
+use dqn::ReplaySettings;
+use reinforce::Reinforce;
 
 
 const DEALER_SIZE: usize = (MAX_CARD + 1 - MIN_CARD) as usize;
@@ -15,19 +24,72 @@ const PLAYER_SIZE: usize = (MAX_SUM + 1 - MIN_SUM) as usize;
 const OUTPUT_SIZE: usize = DEALER_SIZE * PLAYER_SIZE * NUM_ACTIONS;
 
 
+// Trace types are passed as integers, in the order of TRACE_TYPES.
+const TRACE_TYPES: &[TraceType] = &[
+    TraceType::Accumulating,
+    TraceType::Replacing,
+    TraceType::Dutch,
+];
+
+fn to_trace_type(value: c_int) -> Option<TraceType> {
+    if value >= 0 {
+        TRACE_TYPES.get(value as usize).cloned()
+    } else {
+        None
+    }
+}
+
+// Q(lambda) trace modes are passed as integers, in the order of
+// TRACE_MODES.
+const TRACE_MODES: &[TraceMode] = &[TraceMode::Watkins, TraceMode::Naive];
+
+fn to_trace_mode(value: c_int) -> Option<TraceMode> {
+    if value >= 0 {
+        TRACE_MODES.get(value as usize).cloned()
+    } else {
+        None
+    }
+}
+
+// Kinds of importance sampling are passed as integers, in the order of
+// SAMPLINGS.
+const SAMPLINGS: &[Sampling] = &[Sampling::Ordinary, Sampling::Weighted];
+
+fn to_sampling(value: c_int) -> Option<Sampling> {
+    if value >= 0 {
+        SAMPLINGS.get(value as usize).cloned()
+    } else {
+        None
+    }
+}
+
+// Activations are passed as integers, in the order of ACTIVATIONS.
+const ACTIVATIONS: &[Activation] = &[
+    Activation::Tanh,
+    Activation::Relu,
+    Activation::Sigmoid,
+];
+
+fn to_activation(value: c_int) -> Option<Activation> {
+    if value >= 0 {
+        ACTIVATIONS.get(value as usize).cloned()
+    } else {
+        None
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn get_output_size() -> c_int {
     OUTPUT_SIZE as i32
 }
 
-fn write_expected_reward_matrix<T: Alg>(alg: &T, output: *mut c_float) {
+// Writes the given hit and stick values of every state to the output.
+fn write_matrix<F: Fn(State) -> (f32, f32)>(values: F, output: *mut c_float) {
     let mut i = 0;
 
     for dealer in MIN_CARD..MAX_CARD + 1 {
         for player in MIN_SUM..MAX_SUM + 1 {
-            let state = State { dealer, player };
-            let hit = alg.get_expected_reward(state, Action::Hit);
-            let stick = alg.get_expected_reward(state, Action::Stick);
+            let (hit, stick) = values(State { dealer, player });
 
             unsafe {
                 *output.offset(i) = hit;
@@ -39,30 +101,70 @@ fn write_expected_reward_matrix<T: Alg>(alg: &T, output: *mut c_float) {
     }
 }
 
-fn run_gpi<T: Deck, U: Rng, V: Alg>(
-    gpi: &mut Gpi<T, EpsilonGreedyPolicy<U, V>>,
+// Writes the values, e.g. a network's weights, to the output, which must
+// be large enough to hold them.
+fn write_values(values: &[f32], output: *mut c_float) {
+    for (i, &value) in values.iter().enumerate() {
+        unsafe {
+            *output.add(i) = value;
+        }
+    }
+}
+
+fn write_expected_reward_matrix<T: Alg>(alg: &T, output: *mut c_float) {
+    write_matrix(|state| (
+        alg.get_expected_reward(state, Action::Hit),
+        alg.get_expected_reward(state, Action::Stick)
+    ), output);
+}
+
+fn write_action_probability_matrix<T: Rng>(policy: &Reinforce<T>,
+                                           output: *mut c_float) {
+    write_matrix(|state| {
+        let probabilities = policy.action_probabilities(state);
+        (probabilities[0], probabilities[1])
+    }, output);
+}
+
+// Plays the episodes, writing to the output via `write` after the last
+// one, or after every one if there's a callback.
+fn run_policy<P: Policy, F: Fn(&P, *mut c_float)>(
+    gpi: &mut Gpi<Easy21, P>,
     episodes: c_int,
     output: *mut c_float,
-    cb: Option<extern "C" fn()>
+    cb: Option<extern "C" fn()>,
+    write: F
 ) {
     match cb {
         None => {
             gpi.play_episodes(episodes);
-            write_expected_reward_matrix(&gpi.policy.alg, output);
+            write(&gpi.policy, output);
         },
         Some(func) => {
             for _ in 0..episodes {
                 gpi.play_episode();
-                write_expected_reward_matrix(&gpi.policy.alg, output);
+                write(&gpi.policy, output);
                 func();
             }
         }
     }
 }
 
+fn run_gpi<P: AlgPolicy>(
+    gpi: &mut Gpi<Easy21, P>,
+    episodes: c_int,
+    output: *mut c_float,
+    cb: Option<extern "C" fn()>
+) {
+    run_policy(gpi, episodes, output, cb, |policy, output| {
+        write_expected_reward_matrix(policy.alg(), output);
+    });
+}
+
 #[no_mangle]
 pub extern "C" fn run_monte_carlo(
     episodes: c_int,
+    discount: c_float,
     output: *mut c_float,
     cb: Option<extern "C" fn()>
 ) -> i32 {
@@ -70,7 +172,41 @@ pub extern "C" fn run_monte_carlo(
         return -1;
     }
 
-    let mut gpi = shortcuts::run_monte_carlo(0);
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    let config = Config { discount, ..Config::default() };
+    let mut gpi = shortcuts::run_monte_carlo(0, &config);
+
+    run_gpi(&mut gpi, episodes, output, cb);
+
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn run_off_policy_monte_carlo(
+    episodes: c_int,
+    sampling: c_int,
+    discount: c_float,
+    output: *mut c_float,
+    cb: Option<extern "C" fn()>
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    let sampling = match to_sampling(sampling) {
+        Some(sampling) => sampling,
+        None => return -1,
+    };
+
+    let config = Config { discount, ..Config::default() };
+    let mut gpi = shortcuts::run_off_policy_monte_carlo(0, sampling, &config);
 
     run_gpi(&mut gpi, episodes, output, cb);
 
@@ -81,6 +217,91 @@ pub extern "C" fn run_monte_carlo(
 pub extern "C" fn run_sarsa(
     episodes: c_int,
     lambda: c_float,
+    trace_type: c_int,
+    discount: c_float,
+    output: *mut c_float,
+    cb: Option<extern "C" fn()>,
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    if !validators::lambda(lambda) {
+        return -1;
+    }
+
+    let trace_type = match to_trace_type(trace_type) {
+        Some(trace_type) => trace_type,
+        None => return -1,
+    };
+
+    let config = Config { trace_type, discount, ..Config::default() };
+    let mut gpi = shortcuts::run_sarsa(0, lambda, &config);
+
+    run_gpi(&mut gpi, episodes, output, cb);
+
+    0
+}
+
+// Like run_sarsa(), but explores by choosing actions with probabilities
+// given by a softmax over their values, whose temperature is multiplied
+// by `temperature_decay` after every episode.
+#[no_mangle]
+pub extern "C" fn run_sarsa_boltzmann(
+    episodes: c_int,
+    lambda: c_float,
+    temperature: c_float,
+    temperature_decay: c_float,
+    discount: c_float,
+    output: *mut c_float,
+    cb: Option<extern "C" fn()>,
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    if !validators::lambda(lambda) {
+        return -1;
+    }
+
+    if !validators::temperature(temperature) {
+        return -1;
+    }
+
+    if !validators::temperature_decay(temperature_decay) {
+        return -1;
+    }
+
+    let config = Config {
+        temperature,
+        temperature_decay,
+        discount,
+        ..Config::default()
+    };
+    let alg = shortcuts::sarsa(lambda, &config);
+    let mut gpi = shortcuts::run_easy21_boltzmann(0, alg, &config);
+
+    run_gpi(&mut gpi, episodes, output, cb);
+
+    0
+}
+
+// Like run_sarsa(), but explores via upper confidence bounds whose
+// degree of exploration is `ucb_c`.
+#[no_mangle]
+pub extern "C" fn run_sarsa_ucb(
+    episodes: c_int,
+    lambda: c_float,
+    ucb_c: c_float,
+    discount: c_float,
     output: *mut c_float,
     cb: Option<extern "C" fn()>,
 ) -> i32 {
@@ -88,11 +309,21 @@ pub extern "C" fn run_sarsa(
         return -1;
     }
 
+    if !validators::discount(discount) {
+        return -1;
+    }
+
     if !validators::lambda(lambda) {
         return -1;
     }
 
-    let mut gpi = shortcuts::run_sarsa(0, lambda);
+    if !validators::ucb_c(ucb_c) {
+        return -1;
+    }
+
+    let config = Config { ucb_c, discount, ..Config::default() };
+    let alg = shortcuts::sarsa(lambda, &config);
+    let mut gpi = shortcuts::run_easy21_ucb(0, alg, &config);
 
     run_gpi(&mut gpi, episodes, output, cb);
 
@@ -103,6 +334,9 @@ pub extern "C" fn run_sarsa(
 pub extern "C" fn run_q_learning(
     episodes: c_int,
     lambda: c_float,
+    trace_type: c_int,
+    trace_mode: c_int,
+    discount: c_float,
     output: *mut c_float,
     cb: Option<extern "C" fn()>,
 ) -> i32 {
@@ -110,23 +344,188 @@ pub extern "C" fn run_q_learning(
         return -1;
     }
 
+    if !validators::discount(discount) {
+        return -1;
+    }
+
     if !validators::lambda(lambda) {
         return -1;
     }
 
-    let mut gpi = shortcuts::run_q_learning(0, lambda);
+    let trace_type = match to_trace_type(trace_type) {
+        Some(trace_type) => trace_type,
+        None => return -1,
+    };
+
+    let trace_mode = match to_trace_mode(trace_mode) {
+        Some(trace_mode) => trace_mode,
+        None => return -1,
+    };
+
+    let config = Config { trace_type, discount, ..Config::default() };
+    let mut gpi = match trace_mode {
+        TraceMode::Watkins => shortcuts::run_q_learning(0, lambda, &config),
+        TraceMode::Naive => shortcuts::run_naive_q_learning(0, lambda,
+                                                            &config),
+    };
 
     run_gpi(&mut gpi, episodes, output, cb);
 
     0
 }
 
+#[no_mangle]
+pub extern "C" fn run_expected_sarsa(
+    episodes: c_int,
+    lambda: c_float,
+    trace_type: c_int,
+    discount: c_float,
+    output: *mut c_float,
+    cb: Option<extern "C" fn()>,
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    if !validators::lambda(lambda) {
+        return -1;
+    }
+
+    let trace_type = match to_trace_type(trace_type) {
+        Some(trace_type) => trace_type,
+        None => return -1,
+    };
+
+    let config = Config { trace_type, discount, ..Config::default() };
+    let mut gpi = shortcuts::run_expected_sarsa(0, lambda, &config);
+
+    run_gpi(&mut gpi, episodes, output, cb);
+
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn run_n_step_sarsa(
+    episodes: c_int,
+    n: c_int,
+    discount: c_float,
+    output: *mut c_float,
+    cb: Option<extern "C" fn()>,
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    if !validators::n(n) {
+        return -1;
+    }
+
+    let config = Config { discount, ..Config::default() };
+    let mut gpi = shortcuts::run_n_step_sarsa(0, n as usize, &config);
+
+    run_gpi(&mut gpi, episodes, output, cb);
+
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn run_n_step_tree_backup(
+    episodes: c_int,
+    n: c_int,
+    discount: c_float,
+    output: *mut c_float,
+    cb: Option<extern "C" fn()>,
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    if !validators::n(n) {
+        return -1;
+    }
+
+    let config = Config { discount, ..Config::default() };
+    let mut gpi = shortcuts::run_n_step_tree_backup(0, n as usize, &config);
+
+    run_gpi(&mut gpi, episodes, output, cb);
+
+    0
+}
+
+// Like the other entry points, this writes the values the algorithm acts
+// on (the mean of its two estimates) to `output`, but it also writes
+// each estimate on its own to `first_output` and `second_output`.
+#[no_mangle]
+pub extern "C" fn run_double_q_learning(
+    episodes: c_int,
+    discount: c_float,
+    output: *mut c_float,
+    first_output: *mut c_float,
+    second_output: *mut c_float,
+    cb: Option<extern "C" fn()>,
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    let config = Config { discount, ..Config::default() };
+    let mut gpi = shortcuts::run_double_q_learning(0, &config);
+
+    match cb {
+        None => {
+            gpi.play_episodes(episodes);
+            write_double_q_learning_matrices(&gpi.policy.alg, output,
+                                             first_output, second_output);
+        },
+        Some(func) => {
+            for _ in 0..episodes {
+                gpi.play_episode();
+                write_double_q_learning_matrices(&gpi.policy.alg, output,
+                                                 first_output,
+                                                 second_output);
+                func();
+            }
+        }
+    }
+
+    0
+}
+
+fn write_double_q_learning_matrices(
+    alg: &DoubleQLearning,
+    output: *mut c_float,
+    first_output: *mut c_float,
+    second_output: *mut c_float
+) {
+    write_expected_reward_matrix(alg, output);
+    write_expected_reward_matrix(&alg.first_estimate(), first_output);
+    write_expected_reward_matrix(&alg.second_estimate(), second_output);
+}
+
 #[no_mangle]
 pub extern "C" fn run_lfa(
     episodes: c_int,
     lambda: c_float,
     epsilon: c_float,
     step_size: c_float,
+    trace_type: c_int,
+    discount: c_float,
     output: *mut c_float,
     cb: Option<extern "C" fn()>,
 ) -> i32 {
@@ -134,10 +533,223 @@ pub extern "C" fn run_lfa(
         return -1;
     }
 
+    if !validators::discount(discount) {
+        return -1;
+    }
+
     if !validators::lambda(lambda) {
         return -1;
     }
 
+    let trace_type = match to_trace_type(trace_type) {
+        Some(trace_type) => trace_type,
+        None => return -1,
+    };
+
+    if !validators::epsilon(epsilon) {
+        return -1;
+    }
+
+    if !validators::step_size(step_size) {
+        return -1;
+    }
+
+    let config = Config {
+        epsilon: Some(epsilon),
+        step_size: Some(step_size),
+        trace_type,
+        discount,
+        ..Config::default()
+    };
+    let mut gpi = shortcuts::run_lfa(0, lambda, &config);
+
+    run_gpi(&mut gpi, episodes, output, cb);
+
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn run_true_online_lfa(
+    episodes: c_int,
+    lambda: c_float,
+    epsilon: c_float,
+    step_size: c_float,
+    discount: c_float,
+    output: *mut c_float,
+    cb: Option<extern "C" fn()>,
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    if !validators::lambda(lambda) {
+        return -1;
+    }
+
+    if !validators::epsilon(epsilon) {
+        return -1;
+    }
+
+    if !validators::step_size(step_size) {
+        return -1;
+    }
+
+    let config = Config {
+        epsilon: Some(epsilon),
+        step_size: Some(step_size),
+        discount,
+        ..Config::default()
+    };
+    let mut gpi = shortcuts::run_true_online_lfa(0, lambda, &config);
+
+    run_gpi(&mut gpi, episodes, output, cb);
+
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn run_mlp(
+    episodes: c_int,
+    hidden_layers: c_int,
+    width: c_int,
+    activation: c_int,
+    q_learning: c_int,
+    epsilon: c_float,
+    step_size: c_float,
+    discount: c_float,
+    output: *mut c_float,
+    weights: *mut c_float,
+    cb: Option<extern "C" fn()>,
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    if !validators::hidden_layers(hidden_layers) {
+        return -1;
+    }
+
+    if !validators::width(width) {
+        return -1;
+    }
+
+    let activation = match to_activation(activation) {
+        Some(activation) => activation,
+        None => return -1,
+    };
+
+    if !validators::epsilon(epsilon) {
+        return -1;
+    }
+
+    if !validators::step_size(step_size) {
+        return -1;
+    }
+
+    let config = Config {
+        epsilon: Some(epsilon),
+        step_size: Some(step_size),
+        discount,
+        network: NetworkShape {
+            hidden_layers: hidden_layers as usize,
+            width: width as usize,
+            activation,
+        },
+        ..Config::default()
+    };
+    let mut gpi = if q_learning != 0 {
+        shortcuts::run_mlp_q_learning(0, &config)
+    } else {
+        shortcuts::run_mlp(0, &config)
+    };
+
+    run_policy(&mut gpi, episodes, output, cb, |policy, output| {
+        write_expected_reward_matrix(policy.alg(), output);
+        if !weights.is_null() {
+            write_values(&policy.alg().network().to_values(), weights);
+        }
+    });
+
+    0
+}
+
+// Returns how many values run_mlp() writes to its weights, or -1 if the
+// shape is invalid.
+#[no_mangle]
+pub extern "C" fn mlp_weight_count(hidden_layers: c_int, width: c_int) -> i32 {
+    if !validators::hidden_layers(hidden_layers) {
+        return -1;
+    }
+
+    if !validators::width(width) {
+        return -1;
+    }
+
+    let shape = NetworkShape {
+        hidden_layers: hidden_layers as usize,
+        width: width as usize,
+        ..NetworkShape::default()
+    };
+
+    shape.num_values(NUM_INPUTS, NUM_ACTIONS) as i32
+}
+
+#[no_mangle]
+pub extern "C" fn run_dqn(
+    episodes: c_int,
+    hidden_layers: c_int,
+    width: c_int,
+    activation: c_int,
+    buffer_size: c_int,
+    batch_size: c_int,
+    sync_interval: c_int,
+    epsilon: c_float,
+    step_size: c_float,
+    discount: c_float,
+    output: *mut c_float,
+    cb: Option<extern "C" fn()>,
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    if !validators::hidden_layers(hidden_layers) {
+        return -1;
+    }
+
+    if !validators::width(width) {
+        return -1;
+    }
+
+    let activation = match to_activation(activation) {
+        Some(activation) => activation,
+        None => return -1,
+    };
+
+    if !validators::buffer_size(buffer_size) {
+        return -1;
+    }
+
+    if !validators::batch_size(batch_size) {
+        return -1;
+    }
+
+    if !validators::sync_interval(sync_interval) {
+        return -1;
+    }
+
     if !validators::epsilon(epsilon) {
         return -1;
     }
@@ -146,15 +758,93 @@ pub extern "C" fn run_lfa(
         return -1;
     }
 
-    let mut gpi = shortcuts::run_lfa(episodes, lambda, epsilon, step_size);
+    let config = Config {
+        epsilon: Some(epsilon),
+        step_size: Some(step_size),
+        discount,
+        network: NetworkShape {
+            hidden_layers: hidden_layers as usize,
+            width: width as usize,
+            activation,
+        },
+        replay: ReplaySettings {
+            buffer_size: buffer_size as usize,
+            batch_size: batch_size as usize,
+            sync_interval: sync_interval as usize,
+        },
+        ..Config::default()
+    };
+    let mut gpi = shortcuts::run_dqn(0, &config);
 
     run_gpi(&mut gpi, episodes, output, cb);
 
     0
 }
 
+// Unlike the other learners, this writes the probability of hitting and
+// sticking at every state, rather than their expected rewards.
+#[no_mangle]
+pub extern "C" fn run_reinforce(
+    episodes: c_int,
+    step_size: c_float,
+    baseline: c_int,
+    baseline_step_size: c_float,
+    discount: c_float,
+    output: *mut c_float,
+    cb: Option<extern "C" fn()>,
+) -> i32 {
+    if !validators::episodes(episodes) {
+        return -1;
+    }
+
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    if !validators::step_size(step_size) {
+        return -1;
+    }
+
+    if baseline != 0 && !validators::step_size(baseline_step_size) {
+        return -1;
+    }
+
+    let config = Config {
+        step_size: Some(step_size),
+        baseline_step_size: if baseline != 0 {
+            Some(baseline_step_size)
+        } else {
+            None
+        },
+        discount,
+        ..Config::default()
+    };
+    let mut gpi = shortcuts::run_reinforce(0, &config);
+
+    run_policy(&mut gpi, episodes, output, cb,
+               write_action_probability_matrix);
+
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn run_dp(discount: c_float, output: *mut c_float) -> i32 {
+    if !validators::discount(discount) {
+        return -1;
+    }
+
+    let alg = ValueIteration::with_rules_and_discount(&Rules::default(),
+                                                      discount);
+
+    write_expected_reward_matrix(&alg, output);
+
+    0
+}
+
 #[cfg(test)]
 mod tests {
+    use std::ptr;
+
     use gpi::tests::DumbAlg;
     use game::Action;
     use c_api::*;
@@ -166,18 +856,176 @@ mod tests {
 
         write_expected_reward_matrix(&alg, output.as_mut_ptr());
 
-        for i in 0..OUTPUT_SIZE {
-            assert_eq!(output[i], 5.0);
+        for value in output.iter() {
+            assert_eq!(*value, 5.0);
         }
     }
 
     #[test]
     fn test_run_monte_carlo_works() {
-        assert_eq!(run_monte_carlo(5, [0.0; OUTPUT_SIZE].as_mut_ptr(), None), 0);
+        assert_eq!(run_monte_carlo(5, 1.0, [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                   None), 0);
+    }
+
+    #[test]
+    fn test_run_monte_carlo_returns_err_if_invalid_discount() {
+        assert_eq!(run_monte_carlo(5, 1.5, [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                   None), -1);
+    }
+
+    #[test]
+    fn test_run_off_policy_monte_carlo_works() {
+        assert_eq!(run_off_policy_monte_carlo(5, 0, 1.0,
+                                              [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                              None), 0);
+        assert_eq!(run_off_policy_monte_carlo(5, 1, 1.0,
+                                              [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                              None), 0);
+        assert_eq!(run_off_policy_monte_carlo(5, 2, 1.0,
+                                              [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                              None), -1);
+    }
+
+    #[test]
+    fn test_run_q_learning_works() {
+        for trace_mode in 0..2 {
+            assert_eq!(run_q_learning(5, 0.5, 0, trace_mode, 1.0,
+                                      [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                      None), 0);
+        }
+        assert_eq!(run_q_learning(5, 0.5, 0, 2, 1.0,
+                                  [0.0; OUTPUT_SIZE].as_mut_ptr(), None), -1);
+    }
+
+    #[test]
+    fn test_run_expected_sarsa_works() {
+        assert_eq!(run_expected_sarsa(5, 0.5, 0, 1.0,
+                                      [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                      None), 0);
+        assert_eq!(run_expected_sarsa(5, 2.0, 0, 1.0,
+                                      [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                      None), -1);
+    }
+
+    #[test]
+    fn test_trace_types_work() {
+        assert_eq!(to_trace_type(2), Some(TraceType::Dutch));
+        assert_eq!(to_trace_type(3), None);
+        assert_eq!(to_trace_type(-1), None);
+        assert_eq!(run_sarsa(5, 0.5, 1, 1.0, [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                             None), 0);
+        assert_eq!(run_sarsa(5, 0.5, 3, 1.0, [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                             None), -1);
+    }
+
+    #[test]
+    fn test_run_sarsa_with_other_policies_works() {
+        assert_eq!(run_sarsa_boltzmann(5, 0.5, 0.5, 0.99, 1.0,
+                                       [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                       None), 0);
+        assert_eq!(run_sarsa_boltzmann(5, 0.5, 0.0, 0.99, 1.0,
+                                       [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                       None), -1);
+        assert_eq!(run_sarsa_ucb(5, 0.5, 2.0, 1.0,
+                                 [0.0; OUTPUT_SIZE].as_mut_ptr(), None), 0);
+        assert_eq!(run_sarsa_ucb(5, 0.5, -1.0, 1.0,
+                                 [0.0; OUTPUT_SIZE].as_mut_ptr(), None), -1);
+    }
+
+    #[test]
+    fn test_run_n_step_works() {
+        assert_eq!(run_n_step_sarsa(5, 4, 1.0,
+                                    [0.0; OUTPUT_SIZE].as_mut_ptr(), None), 0);
+        assert_eq!(run_n_step_tree_backup(5, 4, 1.0,
+                                          [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                          None), 0);
+        assert_eq!(run_n_step_sarsa(5, 0, 1.0,
+                                    [0.0; OUTPUT_SIZE].as_mut_ptr(), None), -1);
+    }
+
+    #[test]
+    fn test_run_double_q_learning_works() {
+        let mut output = [0.0; OUTPUT_SIZE];
+        let mut first = [0.0; OUTPUT_SIZE];
+        let mut second = [0.0; OUTPUT_SIZE];
+
+        assert_eq!(run_double_q_learning(100, 1.0, output.as_mut_ptr(),
+                                         first.as_mut_ptr(),
+                                         second.as_mut_ptr(), None), 0);
+
+        for i in 0..OUTPUT_SIZE {
+            assert_eq!(output[i], (first[i] + second[i]) / 2.0);
+        }
+    }
+
+    #[test]
+    fn test_run_true_online_lfa_works() {
+        assert_eq!(run_true_online_lfa(5, 1.0, 0.05, 0.01, 1.0,
+                                       [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                       None), 0);
+        assert_eq!(run_true_online_lfa(5, 1.0, 0.05, -1.0, 1.0,
+                                       [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                       None), -1);
+    }
+
+    #[test]
+    fn test_run_mlp_works() {
+        let mut output = [0.0; OUTPUT_SIZE];
+        assert_eq!(run_mlp(5, 2, 8, 1, 1, 0.05, 0.01, 1.0,
+                           output.as_mut_ptr(), ptr::null_mut(), None), 0);
+        assert!(output.iter().any(|&value| value != 0.0));
+        assert_eq!(run_mlp(5, 3, 8, 0, 0, 0.05, 0.01, 1.0,
+                           [0.0; OUTPUT_SIZE].as_mut_ptr(), ptr::null_mut(),
+                           None), -1);
+        assert_eq!(run_mlp(5, 1, 8, 3, 0, 0.05, 0.01, 1.0,
+                           [0.0; OUTPUT_SIZE].as_mut_ptr(), ptr::null_mut(),
+                           None), -1);
+    }
+
+    #[test]
+    fn test_run_mlp_writes_weights() {
+        let count = mlp_weight_count(2, 8);
+        assert_eq!(count, 3 * 8 + 9 * 8 + 9 * 2);
+        assert_eq!(mlp_weight_count(3, 8), -1);
+
+        let mut weights = vec![0.0; count as usize];
+        assert_eq!(run_mlp(5, 2, 8, 1, 1, 0.05, 0.01, 1.0,
+                           [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                           weights.as_mut_ptr(), None), 0);
+        assert!(weights.iter().any(|&value| value != 0.0));
+    }
+
+    #[test]
+    fn test_run_dqn_works() {
+        assert_eq!(run_dqn(5, 1, 8, 0, 100, 4, 10, 0.05, 0.01, 1.0,
+                           [0.0; OUTPUT_SIZE].as_mut_ptr(), None), 0);
+        assert_eq!(run_dqn(5, 1, 8, 0, 100, 0, 10, 0.05, 0.01, 1.0,
+                           [0.0; OUTPUT_SIZE].as_mut_ptr(), None), -1);
+    }
+
+    #[test]
+    fn test_run_reinforce_works() {
+        let mut output = [0.0; OUTPUT_SIZE];
+        assert_eq!(run_reinforce(5, 0.1, 1, 0.1, 1.0, output.as_mut_ptr(),
+                                 None), 0);
+        for i in 0..OUTPUT_SIZE / 2 {
+            assert!((output[i * 2] + output[i * 2 + 1] - 1.0).abs() < 1e-6);
+        }
+        assert_eq!(run_reinforce(5, 0.1, 1, -1.0, 1.0,
+                                 [0.0; OUTPUT_SIZE].as_mut_ptr(), None), -1);
+        assert_eq!(run_reinforce(5, 0.1, 0, -1.0, 1.0,
+                                 [0.0; OUTPUT_SIZE].as_mut_ptr(), None), 0);
+    }
+
+    #[test]
+    fn test_run_dp_works() {
+        assert_eq!(run_dp(1.0, [0.0; OUTPUT_SIZE].as_mut_ptr()), 0);
+        assert_eq!(run_dp(1.5, [0.0; OUTPUT_SIZE].as_mut_ptr()), -1);
     }
 
     #[test]
     fn test_run_monte_carlo_returns_err_if_invalid_episodes() {
-        assert_eq!(run_monte_carlo(-1, [0.0; OUTPUT_SIZE].as_mut_ptr(), None), -1);
+        assert_eq!(run_monte_carlo(-1, 1.0, [0.0; OUTPUT_SIZE].as_mut_ptr(),
+                                   None), -1);
     }
 }
@@ -0,0 +1,393 @@
+// This module saves and loads the learned state of algorithms and
+// policies, so that long runs can be checkpointed and resumed later.
+//
+// Checkpoints are plain text files. The first line identifies the
+// format and its version:
+//
+//     easy21-checkpoint 1
+//
+// The rest of the file consists of named sections. Each section begins
+// with a header line containing the word "section", the section's name
+// and the number of rows in it, followed by that many rows of
+// whitespace-separated fields:
+//
+//     section value_fn 2
+//     1 4 hit 0.25
+//     1 4 stick -0.5
+//
+// The sections currently in use are:
+//
+//   * `alg` - a single row with the name of the algorithm that was
+//     saved, e.g. `sarsa`.
+//   * `episodes` - a single row with the number of episodes played.
+//   * `value_fn` - rows of `dealer player action value`, where action
+//     is `hit` or `stick`.
+//   * `step_sizer_visits` - rows of `dealer player action visits`.
+//   * `times_visited` - rows of `dealer player visits`, used by
+//     the epsilon-greedy policy.
+//   * `weights` - one row per weight of a linear function approximator.
+//
+// Only learned state is saved; hyperparameters like lambda are expected
+// to be provided again when loading. Eligibility traces are also not
+// saved, since they are reset at the beginning of every episode.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use game::{State, Action};
+
+const MAGIC: &str = "easy21-checkpoint";
+
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    Parse(String),
+    UnsupportedVersion(u32),
+    MissingSection(String),
+    WrongAlg { expected: String, found: String },
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CheckpointError::Io(ref err) => write!(f, "{}", err),
+            CheckpointError::Parse(ref msg) => {
+                write!(f, "invalid checkpoint: {}", msg)
+            },
+            CheckpointError::UnsupportedVersion(version) => {
+                write!(f, "unsupported checkpoint version {}", version)
+            },
+            CheckpointError::MissingSection(ref name) => {
+                write!(f, "checkpoint is missing section '{}'", name)
+            },
+            CheckpointError::WrongAlg { ref expected, ref found } => {
+                write!(f, "checkpoint is for '{}', not '{}'", found, expected)
+            },
+        }
+    }
+}
+
+impl Error for CheckpointError {}
+
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> Self {
+        CheckpointError::Io(err)
+    }
+}
+
+type Row = Vec<String>;
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Checkpoint {
+    sections: BTreeMap<String, Vec<Row>>,
+}
+
+fn parse_error<T>(msg: String) -> Result<T, CheckpointError> {
+    Err(CheckpointError::Parse(msg))
+}
+
+fn parse_field<T: ::std::str::FromStr>(field: &str) -> Result<T, CheckpointError> {
+    match field.parse::<T>() {
+        Ok(value) => Ok(value),
+        Err(_) => parse_error(format!("unable to parse '{}'", field)),
+    }
+}
+
+fn expect_fields(row: &[String], count: usize) -> Result<(), CheckpointError> {
+    if row.len() == count {
+        Ok(())
+    } else {
+        parse_error(format!("expected {} fields, found {:?}", count, row))
+    }
+}
+
+fn action_to_str(action: Action) -> &'static str {
+    match action {
+        Action::Hit => "hit",
+        Action::Stick => "stick",
+    }
+}
+
+fn parse_action(field: &str) -> Result<Action, CheckpointError> {
+    match field {
+        "hit" => Ok(Action::Hit),
+        "stick" => Ok(Action::Stick),
+        _ => parse_error(format!("invalid action '{}'", field)),
+    }
+}
+
+impl Checkpoint {
+    pub fn new() -> Self {
+        Checkpoint { sections: BTreeMap::new() }
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, CheckpointError> {
+        let file = File::open(path)?;
+        Checkpoint::read(&mut BufReader::new(file))
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        self.write(&mut file)
+    }
+
+    pub fn read<R: BufRead>(input: &mut R) -> Result<Self, CheckpointError> {
+        let mut lines = input.lines();
+        let header = match lines.next() {
+            Some(line) => line?,
+            None => return parse_error(String::from("file is empty")),
+        };
+        let header: Vec<&str> = header.split_whitespace().collect();
+        if header.len() != 2 || header[0] != MAGIC {
+            return parse_error(String::from("not a checkpoint file"));
+        }
+        let version = parse_field::<u32>(header[1])?;
+        if version != FORMAT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion(version));
+        }
+
+        let mut checkpoint = Checkpoint::new();
+
+        while let Some(line) = lines.next() {
+            let line = line?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+            if fields.len() != 3 || fields[0] != "section" {
+                return parse_error(format!("expected section, found '{}'",
+                                           line));
+            }
+            let count = parse_field::<usize>(fields[2])?;
+            let mut rows = Vec::with_capacity(count);
+            for _ in 0..count {
+                let row = match lines.next() {
+                    Some(row) => row?,
+                    None => return parse_error(format!(
+                        "section '{}' ended early", fields[1]
+                    )),
+                };
+                rows.push(row.split_whitespace().map(String::from).collect());
+            }
+            checkpoint.sections.insert(String::from(fields[1]), rows);
+        }
+
+        Ok(checkpoint)
+    }
+
+    pub fn write<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        writeln!(output, "{} {}", MAGIC, FORMAT_VERSION)?;
+        for (name, rows) in self.sections.iter() {
+            writeln!(output, "section {} {}", name, rows.len())?;
+            for row in rows.iter() {
+                writeln!(output, "{}", row.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn has_section(&self, name: &str) -> bool {
+        self.sections.contains_key(name)
+    }
+
+    fn section(&self, name: &str) -> Result<&Vec<Row>, CheckpointError> {
+        match self.sections.get(name) {
+            Some(rows) => Ok(rows),
+            None => Err(CheckpointError::MissingSection(String::from(name))),
+        }
+    }
+
+    fn set_section(&mut self, name: &str, mut rows: Vec<Row>) {
+        // Tables are usually stored in hash maps, so sort them to make
+        // the output deterministic.
+        rows.sort();
+        self.sections.insert(String::from(name), rows);
+    }
+
+    pub fn set_alg(&mut self, name: &str) {
+        self.set_section("alg", vec![vec![String::from(name)]]);
+    }
+
+    pub fn expect_alg(&self, name: &str) -> Result<(), CheckpointError> {
+        let rows = self.section("alg")?;
+        if rows.len() != 1 {
+            return parse_error(String::from("expected one algorithm"));
+        }
+        expect_fields(&rows[0], 1)?;
+        if rows[0][0] != name {
+            return Err(CheckpointError::WrongAlg {
+                expected: String::from(name),
+                found: rows[0][0].clone(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn set_integer(&mut self, name: &str, value: i64) {
+        self.sections.insert(String::from(name),
+                             vec![vec![value.to_string()]]);
+    }
+
+    pub fn get_integer(&self, name: &str) -> Result<i64, CheckpointError> {
+        let rows = self.section(name)?;
+        if rows.len() != 1 {
+            return parse_error(format!("expected one row in '{}'", name));
+        }
+        expect_fields(&rows[0], 1)?;
+        parse_field(&rows[0][0])
+    }
+
+    pub fn set_values(&mut self, name: &str, values: &[f32]) {
+        let rows = values.iter().map(|v| vec![v.to_string()]).collect();
+        // Order matters here, so don't sort.
+        self.sections.insert(String::from(name), rows);
+    }
+
+    pub fn get_values(&self, name: &str) -> Result<Vec<f32>, CheckpointError> {
+        let mut values = Vec::new();
+        for row in self.section(name)?.iter() {
+            expect_fields(row, 1)?;
+            values.push(parse_field(&row[0])?);
+        }
+        Ok(values)
+    }
+
+    pub fn set_state_table(&mut self, name: &str,
+                           table: &HashMap<State, f32>) {
+        let rows = table.iter().map(|(state, value)| vec![
+            state.dealer.to_string(),
+            state.player.to_string(),
+            value.to_string(),
+        ]).collect();
+        self.set_section(name, rows);
+    }
+
+    pub fn get_state_table(&self, name: &str) -> Result<HashMap<State, f32>, CheckpointError> {
+        let mut table = HashMap::new();
+        for row in self.section(name)?.iter() {
+            expect_fields(row, 3)?;
+            let state = State {
+                dealer: parse_field(&row[0])?,
+                player: parse_field(&row[1])?,
+            };
+            table.insert(state, parse_field(&row[2])?);
+        }
+        Ok(table)
+    }
+
+    pub fn set_value_table(&mut self, name: &str,
+                           table: &HashMap<(State, Action), f32>) {
+        let rows = table.iter().map(|(&(state, action), value)| vec![
+            state.dealer.to_string(),
+            state.player.to_string(),
+            String::from(action_to_str(action)),
+            value.to_string(),
+        ]).collect();
+        self.set_section(name, rows);
+    }
+
+    pub fn get_value_table(&self, name: &str) -> Result<HashMap<(State, Action), f32>, CheckpointError> {
+        let mut table = HashMap::new();
+        for row in self.section(name)?.iter() {
+            expect_fields(row, 4)?;
+            let state = State {
+                dealer: parse_field(&row[0])?,
+                player: parse_field(&row[1])?,
+            };
+            let action = parse_action(&row[2])?;
+            table.insert((state, action), parse_field(&row[3])?);
+        }
+        Ok(table)
+    }
+}
+
+// Anything whose learned state can be stored in a checkpoint.
+pub trait Checkpointable {
+    fn save(&self, checkpoint: &mut Checkpoint);
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use game::{State, Action};
+    use checkpoint::*;
+
+    fn roundtrip(checkpoint: &Checkpoint) -> Checkpoint {
+        let mut output = Vec::new();
+        checkpoint.write(&mut output).unwrap();
+        Checkpoint::read(&mut &output[..]).unwrap()
+    }
+
+    #[test]
+    fn test_value_table_roundtrips() {
+        let mut checkpoint = Checkpoint::new();
+        let mut table = HashMap::new();
+        table.insert((State { dealer: 1, player: 4 }, Action::Hit), 0.1);
+        table.insert((State { dealer: 10, player: 21 }, Action::Stick), -1.0);
+        checkpoint.set_value_table("value_fn", &table);
+
+        let loaded = roundtrip(&checkpoint);
+
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(loaded.get_value_table("value_fn").unwrap(), table);
+    }
+
+    #[test]
+    fn test_values_roundtrip() {
+        let mut checkpoint = Checkpoint::new();
+        checkpoint.set_values("weights", &[3.0, 1.0 / 3.0, -2.5]);
+
+        assert_eq!(roundtrip(&checkpoint).get_values("weights").unwrap(),
+                   vec![3.0, 1.0 / 3.0, -2.5]);
+    }
+
+    #[test]
+    fn test_integer_roundtrips() {
+        let mut checkpoint = Checkpoint::new();
+        checkpoint.set_integer("episodes", 123_456_789);
+
+        assert_eq!(roundtrip(&checkpoint).get_integer("episodes").unwrap(),
+                   123_456_789);
+    }
+
+    #[test]
+    fn test_expect_alg_works() {
+        let mut checkpoint = Checkpoint::new();
+        checkpoint.set_alg("sarsa");
+
+        assert!(checkpoint.expect_alg("sarsa").is_ok());
+        match checkpoint.expect_alg("mc") {
+            Err(CheckpointError::WrongAlg { .. }) => {},
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_read_rejects_unsupported_versions() {
+        let input = "easy21-checkpoint 999\n";
+
+        match Checkpoint::read(&mut input.as_bytes()) {
+            Err(CheckpointError::UnsupportedVersion(999)) => {},
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_read_rejects_truncated_sections() {
+        let input = "easy21-checkpoint 1\nsection weights 2\n1.0\n";
+
+        match Checkpoint::read(&mut input.as_bytes()) {
+            Err(CheckpointError::Parse(_)) => {},
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
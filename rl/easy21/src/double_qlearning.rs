@@ -0,0 +1,219 @@
+use rand::Rng;
+
+use game::{Reward, Easy21};
+use environment::Environment;
+use gpi::Alg;
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+use util::{best_action, StepSizer, ValueTable};
+
+// Double Q-learning, as described in Section 6.7 of Sutton & Barto.
+// Plain Q-learning uses the same estimates both to choose the best next
+// action and to evaluate it, which biases its values upwards. Here we
+// keep two independent estimates, and on every step randomly pick one
+// to update, using it to choose the next action and the other one to
+// evaluate it. We act on the mean of both estimates.
+pub struct DoubleQLearning<E: Environment = Easy21> {
+    rng: Box<dyn Rng>,
+    first: ValueTable<E>,
+    second: ValueTable<E>,
+
+    // Each estimate has its own step sizer, so that with 1/N step sizes
+    // it's the sample average of its own targets.
+    first_step_sizer: StepSizer<E>,
+    second_step_sizer: StepSizer<E>,
+    discount: f32,
+}
+
+impl<E: Environment> DoubleQLearning<E> {
+    pub fn new<T: Rng + 'static>(rng: T) -> Self {
+        DoubleQLearning {
+            rng: Box::new(rng),
+            first: ValueTable::new(),
+            second: ValueTable::new(),
+            first_step_sizer: StepSizer::new(None),
+            second_step_sizer: StepSizer::new(None),
+            discount: 1.0,
+        }
+    }
+
+    pub fn with_constant_step_size(mut self, value: f32) -> Self {
+        self.first_step_sizer = StepSizer::new(Some(value));
+        self.second_step_sizer = StepSizer::new(Some(value));
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    pub fn get_first_expected_reward(&self, state: E::State,
+                                     action: E::Action) -> Reward {
+        self.first.get(state, action)
+    }
+
+    pub fn get_second_expected_reward(&self, state: E::State,
+                                      action: E::Action) -> Reward {
+        self.second.get(state, action)
+    }
+
+    // Views of each estimate on its own, which can be e.g. compared
+    // against optimal values to measure their bias.
+    pub fn first_estimate(&self) -> Estimate<'_, E> {
+        Estimate { values: &self.first }
+    }
+
+    pub fn second_estimate(&self) -> Estimate<'_, E> {
+        Estimate { values: &self.second }
+    }
+}
+
+impl<E: Environment> Alg<E> for DoubleQLearning<E> {
+    fn choose_best_action(&self, state: E::State) -> E::Action {
+        best_action(E::actions(), |action| {
+            self.get_expected_reward(state, action)
+        })
+    }
+
+    // We use the mean of the two estimates, rather than their sum, so
+    // that the values are comparable with other algorithms'. The best
+    // action is the same either way.
+    fn get_expected_reward(&self, state: E::State,
+                           action: E::Action) -> Reward {
+        (self.first.get(state, action) + self.second.get(state, action)) / 2.0
+    }
+
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward, next_state: E::State,
+                       _next_action: Option<E::Action>) -> Option<E::Action> {
+        let (updated, other, step_sizer) = if self.rng.gen::<bool>() {
+            (&mut self.first, &self.second, &mut self.first_step_sizer)
+        } else {
+            (&mut self.second, &self.first, &mut self.second_step_sizer)
+        };
+        let step_size = step_sizer.update(state, action);
+        let next_action = best_action(E::actions(), |action| {
+            updated.get(next_state, action)
+        });
+        let td_error = reward +
+                       self.discount * other.get(next_state, next_action) -
+                       updated.get(state, action);
+        updated.increment(state, action, step_size * td_error);
+        None
+    }
+}
+
+pub struct Estimate<'a, E: Environment + 'a = Easy21> {
+    values: &'a ValueTable<E>,
+}
+
+impl<'a, E: Environment> Alg<E> for Estimate<'a, E> {
+    fn choose_best_action(&self, state: E::State) -> E::Action {
+        best_action(E::actions(), |action| {
+            self.get_expected_reward(state, action)
+        })
+    }
+
+    fn get_expected_reward(&self, state: E::State,
+                           action: E::Action) -> Reward {
+        self.values.get(state, action)
+    }
+}
+
+impl Checkpointable for DoubleQLearning {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg("double_qlearning");
+        checkpoint.set_value_table("first_value_fn",
+                                   &self.first.to_hash_map());
+        checkpoint.set_value_table("second_value_fn",
+                                   &self.second.to_hash_map());
+        self.first_step_sizer.save_as(checkpoint,
+                                      "first_step_sizer_visits");
+        self.second_step_sizer.save_as(checkpoint,
+                                       "second_step_sizer_visits");
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg("double_qlearning")?;
+        self.first = ValueTable::from_hash_map(
+            &checkpoint.get_value_table("first_value_fn")?
+        )?;
+        self.second = ValueTable::from_hash_map(
+            &checkpoint.get_value_table("second_value_fn")?
+        )?;
+        self.first_step_sizer.load_as(checkpoint,
+                                      "first_step_sizer_visits")?;
+        self.second_step_sizer.load_as(checkpoint,
+                                       "second_step_sizer_visits")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+
+    use game::State;
+    use game::Action::*;
+    use double_qlearning::*;
+
+    #[test]
+    fn test_updates_one_estimate_per_step() {
+        let mut alg: DoubleQLearning = DoubleQLearning::new(thread_rng())
+          .with_constant_step_size(1.0);
+        let state = State { dealer: 5, player: 10 };
+        let next = State { dealer: 5, player: 30 };
+
+        alg.on_episode_step(state, Hit, 1.0, next, None);
+
+        let first = alg.get_first_expected_reward(state, Hit);
+        let second = alg.get_second_expected_reward(state, Hit);
+        assert_eq!(first + second, 1.0);
+        assert_eq!(alg.get_expected_reward(state, Hit), 0.5);
+        assert_eq!(alg.first_estimate().get_expected_reward(state, Hit),
+                   first);
+        assert_eq!(alg.second_estimate().get_expected_reward(state, Hit),
+                   second);
+    }
+
+    #[test]
+    fn test_estimates_average_their_own_targets() {
+        let mut alg: DoubleQLearning = DoubleQLearning::new(thread_rng());
+        let state = State { dealer: 5, player: 10 };
+        let next = State { dealer: 5, player: 30 };
+
+        for _ in 0..20 {
+            alg.on_episode_step(state, Hit, 1.0, next, None);
+        }
+
+        // Each estimate that was updated at all has seen only targets of
+        // 1, so with 1/N step sizes it must be exactly 1.
+        for &value in [alg.get_first_expected_reward(state, Hit),
+                       alg.get_second_expected_reward(state, Hit)].iter() {
+            assert!(value == 0.0 || value == 1.0);
+        }
+    }
+
+    #[test]
+    fn test_checkpoints_save_both_step_sizers() {
+        let mut alg: DoubleQLearning = DoubleQLearning::new(thread_rng());
+        let state = State { dealer: 5, player: 10 };
+        let next = State { dealer: 5, player: 30 };
+        for _ in 0..20 {
+            alg.on_episode_step(state, Hit, 1.0, next, None);
+        }
+        let mut checkpoint = Checkpoint::new();
+        alg.save(&mut checkpoint);
+
+        let mut loaded: DoubleQLearning = DoubleQLearning::new(thread_rng());
+        loaded.load(&checkpoint).unwrap();
+        loaded.on_episode_step(state, Hit, -1.0, next, None);
+
+        // The updated estimate moves by -2/(N + 1) from 1, rather than
+        // all the way to -1.
+        let first = loaded.get_first_expected_reward(state, Hit);
+        let second = loaded.get_second_expected_reward(state, Hit);
+        assert!(first > -1.0 && second > -1.0);
+        assert!(first < 1.0 || second < 1.0);
+    }
+}
@@ -0,0 +1,288 @@
+// This module computes the exact optimal action-value function Q* for
+// Easy21 by building the game's transition model and solving it with
+// value iteration. It's useful as a reference to compare the
+// sample-based learners against.
+
+use std::collections::HashMap;
+
+use game::{State, Action, Reward, Rules};
+use game::Action::*;
+use gpi::Alg;
+
+// We stop iterating once no value changes by more than this amount.
+const THRESHOLD: f64 = 1e-10;
+
+type ValueFn = HashMap<(State, Action), Reward>;
+
+// Every possible change to a sum from a single draw, along with its
+// probability.
+fn card_distribution(rules: &Rules) -> Vec<(i32, f64)> {
+    let num_cards = (rules.max_card + 1 - rules.min_card) as f64;
+    let red = rules.red_probability() as f64 / num_cards;
+    let black = (1.0 - rules.red_probability() as f64) / num_cards;
+    let mut dist = Vec::new();
+
+    for number in rules.min_card..rules.max_card + 1 {
+        dist.push((-number, red));
+        dist.push((number, black));
+    }
+
+    dist
+}
+
+// Index into the distributions returned by dealer_outcomes(). Index 0
+// is reserved for the dealer going bust; the rest are final sums from
+// dealer_stick_min to max_sum.
+fn outcome_index(sum: i32, rules: &Rules) -> usize {
+    if rules.is_bust(sum) {
+        0
+    } else {
+        (sum - rules.dealer_stick_min + 1) as usize
+    }
+}
+
+fn num_outcomes(rules: &Rules) -> usize {
+    (rules.max_sum - rules.dealer_stick_min + 2) as usize
+}
+
+// Returns, for every dealer sum that isn't bust, the probability
+// distribution over the dealer's final outcome. Since red cards can
+// lower the dealer's sum, the dealer can revisit sums, so we solve for
+// the distributions iteratively.
+fn dealer_outcomes(cards: &[(i32, f64)],
+                   rules: &Rules) -> HashMap<i32, Vec<f64>> {
+    let mut outcomes = HashMap::new();
+
+    for dealer in rules.min_sum..rules.max_sum + 1 {
+        let mut dist = vec![0.0; num_outcomes(rules)];
+        if dealer >= rules.dealer_stick_min {
+            dist[outcome_index(dealer, rules)] = 1.0;
+        }
+        outcomes.insert(dealer, dist);
+    }
+
+    loop {
+        let mut delta: f64 = 0.0;
+        for dealer in rules.min_sum..rules.dealer_stick_min {
+            let mut dist = vec![0.0; num_outcomes(rules)];
+            for &(card, prob) in cards.iter() {
+                let next = dealer + card;
+                if rules.is_bust(next) {
+                    dist[0] += prob;
+                } else {
+                    for (i, p) in outcomes[&next].iter().enumerate() {
+                        dist[i] += prob * p;
+                    }
+                }
+            }
+            for (new, old) in dist.iter().zip(outcomes[&dealer].iter()) {
+                delta = delta.max((new - old).abs());
+            }
+            outcomes.insert(dealer, dist);
+        }
+        if delta < THRESHOLD {
+            break;
+        }
+    }
+
+    outcomes
+}
+
+fn stick_value(player: i32, outcomes: &[f64], rules: &Rules) -> f64 {
+    let mut value = outcomes[0] * rules.win_reward as f64;
+
+    for dealer in rules.dealer_stick_min..rules.max_sum + 1 {
+        let reward = if dealer == player {
+            rules.draw_reward
+        } else if dealer < player {
+            rules.win_reward
+        } else {
+            rules.lose_reward
+        };
+        value += outcomes[outcome_index(dealer, rules)] * reward as f64;
+    }
+
+    value
+}
+
+pub struct ValueIteration {
+    value_fn: ValueFn,
+}
+
+impl ValueIteration {
+    pub fn new() -> Self {
+        Self::with_rules(&Rules::default())
+    }
+
+    pub fn with_rules(rules: &Rules) -> Self {
+        Self::with_rules_and_discount(rules, 1.0)
+    }
+
+    // Computes the optimal values when future rewards are discounted by
+    // the given factor, for comparison with learners that use it. Only
+    // hitting leads to a later step; the dealer's draws after sticking
+    // all happen within a single one.
+    pub fn with_rules_and_discount(rules: &Rules, discount: f32) -> Self {
+        let discount = discount as f64;
+        let cards = card_distribution(rules);
+        let outcomes = dealer_outcomes(&cards, rules);
+        let mut stick = HashMap::new();
+        let mut hit = HashMap::new();
+
+        // The dealer's first card is always black, so it's never bust
+        // unless the rules are very strange.
+        let dealers: Vec<i32> = (rules.min_card..rules.max_card + 1)
+          .filter(|&dealer| !rules.is_bust(dealer))
+          .collect();
+
+        for &dealer in dealers.iter() {
+            for player in rules.min_sum..rules.max_sum + 1 {
+                stick.insert((dealer, player),
+                             stick_value(player, &outcomes[&dealer], rules));
+                hit.insert((dealer, player), 0.0);
+            }
+        }
+
+        loop {
+            let mut delta: f64 = 0.0;
+            for &dealer in dealers.iter() {
+                for player in rules.min_sum..rules.max_sum + 1 {
+                    let mut value = 0.0;
+                    for &(card, prob) in cards.iter() {
+                        let next = player + card;
+                        value += prob * if rules.is_bust(next) {
+                            rules.lose_reward as f64
+                        } else {
+                            let next_hit: f64 = hit[&(dealer, next)];
+                            discount * next_hit.max(stick[&(dealer, next)])
+                        };
+                    }
+                    let old = hit.insert((dealer, player), value).unwrap();
+                    delta = delta.max((value - old).abs());
+                }
+            }
+            if delta < THRESHOLD {
+                break;
+            }
+        }
+        let mut value_fn = HashMap::new();
+
+        for (&(dealer, player), &value) in hit.iter() {
+            value_fn.insert((State { dealer, player }, Hit), value as Reward);
+        }
+        for (&(dealer, player), &value) in stick.iter() {
+            value_fn.insert((State { dealer, player }, Stick),
+                            value as Reward);
+        }
+
+        ValueIteration { value_fn }
+    }
+}
+
+impl Default for ValueIteration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Alg for ValueIteration {
+    fn choose_best_action(&self, state: State) -> Action {
+        let hit = self.get_expected_reward(state, Hit);
+        let stick = self.get_expected_reward(state, Stick);
+        if hit > stick { Hit } else { Stick }
+    }
+
+    fn get_expected_reward(&self, state: State, action: Action) -> Reward {
+        *self.value_fn.get(&(state, action)).unwrap_or(&0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dp::*;
+    use game::{MIN_CARD, MAX_CARD, MIN_SUM, MAX_SUM};
+
+    #[test]
+    fn test_card_distribution_sums_to_one() {
+        let total: f64 = card_distribution(&Rules::default())
+          .iter()
+          .map(|&(_, p)| p)
+          .sum();
+
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dealer_outcomes_sum_to_one() {
+        let rules = Rules::default();
+        let outcomes = dealer_outcomes(&card_distribution(&rules), &rules);
+
+        for dist in outcomes.values() {
+            let total: f64 = dist.iter().sum();
+            assert!((total - 1.0).abs() < 1e-6, "{:?} should sum to 1", dist);
+        }
+    }
+
+    #[test]
+    fn test_value_iteration_works() {
+        let vi = ValueIteration::new();
+
+        for dealer in MIN_CARD..MAX_CARD + 1 {
+            let state = State { dealer, player: MAX_SUM };
+            assert_eq!(vi.choose_best_action(state), Stick);
+            assert!(vi.get_expected_reward(state, Stick) > 0.5);
+
+            for player in MIN_SUM..MAX_SUM + 1 {
+                let state = State { dealer, player };
+                for &action in [Hit, Stick].iter() {
+                    let value = vi.get_expected_reward(state, action);
+                    assert!((-1.0..=1.0).contains(&value));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_value_iteration_uses_rules() {
+        let rules = Rules {
+            win_reward: 2.0,
+            lose_reward: -2.0,
+            ..Rules::default()
+        };
+        let default = ValueIteration::new();
+        let scaled = ValueIteration::with_rules(&rules);
+
+        for dealer in MIN_CARD..MAX_CARD + 1 {
+            for player in MIN_SUM..MAX_SUM + 1 {
+                let state = State { dealer, player };
+                let expected = default.get_expected_reward(state, Stick);
+                let actual = scaled.get_expected_reward(state, Stick);
+                assert!((actual - 2.0 * expected).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_value_iteration_uses_discount() {
+        let myopic = ValueIteration::with_rules_and_discount(
+            &Rules::default(), 0.0
+        );
+
+        // With no discount, hitting is only worth its chance of going
+        // bust: at 15 that's drawing a black 7-10, at 21 any black card.
+        let state = State { dealer: 5, player: 15 };
+        assert!((myopic.get_expected_reward(state, Hit) + 4.0 / 15.0).abs()
+                < 1e-6);
+        let state = State { dealer: 5, player: 21 };
+        assert!((myopic.get_expected_reward(state, Hit) + 2.0 / 3.0).abs()
+                < 1e-6);
+
+        let default = ValueIteration::new();
+        let discounted = ValueIteration::with_rules_and_discount(
+            &Rules::default(), 0.9
+        );
+        let state = State { dealer: 5, player: 15 };
+        assert_eq!(discounted.get_expected_reward(state, Stick),
+                   default.get_expected_reward(state, Stick));
+    }
+}
@@ -0,0 +1,314 @@
+// This module contains a DQN-style learner, as described by Mnih et
+// al. in "Human-level control through deep reinforcement learning"
+// (2015) and in Section 16.5 of Sutton & Barto, scaled down to Easy21.
+
+use rand::Rng;
+
+use game::{State, Action, Reward, Easy21, NUM_ACTIONS};
+use environment::Environment;
+use gpi::Alg;
+use mlp::{Network, NetworkShape, Sample, NUM_INPUTS, to_inputs,
+          action_values, best_network_action};
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+
+pub const DEFAULT_BUFFER_SIZE: usize = 10000;
+
+pub const DEFAULT_BATCH_SIZE: usize = 32;
+
+pub const DEFAULT_SYNC_INTERVAL: usize = 500;
+
+// How much experience to replay, and how often to sync the target
+// network.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ReplaySettings {
+    // The most transitions the buffer holds before it starts replacing
+    // the oldest ones.
+    pub buffer_size: usize,
+
+    // How many transitions are sampled to train on after every step.
+    pub batch_size: usize,
+
+    // How many steps pass between copies of the network to the target
+    // network.
+    pub sync_interval: usize,
+}
+
+impl Default for ReplaySettings {
+    fn default() -> Self {
+        ReplaySettings {
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            batch_size: DEFAULT_BATCH_SIZE,
+            sync_interval: DEFAULT_SYNC_INTERVAL,
+        }
+    }
+}
+
+pub type Transition = (State, Action, Reward, State);
+
+// A fixed-size buffer of the most recent transitions.
+pub struct ReplayBuffer {
+    capacity: usize,
+    transitions: Vec<Transition>,
+
+    // Where the next transition goes once the buffer is full.
+    next: usize,
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "buffer must hold at least one transition");
+        ReplayBuffer {
+            capacity,
+            transitions: Vec::with_capacity(capacity),
+            next: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty()
+    }
+
+    pub fn push(&mut self, transition: Transition) {
+        if self.transitions.len() < self.capacity {
+            self.transitions.push(transition);
+        } else {
+            self.transitions[self.next] = transition;
+        }
+        self.next = (self.next + 1) % self.capacity;
+    }
+
+    // Returns uniformly random transitions, with replacement.
+    pub fn sample<R: Rng>(&self, rng: &mut R,
+                          count: usize) -> Vec<Transition> {
+        (0..count).map(|_| {
+            self.transitions[rng.gen_range(0, self.transitions.len())]
+        }).collect()
+    }
+}
+
+// Q-learning with a neural network that, rather than training on each
+// step as it happens, stores it in a replay buffer and trains on a
+// random minibatch of past steps instead. This breaks up the
+// correlations between consecutive steps. The targets are computed by
+// a copy of the network that's only synced with it periodically, which
+// keeps them from chasing the network's own updates.
+pub struct Dqn {
+    rng: Box<dyn Rng>,
+    network: Network,
+    target_network: Network,
+    buffer: ReplayBuffer,
+    settings: ReplaySettings,
+    discount: f32,
+    step_size: f32,
+
+    // How many steps we've taken, which determines when to sync.
+    steps: usize,
+}
+
+impl Dqn {
+    pub fn new<T: Rng + 'static>(shape: NetworkShape, step_size: f32,
+                                 mut rng: T) -> Self {
+        let network = Network::new(NUM_INPUTS, NUM_ACTIONS, shape, &mut rng);
+        let settings = ReplaySettings::default();
+        Dqn {
+            rng: Box::new(rng),
+            target_network: network.clone(),
+            network,
+            buffer: ReplayBuffer::new(settings.buffer_size),
+            settings,
+            discount: 1.0,
+            step_size,
+            steps: 0,
+        }
+    }
+
+    // This clears the replay buffer.
+    pub fn with_replay(mut self, settings: ReplaySettings) -> Self {
+        assert!(settings.batch_size > 0 && settings.sync_interval > 0,
+                "batch size and sync interval must be positive");
+        self.buffer = ReplayBuffer::new(settings.buffer_size);
+        self.settings = settings;
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    fn to_sample(&self, transition: Transition) -> Sample {
+        let (state, action, reward, next_state) = transition;
+        let next_value = action_values(&self.target_network, next_state)
+          .into_iter().fold(f32::MIN, f32::max);
+        let target = reward + self.discount * next_value;
+        (to_inputs(state), Easy21::action_index(action), target)
+    }
+}
+
+impl Alg for Dqn {
+    fn choose_best_action(&self, state: State) -> Action {
+        best_network_action(&self.network, state)
+    }
+
+    fn get_expected_reward(&self, state: State, action: Action) -> Reward {
+        action_values(&self.network, state)[Easy21::action_index(action)]
+    }
+
+    fn on_episode_step(&mut self, state: State, action: Action,
+                       reward: Reward, next_state: State,
+                       _: Option<Action>) -> Option<Action> {
+        self.buffer.push((state, action, reward, next_state));
+
+        if self.buffer.len() >= self.settings.batch_size {
+            let batch = self.buffer.sample(&mut self.rng,
+                                           self.settings.batch_size);
+            let samples: Vec<Sample> = batch.into_iter()
+              .map(|transition| self.to_sample(transition)).collect();
+            self.network.train(&samples, self.step_size);
+        }
+
+        self.steps += 1;
+        // is_multiple_of() would need a much newer compiler than the rest
+        // of the crate.
+        #[allow(clippy::manual_is_multiple_of)]
+        if self.steps % self.settings.sync_interval == 0 {
+            self.target_network = self.network.clone();
+        }
+
+        None
+    }
+}
+
+// The replay buffer isn't saved, so a resumed run starts refilling it
+// from scratch.
+impl Checkpointable for Dqn {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg("dqn");
+        checkpoint.set_values("weights", &self.network.to_values());
+        checkpoint.set_values("target_weights",
+                              &self.target_network.to_values());
+        checkpoint.set_integer("steps", self.steps as i64);
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg("dqn")?;
+        self.network.load_values(&checkpoint.get_values("weights")?)?;
+        self.target_network.load_values(
+            &checkpoint.get_values("target_weights")?
+        )?;
+        self.steps = checkpoint.get_integer("steps")? as usize;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, StdRng};
+
+    use dqn::*;
+    use game::Action::*;
+
+    const A: State = State { dealer: 5, player: 10 };
+    const END: State = State { dealer: 5, player: 25 };
+
+    fn rng() -> StdRng {
+        SeedableRng::from_seed(&[1, 2, 3, 4][..])
+    }
+
+    #[test]
+    fn test_replay_buffer_replaces_oldest_transitions() {
+        let mut buffer = ReplayBuffer::new(2);
+        buffer.push((A, Hit, 1.0, END));
+        buffer.push((A, Hit, 2.0, END));
+        buffer.push((A, Hit, 3.0, END));
+
+        assert_eq!(buffer.len(), 2);
+
+        let rewards: Vec<Reward> = buffer.sample(&mut rng(), 50).iter()
+          .map(|&(_, _, reward, _)| reward).collect();
+
+        assert!(!rewards.contains(&1.0));
+        assert!(rewards.contains(&2.0));
+        assert!(rewards.contains(&3.0));
+    }
+
+    #[test]
+    fn test_target_network_syncs_periodically() {
+        let mut alg = Dqn::new(NetworkShape::default(), 0.1, rng())
+          .with_replay(ReplaySettings {
+              buffer_size: 10,
+              batch_size: 1,
+              sync_interval: 3,
+          });
+        let target = alg.target_network.to_values();
+
+        for _ in 0..2 {
+            alg.on_episode_step(A, Stick, 1.0, END, None);
+        }
+
+        assert!(alg.network.to_values() != target);
+        assert_eq!(alg.target_network.to_values(), target);
+
+        alg.on_episode_step(A, Stick, 1.0, END, None);
+
+        assert_eq!(alg.target_network.to_values(), alg.network.to_values());
+    }
+
+    #[test]
+    fn test_waits_for_a_full_batch() {
+        let mut alg = Dqn::new(NetworkShape::default(), 0.1, rng())
+          .with_replay(ReplaySettings {
+              batch_size: 2,
+              ..ReplaySettings::default()
+          });
+        let values = alg.network.to_values();
+
+        alg.on_episode_step(A, Stick, 1.0, END, None);
+        assert_eq!(alg.network.to_values(), values);
+
+        alg.on_episode_step(A, Hit, -1.0, END, None);
+        assert!(alg.network.to_values() != values);
+    }
+
+    #[test]
+    fn test_learns_terminal_rewards() {
+        let mut alg = Dqn::new(NetworkShape::default(), 0.1, rng())
+          .with_replay(ReplaySettings {
+              batch_size: 4,
+              ..ReplaySettings::default()
+          });
+
+        for _ in 0..1000 {
+            alg.on_episode_step(A, Stick, 1.0, END, None);
+            alg.on_episode_step(A, Hit, -1.0, END, None);
+        }
+
+        assert!((alg.get_expected_reward(A, Stick) - 1.0).abs() < 0.1);
+        assert!((alg.get_expected_reward(A, Hit) + 1.0).abs() < 0.1);
+        assert_eq!(alg.choose_best_action(A), Stick);
+    }
+
+    #[test]
+    fn test_checkpoints_work() {
+        let mut alg = Dqn::new(NetworkShape::default(), 0.1, rng())
+          .with_replay(ReplaySettings {
+              batch_size: 1,
+              ..ReplaySettings::default()
+          });
+        alg.on_episode_step(A, Stick, 1.0, END, None);
+        let mut checkpoint = Checkpoint::new();
+        alg.save(&mut checkpoint);
+
+        let mut loaded = Dqn::new(NetworkShape::default(), 0.1, rng());
+        loaded.load(&checkpoint).unwrap();
+
+        assert_eq!(loaded.network.to_values(), alg.network.to_values());
+        assert_eq!(loaded.target_network.to_values(),
+                   alg.target_network.to_values());
+        assert_eq!(loaded.steps, 1);
+    }
+}
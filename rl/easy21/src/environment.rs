@@ -0,0 +1,165 @@
+// This module defines the interface between an episodic task (such as
+// Easy21) and the GPI machinery, so that the same policies and tabular
+// learners can be reused on other small MDPs.
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use game::Reward;
+
+pub trait Environment {
+    type State: Copy + Eq + Hash + Debug;
+    type Action: Copy + Eq + Hash + Debug + 'static;
+
+    // Every action the agent can take. Ties between equally valued
+    // actions are broken in favor of later ones.
+    fn actions() -> &'static [Self::Action];
+
+    // The number of states that can be stored in dense, index-based
+    // tables like util::ValueTable.
+    fn num_states() -> usize;
+
+    // Maps a state to a unique index less than num_states(), or None if
+    // the state is terminal (or otherwise never needs a value of its own).
+    fn state_index(state: Self::State) -> Option<usize>;
+
+    fn action_index(action: Self::Action) -> usize {
+        Self::actions().iter().position(|&a| a == action).unwrap()
+    }
+
+    // The inverse of state_index(), for every index less than
+    // num_states().
+    fn state_at(index: usize) -> Self::State;
+
+    // Begin a new episode, returning its initial state.
+    fn reset(&mut self) -> Self::State;
+
+    // Begin a new episode at the given state rather than a randomly
+    // dealt one, e.g. for exploring starts. Environments with hidden
+    // state (like the dealer's face-down card) should deal it here.
+    fn reset_to(&mut self, state: Self::State) {
+        let _ = state;
+    }
+
+    // Take the given action at the given state, returning the successor
+    // state and the reward for the transition.
+    fn step(&mut self, state: Self::State,
+            action: Self::Action) -> (Self::State, Reward);
+
+    fn is_terminal(&self, state: Self::State) -> bool;
+}
+
+#[cfg(test)]
+pub mod tests {
+    use rand::{SeedableRng, StdRng};
+
+    use game::Reward;
+    use environment::Environment;
+    use gpi::{Gpi, Alg, EpsilonGreedyPolicy};
+    use montecarlo::MonteCarlo;
+    use sarsa::SarsaLambda;
+    use qlearning::QLearning;
+    use expected_sarsa::ExpectedSarsa;
+    use double_qlearning::DoubleQLearning;
+
+    // A tiny deterministic MDP: the agent starts in the middle of a
+    // corridor and is rewarded for reaching its right end, and punished
+    // for reaching its left end.
+    pub struct Corridor;
+
+    pub const CORRIDOR_LENGTH: i32 = 5;
+
+    #[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
+    pub enum Move {
+        Left,
+        Right,
+    }
+
+    impl Environment for Corridor {
+        type State = i32;
+        type Action = Move;
+
+        fn actions() -> &'static [Move] {
+            &[Move::Left, Move::Right]
+        }
+
+        fn num_states() -> usize {
+            CORRIDOR_LENGTH as usize
+        }
+
+        fn state_index(state: i32) -> Option<usize> {
+            if (0..CORRIDOR_LENGTH).contains(&state) {
+                Some(state as usize)
+            } else {
+                None
+            }
+        }
+
+        fn state_at(index: usize) -> i32 {
+            index as i32
+        }
+
+        fn reset(&mut self) -> i32 {
+            CORRIDOR_LENGTH / 2
+        }
+
+        fn step(&mut self, state: i32, action: Move) -> (i32, Reward) {
+            let next = match action {
+                Move::Left => state - 1,
+                Move::Right => state + 1,
+            };
+            let reward = if next < 0 {
+                -1.0
+            } else if next >= CORRIDOR_LENGTH {
+                1.0
+            } else {
+                0.0
+            };
+            (next, reward)
+        }
+
+        fn is_terminal(&self, state: i32) -> bool {
+            !(0..CORRIDOR_LENGTH).contains(&state)
+        }
+    }
+
+    fn learns_corridor<T: Alg<Corridor>>(alg: T) {
+        let seed: &[_] = &[1, 2, 3, 4];
+        let rng: StdRng = SeedableRng::from_seed(seed);
+        let policy = EpsilonGreedyPolicy::new(rng, alg)
+          .with_constant_epsilon(0.1);
+        let mut gpi = Gpi::new(Corridor, policy);
+
+        gpi.play_episodes(1000);
+
+        let start = Corridor.reset();
+        assert_eq!(gpi.policy.alg.choose_best_action(start), Move::Right);
+    }
+
+    #[test]
+    fn test_monte_carlo_works_with_other_environments() {
+        learns_corridor(MonteCarlo::<Corridor>::new());
+    }
+
+    #[test]
+    fn test_sarsa_works_with_other_environments() {
+        learns_corridor(SarsaLambda::<Corridor>::new(0.5));
+    }
+
+    #[test]
+    fn test_q_learning_works_with_other_environments() {
+        learns_corridor(QLearning::<Corridor>::new(0.5));
+    }
+
+    #[test]
+    fn test_expected_sarsa_works_with_other_environments() {
+        learns_corridor(ExpectedSarsa::<Corridor>::new(0.5));
+    }
+
+    #[test]
+    fn test_double_q_learning_works_with_other_environments() {
+        let seed: &[_] = &[5, 6, 7, 8];
+        let rng: StdRng = SeedableRng::from_seed(seed);
+        learns_corridor(DoubleQLearning::<Corridor>::new(rng));
+    }
+}
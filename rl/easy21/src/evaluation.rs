@@ -0,0 +1,122 @@
+// This module compares an algorithm's value function against a
+// reference one (usually the optimal values computed by the `dp`
+// module), which lets us plot learning curves.
+
+use std::io::{self, Write};
+
+use game::{State, Action, MIN_SUM, MAX_SUM, MIN_CARD, MAX_CARD};
+use game::Action::*;
+use gpi::Alg;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ErrorStats {
+    // The mean of the algorithm's values minus the reference's, so a
+    // positive value means that the algorithm overestimates.
+    pub mean_error: f32,
+    pub mean_squared_error: f32,
+    pub max_abs_error: f32,
+}
+
+// Compare the expected rewards of the two algorithms over every
+// (dealer, player, action) triple.
+pub fn compare<T: Alg, U: Alg>(alg: &T, reference: &U) -> ErrorStats {
+    let mut sum_error = 0.0;
+    let mut sum_squared_error = 0.0;
+    let mut max_abs_error: f32 = 0.0;
+    let mut count = 0;
+
+    for dealer in MIN_CARD..MAX_CARD + 1 {
+        for player in MIN_SUM..MAX_SUM + 1 {
+            let state = State { dealer, player };
+            for &action in [Hit, Stick].iter() {
+                let error = error_at(alg, reference, state, action);
+                sum_error += error;
+                sum_squared_error += error * error;
+                max_abs_error = max_abs_error.max(error.abs());
+                count += 1;
+            }
+        }
+    }
+
+    ErrorStats {
+        mean_error: sum_error / count as f32,
+        mean_squared_error: sum_squared_error / count as f32,
+        max_abs_error,
+    }
+}
+
+fn error_at<T: Alg, U: Alg>(alg: &T, reference: &U, state: State,
+                            action: Action) -> f32 {
+    alg.get_expected_reward(state, action) -
+    reference.get_expected_reward(state, action)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CurvePoint {
+    pub episodes: i32,
+    pub stats: ErrorStats,
+}
+
+// A learning curve records how far an algorithm is from a reference
+// as the number of episodes played increases.
+pub struct LearningCurve<'a, T: Alg + 'a> {
+    reference: &'a T,
+    pub points: Vec<CurvePoint>,
+}
+
+impl<'a, T: Alg> LearningCurve<'a, T> {
+    pub fn new(reference: &'a T) -> Self {
+        LearningCurve {
+            reference,
+            points: Vec::new(),
+        }
+    }
+
+    pub fn record<U: Alg>(&mut self, episodes: i32, alg: &U) {
+        let stats = compare(alg, self.reference);
+        self.points.push(CurvePoint { episodes, stats });
+    }
+
+    pub fn write_csv<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        writeln!(output, "episodes,mean_squared_error,max_abs_error")?;
+        for point in self.points.iter() {
+            writeln!(output, "{},{},{}", point.episodes,
+                     point.stats.mean_squared_error,
+                     point.stats.max_abs_error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gpi::tests::DumbAlg;
+    use game::Action;
+    use evaluation::*;
+
+    #[test]
+    fn test_compare_works() {
+        let alg = DumbAlg { action: Action::Hit, reward: 0.5 };
+        let reference = DumbAlg { action: Action::Hit, reward: 1.0 };
+        let stats = compare(&alg, &reference);
+
+        assert_eq!(stats.mean_error, -0.5);
+        assert_eq!(stats.mean_squared_error, 0.25);
+        assert_eq!(stats.max_abs_error, 0.5);
+    }
+
+    #[test]
+    fn test_learning_curve_works() {
+        let alg = DumbAlg { action: Action::Hit, reward: 0.0 };
+        let reference = DumbAlg { action: Action::Hit, reward: 1.0 };
+        let mut curve = LearningCurve::new(&reference);
+        let mut output = Vec::new();
+
+        curve.record(10, &alg);
+        curve.write_csv(&mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(),
+                   "episodes,mean_squared_error,max_abs_error\n10,1,1\n");
+    }
+}
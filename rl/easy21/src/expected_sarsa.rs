@@ -0,0 +1,136 @@
+use game::{Reward, Easy21};
+use environment::Environment;
+use gpi::Alg;
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+use util::{best_action, StepSizer, ValueTable, Traces};
+use traces::TraceType;
+
+// Like Sarsa(lambda), but rather than using the value of the next
+// action the behavior policy happened to choose in its TD target, it
+// uses the expected value of the next state under the behavior policy.
+pub struct ExpectedSarsa<E: Environment = Easy21> {
+    value_fn: ValueTable<E>,
+    step_sizer: StepSizer<E>,
+    traces: Traces<E>,
+    trace_type: TraceType,
+    lambda: f32,
+    discount: f32,
+    next_probabilities: Vec<f32>,
+}
+
+impl<E: Environment> ExpectedSarsa<E> {
+    pub fn new(lambda: f32) -> Self {
+        ExpectedSarsa {
+            value_fn: ValueTable::new(),
+            step_sizer: StepSizer::new(None),
+            traces: Traces::new(),
+            trace_type: TraceType::default(),
+            lambda,
+            discount: 1.0,
+            next_probabilities: Vec::new(),
+        }
+    }
+
+    pub fn with_constant_step_size(mut self, value: f32) -> Self {
+        self.step_sizer = StepSizer::new(Some(value));
+        self
+    }
+
+    pub fn with_trace_type(mut self, trace_type: TraceType) -> Self {
+        self.trace_type = trace_type;
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    fn expected_value(&self, state: E::State) -> Reward {
+        E::actions().iter().zip(self.next_probabilities.iter())
+          .map(|(&action, &probability)| {
+              probability * self.get_expected_reward(state, action)
+          })
+          .sum()
+    }
+}
+
+impl<E: Environment> Alg<E> for ExpectedSarsa<E> {
+    fn choose_best_action(&self, state: E::State) -> E::Action {
+        best_action(E::actions(), |action| {
+            self.get_expected_reward(state, action)
+        })
+    }
+
+    fn get_expected_reward(&self, state: E::State,
+                           action: E::Action) -> Reward {
+        self.value_fn.get(state, action)
+    }
+
+    fn on_episode_begin(&mut self) {
+        self.traces.clear();
+    }
+
+    fn needs_action_probabilities(&self) -> bool {
+        true
+    }
+
+    fn on_action_probabilities(&mut self, _state: E::State,
+                               probabilities: &[f32]) {
+        self.next_probabilities.clear();
+        self.next_probabilities.extend_from_slice(probabilities);
+    }
+
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward, next_state: E::State,
+                       _next_action: Option<E::Action>) -> Option<E::Action> {
+        let step_size = self.step_sizer.update(state, action);
+        let td_error = reward +
+                       self.discount * self.expected_value(next_state) -
+                       self.get_expected_reward(state, action);
+        self.traces.visit(state, action, self.trace_type, step_size);
+        self.value_fn.add_traces(&self.traces, step_size * td_error);
+        self.traces.decay(self.discount * self.lambda);
+        None
+    }
+}
+
+impl Checkpointable for ExpectedSarsa {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg("expected_sarsa");
+        checkpoint.set_value_table("value_fn", &self.value_fn.to_hash_map());
+        self.step_sizer.save(checkpoint);
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg("expected_sarsa")?;
+        self.value_fn = ValueTable::from_hash_map(
+            &checkpoint.get_value_table("value_fn")?
+        )?;
+        self.step_sizer.load(checkpoint)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game::State;
+    use game::Action::*;
+    use expected_sarsa::*;
+
+    #[test]
+    fn test_td_target_uses_action_probabilities() {
+        let mut alg: ExpectedSarsa = ExpectedSarsa::new(0.0)
+          .with_constant_step_size(1.0);
+        let state = State { dealer: 5, player: 10 };
+        let next = State { dealer: 5, player: 15 };
+
+        alg.value_fn.set(next, Hit, 1.0);
+        alg.value_fn.set(next, Stick, -1.0);
+        alg.on_episode_begin();
+        alg.on_action_probabilities(next, &[0.75, 0.25]);
+        alg.on_episode_step(state, Hit, 0.0, next, None);
+
+        assert_eq!(alg.get_expected_reward(state, Hit), 0.5);
+    }
+}
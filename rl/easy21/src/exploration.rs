@@ -0,0 +1,350 @@
+// This module contains alternatives to epsilon-greedy exploration, as
+// described in Sections 2.7 and 13.2 of Sutton & Barto.
+
+use std::marker::PhantomData;
+
+use rand::Rng;
+
+use game::{Reward, Easy21};
+use environment::Environment;
+use gpi::{Alg, Policy, AlgPolicy};
+use util::{best_action, sample_action, StateTable, ValueTable};
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+
+pub const DEFAULT_TEMPERATURE: f32 = 1.0;
+
+// The temperature never decays below this, since the action
+// probabilities become numerically unstable near zero.
+pub const MIN_TEMPERATURE: f32 = 0.01;
+
+pub const DEFAULT_UCB_C: f32 = 1.0;
+
+// Chooses each action with a probability proportional to the
+// exponential of its value divided by a temperature, so that higher
+// temperatures explore more. The temperature is multiplied by a decay
+// factor after every episode.
+pub struct BoltzmannPolicy<T: Rng, U: Alg<E>, E: Environment = Easy21> {
+    rng: T,
+    pub alg: U,
+    temperature: f32,
+    temperature_decay: f32,
+    episodes: i32,
+
+    // Whether the last action we chose was the best one according to
+    // the algorithm.
+    chose_best_action: bool,
+
+    // The probability with which we chose the last action, if the
+    // algorithm needs it.
+    chosen_action_probability: f32,
+
+    env: PhantomData<E>,
+}
+
+impl<T: Rng, U: Alg<E>, E: Environment> BoltzmannPolicy<T, U, E> {
+    pub fn new(rng: T, alg: U) -> Self {
+        BoltzmannPolicy {
+            rng,
+            alg,
+            temperature: DEFAULT_TEMPERATURE,
+            temperature_decay: 1.0,
+            episodes: 0,
+            chose_best_action: true,
+            chosen_action_probability: 1.0,
+            env: PhantomData,
+        }
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    pub fn with_temperature_decay(mut self, decay: f32) -> Self {
+        self.temperature_decay = decay;
+        self
+    }
+
+    // The temperature for the current episode.
+    pub fn temperature(&self) -> f32 {
+        let decay = self.temperature_decay.powi(self.episodes);
+        (self.temperature * decay).max(MIN_TEMPERATURE)
+    }
+}
+
+impl<T: Rng, U: Alg<E>, E: Environment> Policy<E> for BoltzmannPolicy<T, U, E> {
+    fn choose_action(&mut self, state: E::State) -> E::Action {
+        let probabilities = self.action_probabilities(state);
+        let index = sample_action(&probabilities, &mut self.rng);
+        let action = E::actions()[index];
+        self.chose_best_action = action == self.alg.choose_best_action(state);
+        self.chosen_action_probability = probabilities[index];
+        action
+    }
+
+    fn on_episode_begin(&mut self) {
+        self.alg.on_episode_begin();
+    }
+
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward,
+                       next_state: E::State) -> Option<E::Action> {
+        self.step_alg(state, action, reward, next_state,
+                      self.chose_best_action, self.chosen_action_probability)
+    }
+
+    fn on_episode_end(&mut self) {
+        self.alg.on_episode_end();
+        self.episodes += 1;
+    }
+
+    fn on_exploring_start(&mut self, state: E::State, action: E::Action) {
+        self.chose_best_action = action == self.alg.choose_best_action(state);
+        self.chosen_action_probability = 1.0 / E::actions().len() as f32;
+    }
+}
+
+impl<T: Rng, U: Alg<E>, E: Environment> AlgPolicy<E> for BoltzmannPolicy<T, U, E> {
+    type Alg = U;
+
+    fn alg(&self) -> &U {
+        &self.alg
+    }
+
+    fn alg_mut(&mut self) -> &mut U {
+        &mut self.alg
+    }
+
+    fn action_probabilities(&self, state: E::State) -> Vec<f32> {
+        let temperature = self.temperature();
+        let values: Vec<f32> = E::actions().iter().map(|&action| {
+            self.alg.get_expected_reward(state, action) / temperature
+        }).collect();
+
+        // Subtracting the largest value keeps the exponentials from
+        // overflowing without changing the resulting probabilities.
+        let max = values.iter().cloned().fold(f32::MIN, f32::max);
+        let weights: Vec<f32> = values.iter().map(|v| (v - max).exp())
+          .collect();
+        let total: f32 = weights.iter().sum();
+
+        weights.iter().map(|w| w / total).collect()
+    }
+}
+
+impl<T: Rng, U: Alg + Checkpointable> Checkpointable for BoltzmannPolicy<T, U> {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_integer("temperature_episodes", self.episodes as i64);
+        self.alg.save(checkpoint);
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        self.episodes = checkpoint.get_integer("temperature_episodes")? as i32;
+        self.alg.load(checkpoint)
+    }
+}
+
+// Upper-confidence-bound action selection, which deterministically
+// chooses the action maximizing its value plus a bonus that shrinks the
+// more often it's been taken at the state, as in equation 2.10 of
+// Sutton & Barto. Actions that have never been taken at a state are
+// always tried first.
+pub struct UcbPolicy<U: Alg<E>, E: Environment = Easy21> {
+    pub alg: U,
+    c: f32,
+    times_visited: StateTable<E>,
+    times_taken: ValueTable<E>,
+
+    // Whether the last action we chose was the best one according to
+    // the algorithm.
+    chose_best_action: bool,
+}
+
+impl<U: Alg<E>, E: Environment> UcbPolicy<U, E> {
+    pub fn new(alg: U) -> Self {
+        UcbPolicy {
+            alg,
+            c: DEFAULT_UCB_C,
+            times_visited: StateTable::new(),
+            times_taken: ValueTable::new(),
+            chose_best_action: true,
+        }
+    }
+
+    // Sets the degree of exploration, i.e. how much the bonus counts
+    // relative to the action's value.
+    pub fn with_c(mut self, c: f32) -> Self {
+        self.c = c;
+        self
+    }
+
+    fn upper_bound(&self, state: E::State, action: E::Action) -> Reward {
+        let taken = self.times_taken.get(state, action);
+        if taken == 0.0 {
+            return f32::INFINITY;
+        }
+        let visited = self.times_visited.get(state);
+        let bonus = self.c * (visited.ln() / taken).sqrt();
+        self.alg.get_expected_reward(state, action) + bonus
+    }
+
+    fn ucb_action(&self, state: E::State) -> E::Action {
+        best_action(E::actions(), |action| self.upper_bound(state, action))
+    }
+}
+
+impl<U: Alg<E>, E: Environment> Policy<E> for UcbPolicy<U, E> {
+    fn choose_action(&mut self, state: E::State) -> E::Action {
+        let action = self.ucb_action(state);
+        self.chose_best_action = action == self.alg.choose_best_action(state);
+        action
+    }
+
+    fn on_episode_begin(&mut self) {
+        self.alg.on_episode_begin();
+    }
+
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward,
+                       next_state: E::State) -> Option<E::Action> {
+        self.times_visited.increment(state, 1.0);
+        self.times_taken.increment(state, action, 1.0);
+
+        // Our choices are deterministic, so the behavior policy always
+        // takes its action with a probability of 1.
+        self.step_alg(state, action, reward, next_state,
+                      self.chose_best_action, 1.0)
+    }
+
+    fn on_episode_end(&mut self) {
+        self.alg.on_episode_end();
+    }
+
+    fn on_exploring_start(&mut self, state: E::State, action: E::Action) {
+        self.chose_best_action = action == self.alg.choose_best_action(state);
+    }
+}
+
+impl<U: Alg<E>, E: Environment> AlgPolicy<E> for UcbPolicy<U, E> {
+    type Alg = U;
+
+    fn alg(&self) -> &U {
+        &self.alg
+    }
+
+    fn alg_mut(&mut self) -> &mut U {
+        &mut self.alg
+    }
+
+    fn action_probabilities(&self, state: E::State) -> Vec<f32> {
+        let chosen = self.ucb_action(state);
+        E::actions().iter().map(|&action| {
+            if action == chosen { 1.0 } else { 0.0 }
+        }).collect()
+    }
+}
+
+impl<U: Alg + Checkpointable> Checkpointable for UcbPolicy<U> {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_state_table("times_visited",
+                                   &self.times_visited.to_hash_map());
+        checkpoint.set_value_table("times_taken",
+                                   &self.times_taken.to_hash_map());
+        self.alg.save(checkpoint);
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        self.times_visited = StateTable::from_hash_map(
+            &checkpoint.get_state_table("times_visited")?
+        )?;
+        self.times_taken = ValueTable::from_hash_map(
+            &checkpoint.get_value_table("times_taken")?
+        )?;
+        self.alg.load(checkpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+
+    use game::{State, Action, Reward};
+    use game::Action::*;
+    use gpi::{Alg, Policy, AlgPolicy};
+    use exploration::*;
+
+    const A: State = State { dealer: 5, player: 10 };
+    const END: State = State { dealer: 5, player: 25 };
+
+    // An algorithm that values sticking at ln(3) and hitting at 0.
+    struct StickyAlg;
+
+    impl Alg for StickyAlg {
+        fn choose_best_action(&self, _: State) -> Action {
+            Stick
+        }
+
+        fn get_expected_reward(&self, _: State, action: Action) -> Reward {
+            if action == Stick { 3.0f32.ln() } else { 0.0 }
+        }
+    }
+
+    fn assert_close(a: &[f32], b: &[f32]) {
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() < 1e-6, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_boltzmann_probabilities_work() {
+        let policy = BoltzmannPolicy::new(thread_rng(), StickyAlg);
+        assert_close(&policy.action_probabilities(A), &[0.25, 0.75]);
+
+        // Lowering the temperature makes the best action more likely.
+        let policy = policy.with_temperature(0.5);
+        assert_close(&policy.action_probabilities(A), &[0.1, 0.9]);
+    }
+
+    #[test]
+    fn test_boltzmann_temperature_decays() {
+        let mut policy = BoltzmannPolicy::new(thread_rng(), StickyAlg)
+          .with_temperature(2.0)
+          .with_temperature_decay(0.5);
+
+        policy.on_episode_end();
+        policy.on_episode_end();
+        assert_eq!(policy.temperature(), 0.5);
+
+        for _ in 0..100 {
+            policy.on_episode_end();
+        }
+        assert_eq!(policy.temperature(), MIN_TEMPERATURE);
+    }
+
+    #[test]
+    fn test_ucb_tries_every_action_first() {
+        let mut policy = UcbPolicy::new(StickyAlg);
+
+        assert_eq!(policy.choose_action(A), Stick);
+        policy.on_episode_step(A, Stick, 0.0, END);
+        assert_eq!(policy.choose_action(A), Hit);
+        assert_eq!(policy.action_probabilities(A), vec![1.0, 0.0]);
+        policy.on_episode_step(A, Hit, 0.0, END);
+
+        // Both actions have been tried once, so their bonuses are equal.
+        assert_eq!(policy.choose_action(A), Stick);
+    }
+
+    #[test]
+    fn test_ucb_bonus_favors_less_taken_actions() {
+        let mut policy = UcbPolicy::new(StickyAlg).with_c(2.0);
+
+        policy.on_episode_step(A, Hit, 0.0, END);
+        for _ in 0..20 {
+            policy.on_episode_step(A, Stick, 0.0, END);
+        }
+
+        // ln(3) + 2 * sqrt(ln(21) / 20) < 0 + 2 * sqrt(ln(21) / 1)
+        assert_eq!(policy.choose_action(A), Hit);
+    }
+}
@@ -0,0 +1,399 @@
+// This module contains the ways in which the linear function
+// approximator can turn a state/action pair into a vector of features.
+// Only a handful of features are active for any given pair, so the
+// vectors are sparse, which keeps lookups from depending on the total
+// number of features.
+
+use std::ops::Range;
+
+use game::{State, Action, Easy21, NUM_ACTIONS, MIN_CARD, MAX_CARD, MIN_SUM,
+           MAX_SUM};
+use game::Action::*;
+use environment::Environment;
+
+// The active features of a state/action pair, as (index, value) pairs
+// sorted by index. Every other feature is 0.
+pub type FeatureVector = Vec<(usize, f32)>;
+
+pub trait FeatureExtractor {
+    // The number of features, i.e. one more than the largest index any
+    // feature vector can contain.
+    fn num_features(&self) -> usize;
+
+    fn to_feature_vector(&self, state: State, action: Action) -> FeatureVector;
+}
+
+impl FeatureExtractor for Box<dyn FeatureExtractor> {
+    fn num_features(&self) -> usize {
+        (**self).num_features()
+    }
+
+    fn to_feature_vector(&self, state: State, action: Action) -> FeatureVector {
+        (**self).to_feature_vector(state, action)
+    }
+}
+
+// Which of the extractors below to use, so they can be chosen by
+// e.g. shortcuts::Config.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Features {
+    #[default]
+    Cuboids,
+    Tiles(TileCoding),
+    OneHot,
+    Polynomial(PolynomialFeatures),
+}
+
+pub const FEATURE_NAMES: &[&str] = &["cuboids", "tiles", "one-hot",
+                                     "polynomial"];
+
+impl Features {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Features::Cuboids => "cuboids",
+            Features::Tiles(_) => "tiles",
+            Features::OneHot => "one-hot",
+            Features::Polynomial(_) => "polynomial",
+        }
+    }
+
+    pub fn extractor(&self) -> Box<dyn FeatureExtractor> {
+        match *self {
+            Features::Cuboids => Box::new(CuboidFeatures),
+            Features::Tiles(tiles) => Box::new(tiles),
+            Features::OneHot => Box::new(OneHotFeatures),
+            Features::Polynomial(polynomial) => Box::new(polynomial),
+        }
+    }
+
+    // Tiles and polynomials are given their default settings.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cuboids" => Some(Features::Cuboids),
+            "tiles" => Some(Features::Tiles(TileCoding::default())),
+            "one-hot" => Some(Features::OneHot),
+            "polynomial" => Some(Features::Polynomial(
+                PolynomialFeatures::default()
+            )),
+            _ => None,
+        }
+    }
+}
+
+type Ranges = [Range<i32>];
+
+const NUM_DEALER_RANGES: usize = 3;
+
+const DEALER_RANGES: &[Range<i32>; NUM_DEALER_RANGES] = &[
+    1..5, 4..8, 7..11
+];
+
+const NUM_PLAYER_RANGES: usize = 6;
+
+const PLAYER_RANGES: &[Range<i32>; NUM_PLAYER_RANGES] = &[
+    1..7, 4..10, 7..13, 10..16, 13..19, 16..22
+];
+
+const NUM_CUBOID_FEATURES: usize = NUM_DEALER_RANGES * NUM_PLAYER_RANGES *
+                                   NUM_ACTIONS;
+
+// Returns the indexes of the ranges that contain the value.
+fn get_ranges_inside(value: i32, ranges: &Ranges) -> Vec<usize> {
+    (0..ranges.len()).filter(|&i| ranges[i].contains(&value)).collect()
+}
+
+// The coarse coding suggested by the Easy21 assignment, which has a
+// feature for every combination of overlapping dealer and player sum
+// ranges ("cuboids") and action.
+pub struct CuboidFeatures;
+
+impl FeatureExtractor for CuboidFeatures {
+    fn num_features(&self) -> usize {
+        NUM_CUBOID_FEATURES
+    }
+
+    fn to_feature_vector(&self, state: State, action: Action) -> FeatureVector {
+        let mut vector = Vec::new();
+        let action_index = match action {
+            Hit => 0,
+            Stick => 1,
+        };
+
+        for dealer in get_ranges_inside(state.dealer, DEALER_RANGES) {
+            for player in get_ranges_inside(state.player, PLAYER_RANGES) {
+                let cuboid = dealer * NUM_PLAYER_RANGES + player;
+                vector.push((cuboid * NUM_ACTIONS + action_index, 1.0));
+            }
+        }
+
+        vector
+    }
+}
+
+pub const DEFAULT_NUM_TILINGS: usize = 4;
+
+pub const DEFAULT_DEALER_TILE_WIDTH: i32 = 3;
+
+pub const DEFAULT_PLAYER_TILE_WIDTH: i32 = 6;
+
+// Tile coding, as described in Section 9.5.4 of Sutton & Barto. Each of
+// several tilings partitions the dealer and player sums into a grid of
+// tiles, and each tiling is offset from the previous one by a fraction
+// of a tile, so that a state activates one tile per tiling.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TileCoding {
+    num_tilings: usize,
+    dealer_width: i32,
+    player_width: i32,
+
+    // How far each tiling is offset from the previous one along each
+    // dimension, in units of 1/num_tilings of a tile. Section 9.5.4
+    // recommends the first odd integers, which avoids tilings that are
+    // offset along the diagonal.
+    displacement: (i32, i32),
+}
+
+impl Default for TileCoding {
+    fn default() -> Self {
+        TileCoding::new(DEFAULT_NUM_TILINGS, DEFAULT_DEALER_TILE_WIDTH,
+                        DEFAULT_PLAYER_TILE_WIDTH)
+    }
+}
+
+impl TileCoding {
+    pub fn new(num_tilings: usize, dealer_width: i32,
+               player_width: i32) -> Self {
+        assert!(num_tilings > 0, "there must be at least one tiling");
+        assert!(dealer_width > 0 && player_width > 0,
+                "tiles must be at least one sum wide");
+        TileCoding {
+            num_tilings,
+            dealer_width,
+            player_width,
+            displacement: (1, 3),
+        }
+    }
+
+    pub fn with_displacement(mut self, dealer: i32, player: i32) -> Self {
+        self.displacement = (dealer, player);
+        self
+    }
+
+    // The number of tiles along each dimension of a tiling. There's an
+    // extra one to cover the sums that offsetting pushes past the end.
+    fn tiles_per_tiling(&self) -> (usize, usize) {
+        let dealer = (MAX_CARD - MIN_CARD) / self.dealer_width + 2;
+        let player = (MAX_SUM - MIN_SUM) / self.player_width + 2;
+        (dealer as usize, player as usize)
+    }
+
+    // Returns which tile of the given tiling the value falls into, along
+    // a dimension with the given minimum, tile width and displacement.
+    fn tile(&self, tiling: usize, value: i32, min: i32, width: i32,
+            displacement: i32) -> usize {
+        let n = self.num_tilings as i32;
+        let offset = (tiling as i32 * displacement).rem_euclid(n);
+        (((value - min) * n + offset * width) / (width * n)) as usize
+    }
+}
+
+impl FeatureExtractor for TileCoding {
+    fn num_features(&self) -> usize {
+        let (dealer_tiles, player_tiles) = self.tiles_per_tiling();
+        self.num_tilings * dealer_tiles * player_tiles * NUM_ACTIONS
+    }
+
+    fn to_feature_vector(&self, state: State, action: Action) -> FeatureVector {
+        let mut vector = Vec::with_capacity(self.num_tilings);
+
+        // Terminal states have no features, and therefore a value of 0.
+        if Easy21::state_index(state).is_none() {
+            return vector;
+        }
+
+        let (dealer_tiles, player_tiles) = self.tiles_per_tiling();
+        for tiling in 0..self.num_tilings {
+            let dealer = self.tile(tiling, state.dealer, MIN_CARD,
+                                   self.dealer_width, self.displacement.0);
+            let player = self.tile(tiling, state.player, MIN_SUM,
+                                   self.player_width, self.displacement.1);
+            let tile = (tiling * dealer_tiles + dealer) * player_tiles +
+                       player;
+            vector.push((tile * NUM_ACTIONS + Easy21::action_index(action),
+                         1.0));
+        }
+
+        vector
+    }
+}
+
+// A feature for every state/action pair, which makes linear function
+// approximation equivalent to a tabular method.
+pub struct OneHotFeatures;
+
+impl FeatureExtractor for OneHotFeatures {
+    fn num_features(&self) -> usize {
+        Easy21::num_states() * NUM_ACTIONS
+    }
+
+    fn to_feature_vector(&self, state: State, action: Action) -> FeatureVector {
+        match Easy21::state_index(state) {
+            Some(index) => {
+                vec![(index * NUM_ACTIONS + Easy21::action_index(action), 1.0)]
+            },
+            None => Vec::new(),
+        }
+    }
+}
+
+pub const DEFAULT_DEGREE: usize = 2;
+
+// A feature for every product of powers of the dealer and player sums
+// whose exponents add up to at most the degree, as described in Section
+// 9.5.1 of Sutton & Barto, for every action. The sums are scaled to lie
+// between 0 and 1 first, so that higher powers don't dwarf lower ones.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PolynomialFeatures {
+    degree: usize,
+}
+
+impl Default for PolynomialFeatures {
+    fn default() -> Self {
+        PolynomialFeatures::new(DEFAULT_DEGREE)
+    }
+}
+
+impl PolynomialFeatures {
+    pub fn new(degree: usize) -> Self {
+        PolynomialFeatures { degree }
+    }
+
+    fn num_terms(&self) -> usize {
+        (self.degree + 1) * (self.degree + 2) / 2
+    }
+}
+
+impl FeatureExtractor for PolynomialFeatures {
+    fn num_features(&self) -> usize {
+        self.num_terms() * NUM_ACTIONS
+    }
+
+    fn to_feature_vector(&self, state: State, action: Action) -> FeatureVector {
+        let mut vector = Vec::with_capacity(self.num_terms());
+
+        // Terminal states have no features, and therefore a value of 0.
+        if Easy21::state_index(state).is_none() {
+            return vector;
+        }
+
+        let dealer = (state.dealer - MIN_CARD) as f32 /
+                     (MAX_CARD - MIN_CARD) as f32;
+        let player = (state.player - MIN_SUM) as f32 /
+                     (MAX_SUM - MIN_SUM) as f32;
+        let action_index = Easy21::action_index(action);
+        let mut term = 0;
+        for total in 0..self.degree + 1 {
+            for player_power in 0..total + 1 {
+                let dealer_power = total - player_power;
+                let value = dealer.powi(dealer_power as i32) *
+                            player.powi(player_power as i32);
+                vector.push((term * NUM_ACTIONS + action_index, value));
+                term += 1;
+            }
+        }
+
+        vector
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use features::*;
+    use game::State;
+
+    fn active(vector: &FeatureVector) -> Vec<usize> {
+        vector.iter().map(|&(i, _)| i).collect()
+    }
+
+    #[test]
+    fn test_to_feature_vector_works_with_one_cuboid() {
+        let fv = CuboidFeatures.to_feature_vector(State { dealer: 1, player: 1 },
+                                                  Hit);
+
+        assert_eq!(fv, vec![(0, 1.0)]);
+    }
+
+    #[test]
+    fn test_to_feature_vector_works_with_two_cuboids() {
+        let fv = CuboidFeatures.to_feature_vector(State { dealer: 1, player: 5 },
+                                                  Hit);
+
+        assert_eq!(fv, vec![(0, 1.0), (2, 1.0)]);
+    }
+
+    #[test]
+    fn test_tile_coding_activates_one_tile_per_tiling() {
+        let tiles = TileCoding::default();
+
+        for dealer in MIN_CARD..MAX_CARD + 1 {
+            for player in MIN_SUM..MAX_SUM + 1 {
+                let state = State { dealer, player };
+                let fv = tiles.to_feature_vector(state, Stick);
+                assert_eq!(fv.len(), DEFAULT_NUM_TILINGS);
+                assert!(active(&fv).iter().all(|&i| i < tiles.num_features()));
+            }
+        }
+
+        let end = State { dealer: 5, player: 25 };
+        assert!(active(&tiles.to_feature_vector(end, Hit)).is_empty());
+    }
+
+    #[test]
+    fn test_tile_coding_offsets_tilings() {
+        let tiles = TileCoding::new(2, 2, 2).with_displacement(1, 1);
+
+        // The first tiling puts sums 1 and 2 in the same tile, while the
+        // second one is offset by a sum, putting them in different ones.
+        let a = tiles.to_feature_vector(State { dealer: 1, player: 1 }, Hit);
+        let b = tiles.to_feature_vector(State { dealer: 2, player: 2 }, Hit);
+        let shared: Vec<_> = active(&a).into_iter()
+          .filter(|i| active(&b).contains(i)).collect();
+
+        assert_eq!(shared.len(), 1);
+    }
+
+    #[test]
+    fn test_one_hot_features_work() {
+        let state = State { dealer: 3, player: 7 };
+        let fv = OneHotFeatures.to_feature_vector(state, Stick);
+        let index = Easy21::state_index(state).unwrap();
+
+        assert_eq!(active(&fv), vec![index * NUM_ACTIONS + 1]);
+        assert!(active(&OneHotFeatures.to_feature_vector(
+            State { dealer: 3, player: 22 }, Stick
+        )).is_empty());
+    }
+
+    #[test]
+    fn test_polynomial_features_work() {
+        let features = PolynomialFeatures::new(2);
+        let fv = features.to_feature_vector(State { dealer: 10, player: 11 },
+                                            Stick);
+
+        assert_eq!(features.num_features(), 12);
+
+        // 1, d, p, d^2, dp, p^2 with d = 1 and p = 0.5.
+        assert_eq!(fv, vec![(1, 1.0), (3, 1.0), (5, 0.5), (7, 1.0),
+                            (9, 0.5), (11, 0.25)]);
+        assert!(features.to_feature_vector(State { dealer: 10, player: 22 },
+                                           Stick).is_empty());
+    }
+
+    #[test]
+    fn test_feature_names_work() {
+        for &name in FEATURE_NAMES {
+            assert_eq!(Features::from_name(name).unwrap().name(), name);
+        }
+        assert_eq!(Features::from_name("boop"), None);
+    }
+}
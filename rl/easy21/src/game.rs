@@ -1,5 +1,7 @@
 use rand::Rng;
 
+use environment::Environment;
+
 use self::Color::*;
 use self::Action::*;
 
@@ -8,14 +10,96 @@ pub const MAX_CARD: i32 = 10;
 pub const MIN_SUM: i32 = 1;
 pub const MAX_SUM: i32 = 21;
 pub const NUM_ACTIONS: usize = 2;
-const DEALER_STICK_MIN: i32 = 17;
+pub const ACTIONS: &[Action; NUM_ACTIONS] = &[Hit, Stick];
+pub const DEALER_STICK_MIN: i32 = 17;
+pub const RED_PROBABILITY: f32 = 1.0 / 3.0;
 
 pub type Reward = f32;
 
-const NO_REWARD: Reward = 0.0;
-const PLAYER_LOSE_REWARD: Reward = -1.0;
-const PLAYER_WIN_REWARD: Reward = 1.0;
-const DRAW_REWARD: Reward = NO_REWARD;
+pub const NO_REWARD: Reward = 0.0;
+pub const PLAYER_LOSE_REWARD: Reward = -1.0;
+pub const PLAYER_WIN_REWARD: Reward = 1.0;
+pub const DRAW_REWARD: Reward = NO_REWARD;
+
+// The parameters of the game. The default is the game as described in
+// the Easy21 assignment, but they can be changed to study how the
+// optimal policy depends on them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rules {
+    // The dealer sticks on any sum of at least this value.
+    pub dealer_stick_min: i32,
+
+    // A sum below min_sum or above max_sum is bust.
+    pub min_sum: i32,
+    pub max_sum: i32,
+
+    // The probability that a drawn card is red (i.e., subtracted), or
+    // None for the assignment's 1/3. The latter draws one of a red and
+    // two black cards, as this module always has, so that seeded runs of
+    // the standard game are reproducible.
+    pub red_probability: Option<f32>,
+
+    // The range of card numbers, which are drawn uniformly.
+    pub min_card: i32,
+    pub max_card: i32,
+
+    pub win_reward: Reward,
+    pub lose_reward: Reward,
+    pub draw_reward: Reward,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            dealer_stick_min: DEALER_STICK_MIN,
+            min_sum: MIN_SUM,
+            max_sum: MAX_SUM,
+            red_probability: None,
+            min_card: MIN_CARD,
+            max_card: MAX_CARD,
+            win_reward: PLAYER_WIN_REWARD,
+            lose_reward: PLAYER_LOSE_REWARD,
+            draw_reward: DRAW_REWARD,
+        }
+    }
+}
+
+impl Rules {
+    pub fn red_probability(&self) -> f32 {
+        self.red_probability.unwrap_or(RED_PROBABILITY)
+    }
+
+    pub fn is_bust(&self, sum: i32) -> bool {
+        !(self.min_sum..=self.max_sum).contains(&sum)
+    }
+
+    // Returns an error if the rules can't be played. The card and sum
+    // ranges can only be narrowed, since the tabular learners and the C
+    // API size their tables from the default ones, and the dealer's
+    // first card must be below the stick minimum, or every state would
+    // be terminal before the player acts.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_card < MIN_CARD || self.max_card > MAX_CARD ||
+           self.min_card > self.max_card {
+            return Err(format!("cards must be within {} to {}",
+                               MIN_CARD, MAX_CARD));
+        }
+        if self.min_sum < MIN_SUM || self.max_sum > MAX_SUM ||
+           self.min_sum > self.max_sum {
+            return Err(format!("sums must be within {} to {}",
+                               MIN_SUM, MAX_SUM));
+        }
+        if self.dealer_stick_min <= self.max_card ||
+           self.dealer_stick_min > self.max_sum {
+            return Err(format!("dealer stick minimum must be from {} to {}",
+                               self.max_card + 1, self.max_sum));
+        }
+        if !(0.0..=1.0).contains(&self.red_probability()) {
+            return Err(String::from("red probability must be from 0 to 1"));
+        }
+        Ok(())
+    }
+}
 
 #[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
 pub enum Action {
@@ -32,29 +116,53 @@ pub enum Color {
 pub trait Deck {
     fn draw_color(&mut self, color: Color) -> Card;
     fn draw(&mut self) -> Card;
+
+    // The rules of the game being played with this deck.
+    fn rules(&self) -> Rules {
+        Rules::default()
+    }
 }
 
 pub struct RngDeck<T: Rng> {
-    rng: T
+    rng: T,
+    rules: Rules,
 }
 
 impl<T: Rng> RngDeck<T> {
     pub fn new(rng: T) -> Self {
-        RngDeck { rng }
+        RngDeck { rng, rules: Rules::default() }
+    }
+
+    // Returns an error if the rules are invalid.
+    pub fn with_rules(mut self, rules: Rules) -> Result<Self, String> {
+        rules.validate()?;
+        self.rules = rules;
+        Ok(self)
     }
 }
 
 impl<T: Rng> Deck for RngDeck<T> {
     fn draw_color(&mut self, color: Color) -> Card {
-        Card::new(self.rng.gen_range(MIN_CARD, MAX_CARD + 1), color)
+        let rules = self.rules;
+        let number = self.rng.gen_range(rules.min_card, rules.max_card + 1);
+        Card::new(number, color, &rules)
     }
 
     fn draw(&mut self) -> Card {
-        // Red should be drawn with a probability of 1/3, while
-        // Black has a 2/3 probability.
-        let color = *self.rng.choose(&[Red, Black, Black]).unwrap();
+        let color = match self.rules.red_probability {
+            None => *self.rng.choose(&[Red, Black, Black]).unwrap(),
+            Some(probability) => if self.rng.next_f32() < probability {
+                Red
+            } else {
+                Black
+            },
+        };
         self.draw_color(color)
     }
+
+    fn rules(&self) -> Rules {
+        self.rules
+    }
 }
 
 #[derive(PartialEq, Debug)]
@@ -64,8 +172,8 @@ pub struct Card {
 }
 
 impl Card {
-    fn new(number: i32, color: Color) -> Self {
-        assert!(number >= MIN_CARD && number <= MAX_CARD);
+    fn new(number: i32, color: Color, rules: &Rules) -> Self {
+        assert!((rules.min_card..=rules.max_card).contains(&number));
         Self { number, color }
     }
 
@@ -84,25 +192,31 @@ pub struct State {
 }
 
 impl State {
-    pub fn new<T: Deck>(deck: &mut T) -> Self {
+    pub fn new<T: Deck + ?Sized>(deck: &mut T) -> Self {
         State {
             dealer: deck.draw_color(Black).value(),
             player: deck.draw_color(Black).value()
         }
     }
 
+    // Whether the state is terminal under the default rules.
     pub fn is_terminal(&self) -> bool {
-        self.player < MIN_SUM || self.player > MAX_SUM ||
-        self.dealer < MIN_SUM || self.dealer >= DEALER_STICK_MIN
+        self.is_terminal_under(&Rules::default())
     }
 
-    pub fn step<T: Deck>(&self, deck: &mut T,
-                         action: Action) -> (Self, Reward) {
+    pub fn is_terminal_under(&self, rules: &Rules) -> bool {
+        rules.is_bust(self.player) || self.dealer < rules.min_sum ||
+        self.dealer >= rules.dealer_stick_min
+    }
+
+    pub fn step<T: Deck + ?Sized>(&self, deck: &mut T,
+                                  action: Action) -> (Self, Reward) {
+        let rules = deck.rules();
         match action {
             Hit => {
                 let player = self.player + deck.draw().value();
-                let reward = if player < MIN_SUM || player > MAX_SUM {
-                    PLAYER_LOSE_REWARD
+                let reward = if rules.is_bust(player) {
+                    rules.lose_reward
                 } else {
                     NO_REWARD
                 };
@@ -110,17 +224,17 @@ impl State {
             },
             Stick => {
                 let mut dealer = self.dealer;
-                while dealer < DEALER_STICK_MIN && dealer >= MIN_SUM {
+                while (rules.min_sum..rules.dealer_stick_min).contains(&dealer) {
                     dealer += deck.draw().value();
                 }
-                let reward = if dealer < MIN_SUM || dealer > MAX_SUM {
-                    PLAYER_WIN_REWARD
+                let reward = if rules.is_bust(dealer) {
+                    rules.win_reward
                 } else if dealer == self.player {
-                    DRAW_REWARD
+                    rules.draw_reward
                 } else if dealer < self.player {
-                    PLAYER_WIN_REWARD
+                    rules.win_reward
                 } else {
-                    PLAYER_LOSE_REWARD
+                    rules.lose_reward
                 };
 
                 (State { dealer, player: self.player }, reward)
@@ -129,12 +243,79 @@ impl State {
     }
 }
 
+// The Easy21 game as an environment for GPI.
+pub struct Easy21 {
+    deck: Box<dyn Deck>,
+}
+
+impl Easy21 {
+    pub fn new<T: Deck + 'static>(deck: T) -> Self {
+        Easy21 { deck: Box::new(deck) }
+    }
+}
+
+impl Environment for Easy21 {
+    type State = State;
+    type Action = Action;
+
+    fn actions() -> &'static [Action] {
+        ACTIONS
+    }
+
+    // Only states within the default rules' dealer card and sum ranges
+    // have an index, which is why Rules::validate() doesn't allow
+    // widening them.
+    fn num_states() -> usize {
+        ((MAX_CARD + 1 - MIN_CARD) * (MAX_SUM + 1 - MIN_SUM)) as usize
+    }
+
+    fn state_index(state: State) -> Option<usize> {
+        if (MIN_CARD..=MAX_CARD).contains(&state.dealer) &&
+           (MIN_SUM..=MAX_SUM).contains(&state.player) {
+            let dealer = state.dealer - MIN_CARD;
+            let player = state.player - MIN_SUM;
+            Some((dealer * (MAX_SUM + 1 - MIN_SUM) + player) as usize)
+        } else {
+            None
+        }
+    }
+
+    fn action_index(action: Action) -> usize {
+        match action {
+            Hit => 0,
+            Stick => 1,
+        }
+    }
+
+    fn state_at(index: usize) -> State {
+        let index = index as i32;
+        State {
+            dealer: index / (MAX_SUM + 1 - MIN_SUM) + MIN_CARD,
+            player: index % (MAX_SUM + 1 - MIN_SUM) + MIN_SUM,
+        }
+    }
+
+    fn reset(&mut self) -> State {
+        State::new(&mut *self.deck)
+    }
+
+    fn step(&mut self, state: State, action: Action) -> (State, Reward) {
+        state.step(&mut *self.deck, action)
+    }
+
+    fn is_terminal(&self, state: State) -> bool {
+        state.is_terminal_under(&self.deck.rules())
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use game::{State, Card, Deck, RngDeck};
+    use game::{State, Card, Deck, RngDeck, Easy21, Rules, MIN_CARD, MAX_CARD,
+               MIN_SUM, MAX_SUM};
+    use environment::Environment;
     use game::Color::*;
     use game::Action::*;
-    use rand::{thread_rng, ThreadRng};
+    use rand::{thread_rng, ThreadRng, Rng, SeedableRng, StdRng};
 
     fn rng_deck() -> RngDeck<ThreadRng> {
         RngDeck::new(thread_rng())
@@ -172,14 +353,115 @@ mod tests {
         }
     }
 
+    #[test]
+    fn easy21_environment_works() {
+        let mut env = Easy21::new(rng_deck());
+        let start = env.reset();
+
+        assert!(!env.is_terminal(start));
+
+        let (end, _) = env.step(start, Stick);
+
+        assert!(env.is_terminal(end));
+    }
+
+    #[test]
+    fn easy21_state_index_works() {
+        let mut seen = vec![false; Easy21::num_states()];
+
+        for dealer in MIN_CARD..MAX_CARD + 1 {
+            for player in MIN_SUM..MAX_SUM + 1 {
+                let index = Easy21::state_index(State { dealer, player });
+                let index = index.unwrap();
+                assert!(!seen[index]);
+                seen[index] = true;
+                assert_eq!(Easy21::state_at(index), State { dealer, player });
+            }
+        }
+
+        assert!(seen.iter().all(|&s| s));
+        assert_eq!(Easy21::state_index(State { dealer: 5, player: 0 }), None);
+        assert_eq!(Easy21::state_index(State { dealer: 18, player: 5 }),
+                   None);
+    }
+
     #[test]
     fn card_new_works() {
-        let c = Card::new(1, Red);
+        let c = Card::new(1, Red, &Rules::default());
 
         assert_eq!(c.number, 1);
         assert_eq!(c.color, Red);
     }
 
+    #[test]
+    fn rules_are_consulted() {
+        let rules = Rules {
+            dealer_stick_min: 10,
+            red_probability: Some(0.0),
+            max_card: 2,
+            win_reward: 2.0,
+            ..Rules::default()
+        };
+        let mut deck = rng_deck().with_rules(rules).unwrap();
+
+        for _ in 0..300 {
+            let card = deck.draw();
+            assert_eq!(card.color, Black);
+            assert!(card.number <= 2);
+        }
+
+        let start = State { dealer: 5, player: 21 };
+        let (end, reward) = start.step(&mut deck, Stick);
+
+        assert!(end.is_terminal_under(&rules));
+        assert!(end.dealer >= 10 && end.dealer <= 11);
+        assert_eq!(reward, 2.0);
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(Rules::default().validate().is_ok());
+
+        for &rules in [
+            Rules { max_sum: MAX_SUM + 1, ..Rules::default() },
+            Rules { min_sum: MIN_SUM - 1, ..Rules::default() },
+            Rules { max_card: MAX_CARD + 1, ..Rules::default() },
+            Rules { min_card: 0, ..Rules::default() },
+            Rules { dealer_stick_min: MAX_CARD, ..Rules::default() },
+            Rules { red_probability: Some(1.5), ..Rules::default() },
+        ].iter() {
+            assert!(rules.validate().is_err(), "{:?} should be invalid",
+                    rules);
+            assert!(rng_deck().with_rules(rules).is_err());
+        }
+    }
+
+    #[test]
+    fn lowest_valid_stick_min_leaves_start_states_open() {
+        let rules = Rules {
+            dealer_stick_min: MAX_CARD + 1,
+            ..Rules::default()
+        };
+
+        for dealer in MIN_CARD..MAX_CARD + 1 {
+            let state = State { dealer, player: 10 };
+            assert!(!state.is_terminal_under(&rules));
+        }
+    }
+
+    #[test]
+    fn default_rules_draw_colors_as_before() {
+        let seed: &[_] = &[1, 2, 3, 4];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let mut deck = RngDeck::new(rng);
+
+        for _ in 0..300 {
+            let color = *rng.choose(&[Red, Black, Black]).unwrap();
+            rng.gen_range(MIN_CARD, MAX_CARD + 1);
+            assert_eq!(deck.draw().color, color);
+        }
+    }
+
     #[test]
     fn rng_deck_draw_works() {
         let mut deck = rng_deck();
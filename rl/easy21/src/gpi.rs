@@ -6,20 +6,49 @@ use std::collections::HashMap;
 
 use rand::Rng;
 
-use game::{State, Action, Deck, Reward, MIN_SUM, MAX_SUM, MIN_CARD, MAX_CARD};
-use game::Action::*;
+use game::{State, Action, Reward, Easy21, MIN_SUM, MAX_SUM, MIN_CARD,
+           MAX_CARD};
+use environment::Environment;
 use util::increment;
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
 
 
-// This trait encapsulates a specific algorithm to use for GPI.
-pub trait Alg {
+// Prints a grid of the given value of every Easy21 state, in
+// hundredths.
+pub fn print_state_values<F: Fn(State) -> f32>(value: F) {
+    let dealer_rng = MIN_CARD..MAX_CARD + 1;
+    for player in (MIN_SUM..MAX_SUM + 1).rev() {
+        for dealer in dealer_rng.clone() {
+            let ivalue = (value(State { dealer, player }) * 100.0) as i32;
+            print!("{:4} ", ivalue);
+        }
+        println!("  <- player sum = {}", player);
+    }
+    for _ in dealer_rng.clone() {
+        print!("----");
+    }
+    println!();
+    for dealer in dealer_rng {
+        if dealer == 1 {
+            print!("   A ");
+        } else {
+            print!("{:4} ", dealer);
+        }
+    }
+    println!("  <- dealer showing");
+}
+
+// This trait encapsulates a specific algorithm to use for GPI on
+// the given environment.
+pub trait Alg<E: Environment = Easy21> {
     // Given the current state, return the action that maximizes reward
     // in the long-term.
-    fn choose_best_action(&self, state: State) -> Action;
+    fn choose_best_action(&self, state: E::State) -> E::Action;
 
     // Return the expected long-term reward if we take the given action
     // at the given state.
-    fn get_expected_reward(&self, state: State, action: Action) -> Reward;
+    fn get_expected_reward(&self, state: E::State,
+                           action: E::Action) -> Reward;
 
     // A hook that's called whenever an episode begins.
     fn on_episode_begin(&mut self) {
@@ -31,15 +60,51 @@ pub trait Alg {
         false
     }
 
+    // Whether or not the algorithm needs to know how likely the behavior
+    // policy is to take each action, via on_action_probabilities().
+    fn needs_action_probabilities(&self) -> bool {
+        false
+    }
+
+    // A hook that's called just before on_episode_step() if
+    // needs_action_probabilities() returns true. It's passed the
+    // probability that the behavior policy will take each of
+    // E::actions() at the step's next state.
+    fn on_action_probabilities(&mut self, state: E::State,
+                               probabilities: &[f32]) {
+        let _ = (state, probabilities);
+    }
+
+    // Whether or not the algorithm needs to know how likely the behavior
+    // policy was to take each step's action, via
+    // on_behavior_probability().
+    fn needs_behavior_probability(&self) -> bool {
+        false
+    }
+
+    // A hook that's called just before on_episode_step() if
+    // needs_behavior_probability() returns true. It's passed the
+    // probability with which the behavior policy chose the step's action
+    // at the step's state.
+    fn on_behavior_probability(&mut self, probability: f32) {
+        let _ = probability;
+    }
+
+    // A hook that's called just before on_episode_step() if the action
+    // taken wasn't the one the algorithm considered best when the policy
+    // chose it, e.g. because the policy was exploring.
+    fn on_exploratory_step(&mut self) {
+    }
+
     // A hook that's called whenever an episode transitions from one state
     // to another, as the result of an action. Can optionally return a
     // successor action to use next.
     //
     // Note that next_action will only be valid if needs_next_action()
     // returns true.
-    fn on_episode_step(&mut self, state: State, action: Action,
-                       reward: Reward, next_state: State,
-                       next_action: Option<Action>) -> Option<Action> {
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward, next_state: E::State,
+                       next_action: Option<E::Action>) -> Option<E::Action> {
         let _ = (state, action, reward, next_state, next_action);
         None
     }
@@ -51,171 +116,302 @@ pub trait Alg {
 
     // Print the expected reward for every state given that we
     // take the optimal action at each state.
-    fn print_optimal_values(&self) {
-        let dealer_rng = MIN_CARD..MAX_CARD + 1;
-        for player in (MIN_SUM..MAX_SUM + 1).rev() {
-            for dealer in dealer_rng.clone() {
-                let state = State { dealer, player };
-                let action = self.choose_best_action(state);
-                let value = self.get_expected_reward(state, action);
-                let ivalue = (value * 100.0) as i32;
-                print!("{:4} ", ivalue);
-            }
-            println!("  <- player sum = {}", player);
-        }
-        for _ in dealer_rng.clone() {
-            print!("----");
-        }
-        println!();
-        for dealer in dealer_rng {
-            if dealer == 1 {
-                print!("   A ");
-            } else {
-                print!("{:4} ", dealer);
-            }
-        }
-        println!("  <- dealer showing");
+    fn print_optimal_values(&self)
+        where E: Environment<State = State, Action = Action> {
+        print_state_values(|state| {
+            self.get_expected_reward(state, self.choose_best_action(state))
+        });
     }
 }
 
-pub trait Policy {
-    fn choose_action(&mut self, state: State) -> Action;
+pub trait Policy<E: Environment = Easy21> {
+    fn choose_action(&mut self, state: E::State) -> E::Action;
 
     fn on_episode_begin(&mut self);
 
-    fn on_episode_step(&mut self, state: State, action: Action,
-                       reward: Reward, next_state: State) -> Option<Action>;
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward,
+                       next_state: E::State) -> Option<E::Action>;
 
     fn on_episode_end(&mut self);
+
+    // A hook that's called when an episode begins with an action that
+    // was chosen uniformly at random instead of by the policy, as with
+    // exploring starts.
+    fn on_exploring_start(&mut self, state: E::State, action: E::Action) {
+        let _ = (state, action);
+    }
+}
+
+// A policy that chooses actions based on the values an algorithm has
+// learned, and passes each step on to it.
+pub trait AlgPolicy<E: Environment = Easy21>: Policy<E> {
+    type Alg: Alg<E>;
+
+    fn alg(&self) -> &Self::Alg;
+
+    fn alg_mut(&mut self) -> &mut Self::Alg;
+
+    // Returns the probability that we'll take each of E::actions() at
+    // the given state.
+    fn action_probabilities(&self, state: E::State) -> Vec<f32>;
+
+    // Passes a step on to the algorithm, calling whichever of its hooks
+    // it needs. `chose_best_action` and `chosen_action_probability`
+    // describe how the step's action was chosen.
+    fn step_alg(&mut self, state: E::State, action: E::Action,
+                reward: Reward, next_state: E::State,
+                chose_best_action: bool,
+                chosen_action_probability: f32) -> Option<E::Action> {
+        if !chose_best_action {
+            self.alg_mut().on_exploratory_step();
+        }
+
+        if self.alg().needs_behavior_probability() {
+            self.alg_mut().on_behavior_probability(chosen_action_probability);
+        }
+
+        // Argh, I wanted to just pass the policy in as the last argument, so
+        // that the algorithm (e.g. Sarsa) could calculate the next action
+        // only if it needed to, but that raised an error complaining that
+        // `EpsilonGreedyPolicy` didn't implement `Policy`.
+        //
+        // Then I tried changing the last parameter to just being
+        // `FnMut(State) -> Action`, but that raised errors with the
+        // borrow checker when I tried passing `self.choose_action`.
+        //
+        // Then I tried using RefCells to make this class' mutability
+        // more granular, but that didn't work either:
+        //
+        //     https://github.com/toolness/ml-fun/pull/2
+        //
+        // The only remaining option is to create Alg::needs_next_action(),
+        // and pass the next action to the algorithm only if that returns
+        // true.
+        //
+        // It should also be noted that tinkering with the calling
+        // convention of `Alg.on_episode_step()` is difficult once we
+        // have multiple trait implementations in place, since we have to
+        // change the trait definition *and* every implementation site
+        // just to see what the borrow checker thinks.
+        let next_action = if self.alg().needs_next_action() {
+            Some(self.choose_action(next_state))
+        } else {
+            None
+        };
+        if self.alg().needs_action_probabilities() {
+            let probabilities = self.action_probabilities(next_state);
+            self.alg_mut().on_action_probabilities(next_state, &probabilities);
+        }
+        self.alg_mut().on_episode_step(state, action, reward, next_state,
+                                       next_action)
+    }
 }
 
+// The default value of N_0 for varying epsilon, which is the number
+// of visits to a state at which epsilon becomes 0.5.
+pub const DEFAULT_N0: f32 = 100.0;
+
 #[derive(Debug, PartialEq)]
 enum EpsilonType {
-    Varying,
+    Varying(f32),
     Constant(f32),
 }
 
-pub struct EpsilonGreedyPolicy<T: Rng, U: Alg> {
-    times_visited: HashMap<State, f32>,
+pub struct EpsilonGreedyPolicy<T: Rng, U: Alg<E>, E: Environment = Easy21> {
+    times_visited: HashMap<E::State, f32>,
     rng: T,
     pub alg: U,
     epsilon: EpsilonType,
+
+    // Whether the last action we chose was the best one according to
+    // the algorithm.
+    chose_best_action: bool,
+
+    // The probability with which we chose the last action, if the
+    // algorithm needs it.
+    chosen_action_probability: f32,
 }
 
-impl<T: Rng, U: Alg> EpsilonGreedyPolicy<T, U> {
+impl<T: Rng, U: Alg<E>, E: Environment> EpsilonGreedyPolicy<T, U, E> {
     pub fn new(rng: T, alg: U) -> Self {
         EpsilonGreedyPolicy {
             times_visited: HashMap::new(),
             rng,
             alg,
-            epsilon: EpsilonType::Varying,
+            epsilon: EpsilonType::Varying(DEFAULT_N0),
+            chose_best_action: true,
+            chosen_action_probability: 1.0,
         }
     }
 
+    pub fn with_varying_epsilon(mut self, n_0: f32) -> Self {
+        self.epsilon = EpsilonType::Varying(n_0);
+        self
+    }
+
     pub fn with_constant_epsilon(mut self, value: f32) -> Self {
         self.epsilon = EpsilonType::Constant(value);
         self
     }
 
-    fn exploratory_action(&mut self) -> Action {
-        if self.rng.gen_weighted_bool(2) { Hit } else { Stick }
+    fn exploratory_action(&mut self) -> E::Action {
+        *self.rng.choose(E::actions()).unwrap()
     }
 
-    fn should_explore(&mut self, state: State) -> bool {
-        let epsilon = match self.epsilon {
-            EpsilonType::Varying => {
-                let n_0 = 100.0;
+    fn epsilon(&self, state: E::State) -> f32 {
+        match self.epsilon {
+            EpsilonType::Varying(n_0) => {
                 let visited = *self.times_visited.get(&state).unwrap_or(&0.0);
                 n_0 / (n_0 + visited)
             },
             EpsilonType::Constant(value) => {
                 value
             }
-        };
+        }
+    }
+
+    fn should_explore(&mut self, state: E::State) -> bool {
+        let epsilon = self.epsilon(state);
         self.rng.next_f32() < epsilon
     }
 }
 
-impl<T: Rng, U: Alg> Policy for EpsilonGreedyPolicy<T, U> {
-    fn choose_action(&mut self, state: State) -> Action {
-        if self.should_explore(state) {
-            self.exploratory_action()
+impl<T: Rng, U: Alg<E>, E: Environment> Policy<E> for EpsilonGreedyPolicy<T, U, E> {
+    fn choose_action(&mut self, state: E::State) -> E::Action {
+        let action = if self.should_explore(state) {
+            let action = self.exploratory_action();
+            self.chose_best_action =
+                action == self.alg.choose_best_action(state);
+            action
         } else {
+            self.chose_best_action = true;
             self.alg.choose_best_action(state)
+        };
+        if self.alg.needs_behavior_probability() {
+            let index = E::action_index(action);
+            self.chosen_action_probability =
+                self.action_probabilities(state)[index];
         }
+        action
     }
 
     fn on_episode_begin(&mut self) {
         self.alg.on_episode_begin();
     }
 
-    fn on_episode_step(&mut self, state: State, action: Action,
-                       reward: Reward, next_state: State) -> Option<Action> {
-        if self.epsilon == EpsilonType::Varying {
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward,
+                       next_state: E::State) -> Option<E::Action> {
+        if let EpsilonType::Varying(_) = self.epsilon {
             increment(&mut self.times_visited, state, 1.0);
         }
 
-        // Argh, I wanted to just pass the policy in as the last argument, so
-        // that the algorithm (e.g. Sarsa) could calculate the next action
-        // only if it needed to, but that raised an error complaining that
-        // `EpsilonGreedyPolicy` didn't implement `Policy`.
-        //
-        // Then I tried changing the last parameter to just being
-        // `FnMut(State) -> Action`, but that raised errors with the
-        // borrow checker when I tried passing `self.choose_action`.
-        //
-        // Then I tried using RefCells to make this class' mutability
-        // more granular, but that didn't work either:
-        //
-        //     https://github.com/toolness/ml-fun/pull/2
-        //
-        // The only remaining option is to create Alg::needs_next_action(),
-        // and pass the next action to the algorithm only if that returns
-        // true.
-        //
-        // It should also be noted that tinkering with the calling
-        // convention of `Alg.on_episode_step()` is difficult once we
-        // have multiple trait implementations in place, since we have to
-        // change the trait definition *and* every implementation site
-        // just to see what the borrow checker thinks.
-        let next_action = if self.alg.needs_next_action() {
-            Some(self.choose_action(next_state))
-        } else {
-            None
-        };
-        self.alg.on_episode_step(state, action, reward, next_state,
-                                 next_action)
+        self.step_alg(state, action, reward, next_state,
+                      self.chose_best_action, self.chosen_action_probability)
     }
 
     fn on_episode_end(&mut self) {
         self.alg.on_episode_end();
     }
+
+    fn on_exploring_start(&mut self, state: E::State, action: E::Action) {
+        self.chose_best_action = action == self.alg.choose_best_action(state);
+        self.chosen_action_probability = 1.0 / E::actions().len() as f32;
+    }
+}
+
+impl<T: Rng, U: Alg<E>, E: Environment> AlgPolicy<E> for EpsilonGreedyPolicy<T, U, E> {
+    type Alg = U;
+
+    fn alg(&self) -> &U {
+        &self.alg
+    }
+
+    fn alg_mut(&mut self) -> &mut U {
+        &mut self.alg
+    }
+
+    fn action_probabilities(&self, state: E::State) -> Vec<f32> {
+        let epsilon = self.epsilon(state);
+        let best = self.alg.choose_best_action(state);
+        let actions = E::actions();
+        let explore = epsilon / actions.len() as f32;
+
+        actions.iter().map(|&action| {
+            if action == best { explore + 1.0 - epsilon } else { explore }
+        }).collect()
+    }
+}
+
+impl<T: Rng, U: Alg + Checkpointable> Checkpointable for EpsilonGreedyPolicy<T, U> {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_state_table("times_visited", &self.times_visited);
+        self.alg.save(checkpoint);
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        self.times_visited = checkpoint.get_state_table("times_visited")?;
+        self.alg.load(checkpoint)
+    }
 }
 
-pub struct Gpi<T: Deck, U: Policy> {
+pub struct Gpi<E: Environment, U: Policy<E>> {
     episodes: i32,
-    deck: T,
+    env: E,
     pub policy: U,
+
+    // If present, every episode starts at a uniformly random state and
+    // action chosen with this, rather than at the environment's usual
+    // start with the policy's action.
+    exploring_starts: Option<Box<dyn Rng>>,
 }
 
-impl<T: Deck, U: Policy> Gpi<T, U> {
-    pub fn new(deck: T, policy: U) -> Self {
+impl<E: Environment, U: Policy<E>> Gpi<E, U> {
+    pub fn new(env: E, policy: U) -> Self {
         Gpi {
             episodes: 0,
-            deck,
+            env,
             policy,
+            exploring_starts: None,
         }
     }
 
-    pub fn play_episode(&mut self) {
-        let mut state = State::new(&mut self.deck);
+    pub fn with_exploring_starts<T: Rng + 'static>(mut self, rng: T) -> Self {
+        self.exploring_starts = Some(Box::new(rng));
+        self
+    }
 
-        self.policy.on_episode_begin();
+    // Begins an episode at a random non-terminal state, returning it
+    // along with a random action to take there.
+    fn exploring_start(&mut self) -> (E::State, E::Action) {
+        let rng = self.exploring_starts.as_mut().unwrap();
+        loop {
+            let state = E::state_at(rng.gen_range(0, E::num_states()));
+            if !self.env.is_terminal(state) {
+                self.env.reset_to(state);
+                return (state, *rng.choose(E::actions()).unwrap());
+            }
+        }
+    }
 
-        let mut action = self.policy.choose_action(state);
+    // Plays a single episode, returning the total reward received.
+    pub fn play_episode(&mut self) -> Reward {
+        let mut total_reward = 0.0;
+        let (mut state, mut action) = if self.exploring_starts.is_some() {
+            let (state, action) = self.exploring_start();
+            self.policy.on_episode_begin();
+            self.policy.on_exploring_start(state, action);
+            (state, action)
+        } else {
+            let state = self.env.reset();
+            self.policy.on_episode_begin();
+            (state, self.policy.choose_action(state))
+        };
 
-        while !state.is_terminal() {
-            let (next_state, reward) = state.step(&mut self.deck, action);
+        while !self.env.is_terminal(state) {
+            let (next_state, reward) = self.env.step(state, action);
+            total_reward += reward;
             match self.policy.on_episode_step(state, action, reward,
                                               next_state) {
                 None => {
@@ -230,6 +426,8 @@ impl<T: Deck, U: Policy> Gpi<T, U> {
 
         self.policy.on_episode_end();
         self.episodes += 1;
+
+        total_reward
     }
 
     pub fn play_episodes(&mut self, count: i32) {
@@ -237,14 +435,51 @@ impl<T: Deck, U: Policy> Gpi<T, U> {
             self.play_episode();
         }
     }
+
+    // Like play_episodes(), but calls the given observer after every
+    // `interval` episodes, and after the final one. This can be used to
+    // e.g. record a learning curve.
+    pub fn play_episodes_with_observer<F: FnMut(&Self)>(
+        &mut self,
+        count: i32,
+        interval: i32,
+        mut observer: F
+    ) {
+        for i in 1..count + 1 {
+            self.play_episode();
+            if i % interval == 0 || i == count {
+                observer(self);
+            }
+        }
+    }
+
+    // The total number of episodes played so far.
+    pub fn episodes(&self) -> i32 {
+        self.episodes
+    }
+}
+
+impl<U: Policy + Checkpointable> Checkpointable for Gpi<Easy21, U> {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_integer("episodes", self.episodes as i64);
+        self.policy.save(checkpoint);
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        self.episodes = checkpoint.get_integer("episodes")? as i32;
+        self.policy.load(checkpoint)
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
-    use game::{RngDeck, State, Action, Reward};
+    use std::collections::HashSet;
+
+    use game::{RngDeck, State, Action, Reward, Easy21};
     use rand::thread_rng;
 
-    use gpi::{Gpi, Alg, EpsilonGreedyPolicy, EpsilonType};
+    use gpi::{Gpi, Alg, AlgPolicy, EpsilonGreedyPolicy, EpsilonType,
+              DEFAULT_N0};
 
     pub struct DumbAlg {
         pub action: Action,
@@ -263,19 +498,36 @@ pub mod tests {
 
     #[test]
     fn test_play_episodes_works() {
-        let deck = RngDeck::new(thread_rng());
+        let env = Easy21::new(RngDeck::new(thread_rng()));
         let policy = EpsilonGreedyPolicy::new(thread_rng(), DumbAlg {
             action: Action::Hit,
             reward: 0.0,
         });
-        assert_eq!(policy.epsilon, EpsilonType::Varying);
-        let mut gpi = Gpi::new(deck, policy);
+        assert_eq!(policy.epsilon, EpsilonType::Varying(DEFAULT_N0));
+        let mut gpi = Gpi::new(env, policy);
 
         gpi.play_episodes(3);
 
         assert_eq!(gpi.episodes, 3);
     }
 
+    #[test]
+    fn test_play_episodes_with_observer_works() {
+        let env = Easy21::new(RngDeck::new(thread_rng()));
+        let policy = EpsilonGreedyPolicy::new(thread_rng(), DumbAlg {
+            action: Action::Hit,
+            reward: 0.0,
+        });
+        let mut gpi = Gpi::new(env, policy);
+        let mut observed = Vec::new();
+
+        gpi.play_episodes_with_observer(7, 3, |gpi| {
+            observed.push(gpi.episodes());
+        });
+
+        assert_eq!(observed, vec![3, 6, 7]);
+    }
+
     #[test]
     fn test_constant_epsilon_works() {
         let policy = EpsilonGreedyPolicy::new(thread_rng(), DumbAlg {
@@ -284,4 +536,87 @@ pub mod tests {
         }).with_constant_epsilon(0.5);
         assert_eq!(policy.epsilon, EpsilonType::Constant(0.5));
     }
+
+    #[test]
+    fn test_action_probabilities_works() {
+        let policy = EpsilonGreedyPolicy::new(thread_rng(), DumbAlg {
+            action: Action::Stick,
+            reward: 0.0,
+        }).with_constant_epsilon(0.5);
+        let state = State { dealer: 1, player: 1 };
+
+        assert_eq!(policy.action_probabilities(state), vec![0.25, 0.75]);
+    }
+
+    #[test]
+    fn test_exploring_starts_works() {
+        let env = Easy21::new(RngDeck::new(thread_rng()));
+        let policy = EpsilonGreedyPolicy::new(thread_rng(), DumbAlg {
+            action: Action::Stick,
+            reward: 0.0,
+        }).with_constant_epsilon(0.0);
+        let mut gpi = Gpi::new(env, policy)
+          .with_exploring_starts(thread_rng());
+        let mut hit = false;
+        let mut players = HashSet::new();
+
+        for _ in 0..200 {
+            let (state, action) = gpi.exploring_start();
+            hit = hit || action == Action::Hit;
+            players.insert(state.player);
+            gpi.play_episode();
+        }
+
+        // The policy always sticks, but exploring starts still hit at
+        // a wide range of player sums.
+        assert!(hit);
+        assert!(players.len() > 10);
+    }
+
+    struct BehaviorProbabilityAlg {
+        probabilities: Vec<f32>,
+    }
+
+    impl Alg for BehaviorProbabilityAlg {
+        fn choose_best_action(&self, _: State) -> Action {
+            Action::Stick
+        }
+
+        fn get_expected_reward(&self, _: State, _: Action) -> Reward {
+            0.0
+        }
+
+        fn needs_behavior_probability(&self) -> bool {
+            true
+        }
+
+        fn on_behavior_probability(&mut self, probability: f32) {
+            self.probabilities.push(probability);
+        }
+    }
+
+    #[test]
+    fn test_behavior_probability_works() {
+        let env = Easy21::new(RngDeck::new(thread_rng()));
+        let policy = EpsilonGreedyPolicy::new(thread_rng(),
+                                              BehaviorProbabilityAlg {
+            probabilities: Vec::new(),
+        }).with_constant_epsilon(0.5);
+        let mut gpi = Gpi::new(env, policy);
+
+        gpi.play_episodes(10);
+
+        let probabilities = &gpi.policy.alg.probabilities;
+        assert!(probabilities.len() >= 10);
+        assert!(probabilities.iter().all(|&p| p == 0.25 || p == 0.75));
+    }
+
+    #[test]
+    fn test_varying_epsilon_works() {
+        let policy = EpsilonGreedyPolicy::new(thread_rng(), DumbAlg {
+            action: Action::Hit,
+            reward: 0.0,
+        }).with_varying_epsilon(50.0);
+        assert_eq!(policy.epsilon, EpsilonType::Varying(50.0));
+    }
 }
@@ -1,29 +1,179 @@
-use std::collections::HashMap;
-use std::ops::Range;
-
-use game::{State, Action, Reward, NUM_ACTIONS};
+use game::{State, Action, Reward};
 use game::Action::*;
 use gpi::Alg;
-use util::{increment};
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+use traces::TraceType;
+use features::{FeatureExtractor, FeatureVector, CuboidFeatures};
+
 
+pub const DEFAULT_STEP_SIZE: f32 = 0.01;
 
-type EligibilityHash = HashMap<(State, Action), f32>;
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UpdateMode {
+    // Semi-gradient Sarsa(lambda) with eligibility traces, which only
+    // approximates the forward view and can diverge at lambda near 1.
+    Classic,
+
+    // True online Sarsa(lambda), as described in Section 12.8 of Sutton &
+    // Barto, which exactly matches the online forward view. It always
+    // uses Dutch traces, regardless of the trace type.
+    TrueOnline,
+}
 
 pub struct LinearFunctionApproximator {
-    traces: EligibilityHash,
+    features: Box<dyn FeatureExtractor>,
+
+    // An eligibility trace for every weight. Only the traces of features
+    // that have been active since the episode began are kept track of,
+    // so that updates don't need to iterate over every weight.
+    traces: Vec<f32>,
+    is_traced: Vec<bool>,
+    traced: Vec<usize>,
+    trace_type: TraceType,
     weights: Weights,
     lambda: f32,
+    discount: f32,
     step_size: f32,
+    update_mode: UpdateMode,
+
+    // The value of the current state/action pair as of the previous
+    // step, which true online updates correct for.
+    old_value: Reward,
 }
 
 impl LinearFunctionApproximator {
     pub fn new(lambda: f32, step_size: f32) -> Self {
+        let features = CuboidFeatures;
+        let num_features = features.num_features();
         LinearFunctionApproximator {
-            traces: HashMap::new(),
-            weights: [0.0; NUM_FEATURES],
+            features: Box::new(features),
+            traces: vec![0.0; num_features],
+            is_traced: vec![false; num_features],
+            traced: Vec::new(),
+            trace_type: TraceType::default(),
+            weights: vec![0.0; num_features],
             lambda,
+            discount: 1.0,
             step_size,
+            update_mode: UpdateMode::Classic,
+            old_value: 0.0,
+        }
+    }
+
+    // Uses the given features instead of the default cuboids. This
+    // resets the weights.
+    pub fn with_features<F: FeatureExtractor + 'static>(mut self,
+                                                        features: F) -> Self {
+        let num_features = features.num_features();
+        self.features = Box::new(features);
+        self.traces = vec![0.0; num_features];
+        self.is_traced = vec![false; num_features];
+        self.traced.clear();
+        self.weights = vec![0.0; num_features];
+        self
+    }
+
+    fn to_feature_vector(&self, state: State,
+                         action: Action) -> FeatureVector {
+        self.features.to_feature_vector(state, action)
+    }
+
+    pub fn with_true_online_updates(mut self) -> Self {
+        self.update_mode = UpdateMode::TrueOnline;
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    pub fn with_trace_type(mut self, trace_type: TraceType) -> Self {
+        self.trace_type = trace_type;
+        self
+    }
+
+    // Starts keeping track of the traces of the given features.
+    fn trace(&mut self, features: &FeatureVector) {
+        for &(index, _) in features.iter() {
+            if !self.is_traced[index] {
+                self.is_traced[index] = true;
+                self.traced.push(index);
+            }
+        }
+    }
+
+    fn clear_traces(&mut self) {
+        for &index in self.traced.iter() {
+            self.traces[index] = 0.0;
+            self.is_traced[index] = false;
+        }
+        self.traced.clear();
+    }
+
+    // Updates the traces of the given features, which were just visited.
+    // Replacing traces set the trace of every active feature to its
+    // value, as described in Section 12.2 of Sutton & Barto, while Dutch
+    // traces follow equation 12.11.
+    fn visit(&mut self, features: &FeatureVector) {
+        self.trace(features);
+        match self.trace_type {
+            TraceType::Accumulating => {
+                for &(index, feature) in features.iter() {
+                    self.traces[index] += feature;
+                }
+            },
+            TraceType::Replacing => {
+                for &(index, feature) in features.iter() {
+                    if feature != 0.0 {
+                        self.traces[index] = feature;
+                    }
+                }
+            },
+            TraceType::Dutch => {
+                let scale = 1.0 - self.step_size *
+                                  dot_product(features, &self.traces);
+                for &(index, feature) in features.iter() {
+                    self.traces[index] += scale * feature;
+                }
+            }
+        }
+    }
+
+    fn classic_update(&mut self, features: &FeatureVector,
+                      next_features: &FeatureVector, reward: Reward) {
+        let td_error = reward +
+                       self.discount *
+                       dot_product(next_features, &self.weights) -
+                       dot_product(features, &self.weights);
+        let decay = self.discount * self.lambda;
+        self.visit(features);
+        for &index in self.traced.iter() {
+            self.weights[index] += self.step_size * td_error *
+                                   self.traces[index];
+            self.traces[index] *= decay;
+        }
+    }
+
+    fn true_online_update(&mut self, features: &FeatureVector,
+                          next_features: &FeatureVector, reward: Reward) {
+        let value = dot_product(features, &self.weights);
+        let next_value = dot_product(next_features, &self.weights);
+        let td_error = reward + self.discount * next_value - value;
+        let decay = self.discount * self.lambda;
+        let scale = 1.0 - self.step_size * decay *
+                          dot_product(features, &self.traces);
+        let correction = value - self.old_value;
+        self.trace(features);
+        for &index in self.traced.iter() {
+            let feature = feature_value(features, index);
+            let trace = decay * self.traces[index] + scale * feature;
+            self.traces[index] = trace;
+            self.weights[index] += self.step_size * (td_error + correction) *
+                                   trace -
+                                   self.step_size * correction * feature;
         }
+        self.old_value = next_value;
     }
 }
 
@@ -35,12 +185,13 @@ impl Alg for LinearFunctionApproximator {
     }
 
     fn get_expected_reward(&self, state: State, action: Action) -> Reward {
-        let features = to_feature_vector(state, action);
-        dot_product(features, self.weights)
+        let features = self.to_feature_vector(state, action);
+        dot_product(&features, &self.weights)
     }
 
     fn on_episode_begin(&mut self) {
-        self.traces.drain();
+        self.clear_traces();
+        self.old_value = 0.0;
     }
 
     fn needs_next_action(&self) -> bool {
@@ -50,123 +201,161 @@ impl Alg for LinearFunctionApproximator {
     fn on_episode_step(&mut self, state: State, action: Action,
                        reward: Reward, next_state: State,
                        next_action: Option<Action>) -> Option<Action> {
-        let td_error = reward +
-                       self.get_expected_reward(next_state,
-                                                next_action.unwrap()) -
-                       self.get_expected_reward(state, action);
-        increment(&mut self.traces, (state, action), 1.0);
-        for (&(state, action), trace) in self.traces.iter_mut() {
-            let eligibility_trace = *trace;
-
-            let features = to_feature_vector(state, action);
-            for i in 0..NUM_FEATURES {
-                self.weights[i] += self.step_size * eligibility_trace *
-                                   td_error * features[i];
-            }
-
-            *trace = self.lambda * eligibility_trace;
+        let features = self.to_feature_vector(state, action);
+        let next_features = self.to_feature_vector(next_state,
+                                                   next_action.unwrap());
+        match self.update_mode {
+            UpdateMode::Classic => {
+                self.classic_update(&features, &next_features, reward);
+            },
+            UpdateMode::TrueOnline => {
+                self.true_online_update(&features, &next_features, reward);
+            },
         }
         next_action
     }
 }
 
-type Ranges = [Range<i32>];
-
-const NUM_DEALER_RANGES: usize = 3;
-
-const DEALER_RANGES: &[Range<i32>; NUM_DEALER_RANGES] = &[
-    1..5, 4..8, 7..11
-];
-
-const NUM_PLAYER_RANGES: usize = 6;
-
-const PLAYER_RANGES: &[Range<i32>; NUM_PLAYER_RANGES] = &[
-    1..7, 4..10, 7..13, 10..16, 13..19, 16..22
-];
-
-const NUM_FEATURES: usize = NUM_DEALER_RANGES * NUM_PLAYER_RANGES *
-                            NUM_ACTIONS;
-
-type FeatureVector = [f32; NUM_FEATURES];
-
-type Weights = FeatureVector;
+impl Checkpointable for LinearFunctionApproximator {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg("lfa");
+        checkpoint.set_values("weights", &self.weights);
+    }
 
-fn dot_product(features: FeatureVector, weights: Weights) -> f32 {
-    (0..NUM_FEATURES).fold(0.0, |sum, i| sum + features[i] * weights[i])
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg("lfa")?;
+        let weights = checkpoint.get_values("weights")?;
+        if weights.len() != self.weights.len() {
+            return Err(CheckpointError::Parse(format!(
+                "expected {} weights, found {}", self.weights.len(),
+                weights.len()
+            )));
+        }
+        self.weights.copy_from_slice(&weights);
+        Ok(())
+    }
 }
 
-fn get_ranges_inside(value: i32, ranges: &Ranges, target: &mut [f32]) {
-    for i in 0..ranges.len() {
-        target[i] = if value >= ranges[i].start && value < ranges[i].end {
-            1.0
-        } else {
-            0.0
-        };
-    }
+type Weights = Vec<f32>;
+
+pub(crate) fn dot_product(features: &FeatureVector, weights: &[f32]) -> f32 {
+    features.iter()
+      .fold(0.0, |sum, &(index, feature)| sum + feature * weights[index])
 }
 
-fn to_feature_vector(state: State, action: Action) -> FeatureVector {
-    let mut vector = [0.0; NUM_FEATURES];
-    let mut i = 0;
-    let mut dealer_indexes = [0.0; NUM_DEALER_RANGES];
-    let mut player_indexes = [0.0; NUM_PLAYER_RANGES];
-    let action_indexes: &[f32; NUM_ACTIONS] = match action {
-        Hit => &[1.0, 0.0],
-        Stick => &[0.0, 1.0]
-    };
-
-    get_ranges_inside(state.player, PLAYER_RANGES, &mut player_indexes);
-    get_ranges_inside(state.dealer, DEALER_RANGES, &mut dealer_indexes);
-
-    for dealer in dealer_indexes.iter() {
-        for player in player_indexes.iter() {
-            for action in action_indexes.iter() {
-                vector[i] = dealer * player * action;
-                i += 1;
-            }
-        }
+// Returns the value of the feature at the given index, which is 0 if
+// it's inactive.
+fn feature_value(features: &FeatureVector, index: usize) -> f32 {
+    match features.binary_search_by_key(&index, |&(i, _)| i) {
+        Ok(i) => features[i].1,
+        Err(_) => 0.0,
     }
-
-    vector
 }
 
 #[cfg(test)]
 mod tests {
     use lfa::*;
     use game::State;
+    use features::{CuboidFeatures, OneHotFeatures};
+    use shortcuts;
+    use shortcuts::Config;
+
+    const NUM_FEATURES: usize = 36;
+
+    #[test]
+    fn test_trace_types_work() {
+        let features = CuboidFeatures.to_feature_vector(
+            State { dealer: 1, player: 5 }, Hit
+        );
+        let mut accumulating = LinearFunctionApproximator::new(0.5, 0.5);
+        let mut replacing = LinearFunctionApproximator::new(0.5, 0.5)
+          .with_trace_type(TraceType::Replacing);
+        let mut dutch = LinearFunctionApproximator::new(0.5, 0.5)
+          .with_trace_type(TraceType::Dutch);
+
+        for alg in [&mut accumulating, &mut replacing, &mut dutch].iter_mut() {
+            alg.visit(&features);
+            alg.visit(&features);
+        }
+
+        assert_eq!(accumulating.traces[0], 2.0);
+        assert_eq!(replacing.traces[0], 1.0);
+
+        // The first visit sets both active traces to 1, so e.x is 2 and
+        // the second visit adds (1 - 0.5 * 2) = 0 to them.
+        assert_eq!(dutch.traces[0], 1.0);
+        assert_eq!(dutch.traces[2], 1.0);
+        assert_eq!(dutch.traces[1], 0.0);
+    }
 
     #[test]
-    fn test_to_feature_vector_works_with_one_cuboid() {
-        let fv = to_feature_vector(State { dealer: 1, player: 1 }, Hit);
-        let mut expected = vec![0.0; NUM_FEATURES];
-        expected[0] = 1.0;
+    fn test_true_online_updates_match_classic_ones_without_traces() {
+        let mut classic = LinearFunctionApproximator::new(0.0, 0.5);
+        let mut true_online = LinearFunctionApproximator::new(0.0, 0.5)
+          .with_true_online_updates();
+        let state = State { dealer: 1, player: 5 };
+        let next = State { dealer: 1, player: 8 };
+
+        for alg in [&mut classic, &mut true_online].iter_mut() {
+            alg.on_episode_begin();
+            alg.on_episode_step(state, Hit, 1.0, next, Some(Hit));
+            alg.on_episode_step(next, Hit, -1.0, state, Some(Stick));
+        }
 
-        assert_eq!(fv.to_vec(), expected);
+        assert_eq!(classic.weights, true_online.weights);
     }
 
     #[test]
-    fn test_to_feature_vector_works_with_two_cuboids() {
-        let fv = to_feature_vector(State { dealer: 1, player: 5 }, Hit);
-        let mut expected = vec![0.0; NUM_FEATURES];
-        expected[0] = 1.0;
-        expected[2] = 1.0;
+    fn test_one_hot_features_match_tabular_sarsa() {
+        let config = Config {
+            step_size: Some(0.05),
+            trace_type: TraceType::Replacing,
+            discount: 0.9,
+            ..Config::default()
+        };
+        let lfa = shortcuts::lfa(0.5, &config).with_features(OneHotFeatures);
+        let lfa = shortcuts::run_easy21(1000, lfa, &config);
+        let sarsa = shortcuts::run_sarsa(1000, 0.5, &config);
+        let mut learned = false;
+
+        for dealer in 1..11 {
+            for player in 1..22 {
+                let state = State { dealer, player };
+                for &action in [Hit, Stick].iter() {
+                    let value = sarsa.policy.alg.get_expected_reward(state,
+                                                                     action);
+                    assert_eq!(
+                        lfa.policy.alg.get_expected_reward(state, action),
+                        value
+                    );
+                    learned = learned || value != 0.0;
+                }
+            }
+        }
 
-        assert_eq!(fv.to_vec(), expected);
+        // Make sure the values being compared aren't all still zero.
+        assert!(learned);
     }
 
     #[test]
     fn test_dot_product_works() {
-        let features = [1.0; NUM_FEATURES];
-        let mut weights = [2.0; NUM_FEATURES];
+        let features = (0..NUM_FEATURES).map(|i| (i, 1.0)).collect();
+        let mut weights = vec![2.0; NUM_FEATURES];
 
-        assert_eq!(dot_product(features, weights), 2.0 * NUM_FEATURES as f32);
+        assert_eq!(dot_product(&features, &weights), 2.0 * NUM_FEATURES as f32);
 
         weights[0] = 1.0;
 
-        assert_eq!(dot_product(features, weights), 2.0 * NUM_FEATURES as f32 - 1.0);
+        assert_eq!(dot_product(&features, &weights), 2.0 * NUM_FEATURES as f32 - 1.0);
 
         weights[0] = 0.0;
 
-        assert_eq!(dot_product(features, weights), 2.0 * NUM_FEATURES as f32 - 2.0);
+        assert_eq!(dot_product(&features, &weights), 2.0 * NUM_FEATURES as f32 - 2.0);
+
+        let sparse = vec![(1, 0.5), (3, 2.0)];
+
+        assert_eq!(dot_product(&sparse, &weights), 5.0);
+        assert_eq!(feature_value(&sparse, 3), 2.0);
+        assert_eq!(feature_value(&sparse, 2), 0.0);
     }
 }
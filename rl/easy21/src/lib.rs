@@ -1,13 +1,29 @@
 extern crate rand;
 extern crate libc;
 
+pub mod environment;
 pub mod game;
+pub mod blackjack;
 pub mod gpi;
+pub mod exploration;
+pub mod traces;
 pub mod montecarlo;
+pub mod off_policy_montecarlo;
 pub mod sarsa;
 pub mod qlearning;
+pub mod expected_sarsa;
+pub mod double_qlearning;
+pub mod nstep;
+pub mod features;
 pub mod lfa;
+pub mod mlp;
+pub mod dqn;
+pub mod reinforce;
+pub mod dp;
+pub mod evaluation;
+pub mod checkpoint;
 pub mod shortcuts;
+pub mod sweep;
 pub mod validators;
 pub mod c_api;
 
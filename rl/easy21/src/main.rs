@@ -3,53 +3,499 @@ extern crate clap;
 
 extern crate easy21;
 
+use std::fs::File;
+
 use clap::{App, Arg, ArgMatches, SubCommand};
 
-use easy21::gpi::Alg;
+use easy21::game::{Easy21, Rules, MAX_CARD, MAX_SUM};
+use easy21::blackjack;
+use easy21::blackjack::Blackjack;
+use easy21::gpi::{self, Alg, AlgPolicy, Policy, Gpi};
+use easy21::dp::ValueIteration;
+use easy21::evaluation::{compare, LearningCurve};
+use easy21::checkpoint::{Checkpoint, Checkpointable};
 use easy21::shortcuts;
+use easy21::shortcuts::Config;
+use easy21::sweep::{self, SweepAlg, SweepGrid};
+use easy21::traces::{TraceType, TRACE_TYPE_NAMES};
+use easy21::features::{Features, TileCoding, PolynomialFeatures,
+                       FEATURE_NAMES, DEFAULT_NUM_TILINGS,
+                       DEFAULT_DEALER_TILE_WIDTH, DEFAULT_PLAYER_TILE_WIDTH,
+                       DEFAULT_DEGREE};
+use easy21::mlp::{Activation, NetworkShape, ACTIVATION_NAMES,
+                  DEFAULT_HIDDEN_LAYERS, DEFAULT_WIDTH};
+use easy21::dqn::{ReplaySettings, DEFAULT_BUFFER_SIZE, DEFAULT_BATCH_SIZE,
+                  DEFAULT_SYNC_INTERVAL};
+use easy21::off_policy_montecarlo::Sampling;
+use easy21::nstep::NStep;
 use easy21::validators;
 
-fn run_monte_carlo(episodes: i32) {
+struct CurveArgs {
+    filename: String,
+    interval: i32,
+}
+
+enum Game {
+    Easy21,
+    Blackjack,
+}
+
+#[derive(Clone, Copy)]
+enum PolicyType {
+    EpsilonGreedy,
+    Boltzmann,
+    Ucb,
+}
+
+struct TrainArgs {
+    curve: Option<CurveArgs>,
+    load: Option<String>,
+    save: Option<String>,
+    compare: bool,
+
+    // The policy that chooses actions while training.
+    policy: PolicyType,
+
+    // The rules and discount used to compute the optimal values we
+    // compare against.
+    rules: Rules,
+    discount: f32,
+}
+
+fn exit_with_error(msg: String) -> ! {
+    eprintln!("error: {}", msg);
+    std::process::exit(1);
+}
+
+fn load_checkpoint<P: Policy + Checkpointable>(
+    gpi: &mut Gpi<Easy21, P>,
+    filename: &str
+) {
+    let result = Checkpoint::load_from_file(filename)
+        .and_then(|checkpoint| gpi.load(&checkpoint));
+    if let Err(err) = result {
+        exit_with_error(format!("Unable to load {}: {}", filename, err));
+    }
+    println!("Resuming from {} after {} episodes.", filename,
+             gpi.episodes());
+}
+
+fn save_checkpoint<P: Policy + Checkpointable>(
+    gpi: &Gpi<Easy21, P>,
+    filename: &str
+) {
+    let mut checkpoint = Checkpoint::new();
+    gpi.save(&mut checkpoint);
+    if let Err(err) = checkpoint.save_to_file(filename) {
+        exit_with_error(format!("Unable to write {}: {}", filename, err));
+    }
+    println!("Saved checkpoint to {}.", filename);
+}
+
+fn train<P: AlgPolicy + Checkpointable>(
+    gpi: &mut Gpi<Easy21, P>,
+    episodes: i32,
+    train_args: TrainArgs
+) {
+    if let Some(filename) = train_args.load {
+        load_checkpoint(gpi, &filename);
+    }
+
+    match train_args.curve {
+        None => {
+            gpi.play_episodes(episodes);
+        },
+        Some(curve_args) => {
+            let reference = ValueIteration::with_rules_and_discount(
+                &train_args.rules, train_args.discount
+            );
+            let mut curve = LearningCurve::new(&reference);
+
+            gpi.play_episodes_with_observer(episodes, curve_args.interval,
+                                            |gpi| {
+                curve.record(gpi.episodes(), gpi.policy.alg());
+            });
+
+            let result = File::create(&curve_args.filename)
+                .and_then(|mut file| curve.write_csv(&mut file));
+            if let Err(err) = result {
+                exit_with_error(format!("Unable to write {}: {}",
+                                        curve_args.filename, err));
+            }
+            println!("Wrote learning curve to {}.", curve_args.filename);
+        }
+    }
+
+    if let Some(filename) = train_args.save {
+        save_checkpoint(gpi, &filename);
+    }
+
+    if train_args.compare {
+        let reference = ValueIteration::with_rules_and_discount(
+            &train_args.rules, train_args.discount
+        );
+        print_comparison("Learned values", gpi.policy.alg(), &reference);
+    }
+}
+
+// Trains the algorithm on Easy21 with the policy given by `train_args`,
+// returning it afterwards.
+fn train_easy21<V: Alg + Checkpointable>(
+    alg: V,
+    episodes: i32,
+    config: &Config,
+    train_args: TrainArgs
+) -> V {
+    match train_args.policy {
+        PolicyType::EpsilonGreedy => {
+            let mut gpi = shortcuts::run_easy21(0, alg, config);
+            train(&mut gpi, episodes, train_args);
+            gpi.policy.alg
+        },
+        PolicyType::Boltzmann => {
+            let mut gpi = shortcuts::run_easy21_boltzmann(0, alg, config);
+            train(&mut gpi, episodes, train_args);
+            gpi.policy.alg
+        },
+        PolicyType::Ucb => {
+            let mut gpi = shortcuts::run_easy21_ucb(0, alg, config);
+            train(&mut gpi, episodes, train_args);
+            gpi.policy.alg
+        },
+    }
+}
+
+fn print_comparison<T: Alg>(label: &str, alg: &T,
+                            reference: &ValueIteration) {
+    let stats = compare(alg, reference);
+    println!("{} compared to optimal values: mean error={:.6}, \
+              mean squared error={:.6}, max abs error={:.6}",
+             label, stats.mean_error, stats.mean_squared_error,
+             stats.max_abs_error);
+}
+
+// Learning curves and checkpoints are currently specific to Easy21.
+fn train_blackjack<T: Policy<Blackjack>>(
+    gpi: &mut Gpi<Blackjack, T>,
+    episodes: i32,
+    train_args: TrainArgs
+) {
+    if train_args.curve.is_some() || train_args.load.is_some() ||
+       train_args.save.is_some() || train_args.compare {
+        exit_with_error(String::from(
+            "--curve, --load, --save and --compare are only supported for \
+             easy21."
+        ));
+    }
+
+    gpi.play_episodes(episodes);
+}
+
+// Like train_easy21(), but for Blackjack.
+fn train_blackjack_alg<V: Alg<Blackjack>>(
+    alg: V,
+    episodes: i32,
+    config: &Config,
+    train_args: TrainArgs
+) -> V {
+    match train_args.policy {
+        PolicyType::EpsilonGreedy => {
+            let mut gpi = shortcuts::run_blackjack(0, alg, config);
+            train_blackjack(&mut gpi, episodes, train_args);
+            gpi.policy.alg
+        },
+        PolicyType::Boltzmann => {
+            let mut gpi = shortcuts::run_blackjack_boltzmann(0, alg, config);
+            train_blackjack(&mut gpi, episodes, train_args);
+            gpi.policy.alg
+        },
+        PolicyType::Ucb => {
+            let mut gpi = shortcuts::run_blackjack_ucb(0, alg, config);
+            train_blackjack(&mut gpi, episodes, train_args);
+            gpi.policy.alg
+        },
+    }
+}
+
+fn run_monte_carlo(episodes: i32, game: Game, config: &Config,
+                   train_args: TrainArgs) {
     println!("Performing GPI over {} episodes using Monte Carlo...",
              episodes);
 
-    let gpi = shortcuts::run_monte_carlo(episodes);
+    match game {
+        Game::Easy21 => {
+            let alg = shortcuts::monte_carlo(config);
+            train_easy21(alg, episodes, config, train_args)
+              .print_optimal_values();
+        },
+        Game::Blackjack => {
+            let alg = shortcuts::monte_carlo(config);
+            let alg = train_blackjack_alg(alg, episodes, config, train_args);
+            blackjack::print_optimal_values(&alg);
+        }
+    }
+}
+
+fn run_off_policy_monte_carlo(episodes: i32, sampling: Sampling, game: Game,
+                              config: &Config, train_args: TrainArgs) {
+    println!(
+        "Performing GPI over {} episodes using off-policy Monte Carlo with \
+         {} importance sampling...",
+        episodes,
+        sampling.name()
+    );
 
-    gpi.policy.alg.print_optimal_values();
+    match game {
+        Game::Easy21 => {
+            let alg = shortcuts::off_policy_monte_carlo(sampling, config);
+            train_easy21(alg, episodes, config, train_args)
+              .print_optimal_values();
+        },
+        Game::Blackjack => {
+            let alg = shortcuts::off_policy_monte_carlo(sampling, config);
+            let alg = train_blackjack_alg(alg, episodes, config, train_args);
+            blackjack::print_optimal_values(&alg);
+        }
+    }
 }
 
-fn run_sarsa(episodes: i32, lambda: f32) {
+fn run_sarsa(episodes: i32, lambda: f32, game: Game, config: &Config,
+             train_args: TrainArgs) {
     println!(
         "Performing GPI over {} episodes using Sarsa with lambda={}...",
         episodes,
         lambda
     );
 
-    let gpi = shortcuts::run_sarsa(episodes, lambda);
+    match game {
+        Game::Easy21 => {
+            let alg = shortcuts::sarsa(lambda, config);
+            train_easy21(alg, episodes, config, train_args)
+              .print_optimal_values();
+        },
+        Game::Blackjack => {
+            let alg = shortcuts::sarsa(lambda, config);
+            let alg = train_blackjack_alg(alg, episodes, config, train_args);
+            blackjack::print_optimal_values(&alg);
+        }
+    }
+}
+
+fn run_q_learning(episodes: i32, lambda: f32, naive: bool, game: Game,
+                  config: &Config, train_args: TrainArgs) {
+    println!(
+        "Performing GPI over {} episodes using {} Q-learning with \
+         lambda={}...",
+        episodes,
+        if naive { "naive" } else { "Watkins's" },
+        lambda
+    );
 
-    gpi.policy.alg.print_optimal_values();
+    match game {
+        Game::Easy21 => {
+            let mut alg = shortcuts::q_learning(lambda, config);
+            if naive {
+                alg = alg.with_naive_traces();
+            }
+            train_easy21(alg, episodes, config, train_args)
+              .print_optimal_values();
+        },
+        Game::Blackjack => {
+            let mut alg = shortcuts::q_learning(lambda, config);
+            if naive {
+                alg = alg.with_naive_traces();
+            }
+            let alg = train_blackjack_alg(alg, episodes, config, train_args);
+            blackjack::print_optimal_values(&alg);
+        }
+    }
 }
 
-fn run_lfa(episodes: i32, lambda: f32) {
+fn run_expected_sarsa(episodes: i32, lambda: f32, game: Game,
+                      config: &Config, train_args: TrainArgs) {
     println!(
-        "Performing GPI over {} episodes using LFA with lambda={}...",
+        "Performing GPI over {} episodes using Expected Sarsa with \
+         lambda={}...",
         episodes,
         lambda
     );
 
-    let epsilon = 0.05;
-    let step_size = 0.01;
-    let gpi = shortcuts::run_lfa(episodes, lambda, epsilon, step_size);
+    match game {
+        Game::Easy21 => {
+            let alg = shortcuts::expected_sarsa(lambda, config);
+            train_easy21(alg, episodes, config, train_args)
+              .print_optimal_values();
+        },
+        Game::Blackjack => {
+            let alg = shortcuts::expected_sarsa(lambda, config);
+            let alg = train_blackjack_alg(alg, episodes, config, train_args);
+            blackjack::print_optimal_values(&alg);
+        }
+    }
+}
+
+fn run_n_step(episodes: i32, n: usize, tree_backup: bool, game: Game,
+              config: &Config, train_args: TrainArgs) {
+    println!(
+        "Performing GPI over {} episodes using {} with n={}...",
+        episodes,
+        if tree_backup { "n-step Tree Backup" } else { "n-step Sarsa" },
+        n
+    );
 
-    gpi.policy.alg.print_optimal_values();
+    match game {
+        Game::Easy21 => {
+            let alg = if tree_backup {
+                NStep::tree_backup(n)
+            } else {
+                NStep::sarsa(n)
+            };
+            let alg = shortcuts::n_step(alg, config);
+            train_easy21(alg, episodes, config, train_args)
+              .print_optimal_values();
+        },
+        Game::Blackjack => {
+            let alg = if tree_backup {
+                NStep::tree_backup(n)
+            } else {
+                NStep::sarsa(n)
+            };
+            let alg = shortcuts::n_step(alg, config);
+            let alg = train_blackjack_alg(alg, episodes, config, train_args);
+            blackjack::print_optimal_values(&alg);
+        }
+    }
+}
+
+fn run_double_q_learning(episodes: i32, game: Game, config: &Config,
+                         train_args: TrainArgs) {
+    println!("Performing GPI over {} episodes using Double Q-learning...",
+             episodes);
+
+    match game {
+        Game::Easy21 => {
+            let reference = if train_args.compare {
+                Some(ValueIteration::with_rules_and_discount(
+                    &train_args.rules, train_args.discount
+                ))
+            } else {
+                None
+            };
+            let alg = shortcuts::double_q_learning(config);
+            let alg = train_easy21(alg, episodes, config, train_args);
+            if let Some(reference) = reference {
+                print_comparison("First estimate", &alg.first_estimate(),
+                                 &reference);
+                print_comparison("Second estimate", &alg.second_estimate(),
+                                 &reference);
+            }
+            alg.print_optimal_values();
+        },
+        Game::Blackjack => {
+            let alg = shortcuts::double_q_learning(config);
+            let alg = train_blackjack_alg(alg, episodes, config, train_args);
+            blackjack::print_optimal_values(&alg);
+        }
+    }
+}
+
+fn run_lfa(episodes: i32, lambda: f32, true_online: bool, config: &Config,
+           train_args: TrainArgs) {
+    println!(
+        "Performing GPI over {} episodes using {}LFA with lambda={}...",
+        episodes,
+        if true_online { "true online " } else { "" },
+        lambda
+    );
+
+    let mut alg = shortcuts::lfa(lambda, config);
+    if true_online {
+        alg = alg.with_true_online_updates();
+    }
+
+    train_easy21(alg, episodes, config, train_args).print_optimal_values();
+}
+
+fn run_mlp(episodes: i32, q_learning: bool, config: &Config,
+           train_args: TrainArgs) {
+    println!(
+        "Performing GPI over {} episodes using a neural network trained by \
+         {}...",
+        episodes,
+        if q_learning { "Q-learning" } else { "Sarsa" }
+    );
+
+    let mut alg = shortcuts::mlp(config);
+    if q_learning {
+        alg = alg.with_q_learning();
+    }
+
+    train_easy21(alg, episodes, config, train_args).print_optimal_values();
+}
+
+fn run_dqn(episodes: i32, config: &Config, train_args: TrainArgs) {
+    println!(
+        "Performing GPI over {} episodes using DQN with batches of {} from \
+         the last {} steps...",
+        episodes, config.replay.batch_size, config.replay.buffer_size
+    );
+
+    train_easy21(shortcuts::dqn(config), episodes, config, train_args)
+      .print_optimal_values();
+}
+
+fn run_reinforce(episodes: i32, config: &Config, load: Option<String>,
+                 save: Option<String>) {
+    println!(
+        "Performing {} episodes of REINFORCE {}...",
+        episodes,
+        if config.baseline_step_size.is_some() {
+            "with a baseline"
+        } else {
+            "without a baseline"
+        }
+    );
+
+    let mut gpi = shortcuts::run_reinforce(0, config);
+    if let Some(filename) = load {
+        load_checkpoint(&mut gpi, &filename);
+    }
+    gpi.play_episodes(episodes);
+    if let Some(filename) = save {
+        save_checkpoint(&gpi, &filename);
+    }
+
+    println!("Learned probabilities of hitting (%):");
+    gpi::print_state_values(|state| {
+        gpi.policy.action_probabilities(state)[0]
+    });
+}
+
+fn run_dp(rules: &Rules, discount: f32) {
+    println!("Computing optimal values via value iteration...");
+
+    let alg = ValueIteration::with_rules_and_discount(rules, discount);
+
+    alg.print_optimal_values();
+}
+
+fn run_sweep(grid: &SweepGrid, threads: usize) {
+    println!("Sweeping {} settings over {} episodes each using {} threads...",
+             grid.jobs().len(), grid.episodes, threads);
+
+    let results = sweep::run_sweep(grid, threads);
+    let stdout = std::io::stdout();
+    if let Err(err) = sweep::write_table(&results, &mut stdout.lock()) {
+        exit_with_error(format!("Unable to write results: {}", err));
+    }
 }
 
 fn validate_episodes(v: String) -> Result<(), String> {
-    return if validators::episodes(v.parse::<i32>().unwrap_or(-1)) {
+    if validators::episodes(v.parse::<i32>().unwrap_or(-1)) {
         Ok(())
     } else {
         Err(String::from("Episodes must be a number greater than 0."))
-    };
+    }
 }
 
 fn get_episodes(m: &ArgMatches) -> i32 {
@@ -57,7 +503,7 @@ fn get_episodes(m: &ArgMatches) -> i32 {
 }
 
 fn validate_lambda(v: String) -> Result<(), String> {
-    return if validators::lambda(v.parse::<f32>().unwrap_or(-1.0)) {
+    if validators::lambda(v.parse::<f32>().unwrap_or(-1.0)) {
         Ok(())
     } else {
         Err(String::from("Lambda must be a float between 0 and 1."))
@@ -68,6 +514,451 @@ fn get_lambda(m: &ArgMatches) -> f32 {
     m.value_of("lambda").unwrap().parse::<f32>().unwrap()
 }
 
+fn validate_n(v: String) -> Result<(), String> {
+    if validators::n(v.parse::<i32>().unwrap_or(-1)) {
+        Ok(())
+    } else {
+        Err(String::from("N must be a number greater than 0."))
+    }
+}
+
+fn get_n(m: &ArgMatches) -> usize {
+    m.value_of("n").unwrap().parse::<usize>().unwrap()
+}
+
+fn validate_discount(v: String) -> Result<(), String> {
+    if validators::discount(v.parse::<f32>().unwrap_or(-1.0)) {
+        Ok(())
+    } else {
+        Err(String::from("Discount must be a float between 0 and 1."))
+    }
+}
+
+fn get_discount(m: &ArgMatches) -> f32 {
+    m.value_of("discount").unwrap().parse::<f32>().unwrap()
+}
+
+fn validate_epsilon(v: String) -> Result<(), String> {
+    if validators::epsilon(v.parse::<f32>().unwrap_or(-1.0)) {
+        Ok(())
+    } else {
+        Err(String::from("Epsilon must be a float between 0 and 1."))
+    }
+}
+
+fn get_epsilon(m: &ArgMatches) -> Option<f32> {
+    m.value_of("epsilon").map(|v| v.parse::<f32>().unwrap())
+}
+
+fn validate_step_size(v: String) -> Result<(), String> {
+    if validators::step_size(v.parse::<f32>().unwrap_or(-1.0)) {
+        Ok(())
+    } else {
+        Err(String::from("Step size must be a float greater than 0."))
+    }
+}
+
+fn get_step_size(m: &ArgMatches) -> Option<f32> {
+    m.value_of("step_size").map(|v| v.parse::<f32>().unwrap())
+}
+
+fn get_game(m: &ArgMatches) -> Game {
+    match m.value_of("game").unwrap() {
+        "blackjack" => Game::Blackjack,
+        _ => Game::Easy21,
+    }
+}
+
+fn get_sampling(m: &ArgMatches) -> Sampling {
+    Sampling::from_name(m.value_of("sampling").unwrap()).unwrap()
+}
+
+fn get_policy_type(m: &ArgMatches) -> PolicyType {
+    match m.value_of("policy").unwrap() {
+        "boltzmann" => PolicyType::Boltzmann,
+        "ucb" => PolicyType::Ucb,
+        _ => PolicyType::EpsilonGreedy,
+    }
+}
+
+fn validate_temperature(v: String) -> Result<(), String> {
+    if validators::temperature(v.parse::<f32>().unwrap_or(0.0)) {
+        Ok(())
+    } else {
+        Err(String::from("Temperature must be a positive float."))
+    }
+}
+
+fn validate_temperature_decay(v: String) -> Result<(), String> {
+    if validators::temperature_decay(v.parse::<f32>().unwrap_or(0.0)) {
+        Ok(())
+    } else {
+        Err(String::from("Temperature decay must be a float greater than 0 \
+                          and at most 1."))
+    }
+}
+
+fn validate_ucb_c(v: String) -> Result<(), String> {
+    if validators::ucb_c(v.parse::<f32>().unwrap_or(-1.0)) {
+        Ok(())
+    } else {
+        Err(String::from("UCB c must be a non-negative float."))
+    }
+}
+
+fn get_float(m: &ArgMatches, name: &str) -> f32 {
+    m.value_of(name).unwrap().parse::<f32>().unwrap()
+}
+
+fn validate_n0(v: String) -> Result<(), String> {
+    if validators::n0(v.parse::<f32>().unwrap_or(-1.0)) {
+        Ok(())
+    } else {
+        Err(String::from("N0 must be a float greater than 0."))
+    }
+}
+
+fn get_n0(m: &ArgMatches) -> f32 {
+    m.value_of("n0").unwrap().parse::<f32>().unwrap()
+}
+
+fn parse_seed(v: &str) -> Option<Vec<usize>> {
+    v.split(',').map(|part| part.trim().parse::<usize>().ok()).collect()
+}
+
+fn validate_seed(v: String) -> Result<(), String> {
+    if validators::seed(&parse_seed(&v).unwrap_or_default()) {
+        Ok(())
+    } else {
+        Err(String::from("Seed must be a comma-separated list of \
+                          non-negative integers."))
+    }
+}
+
+fn get_seed(m: &ArgMatches) -> Vec<usize> {
+    parse_seed(m.value_of("seed").unwrap()).unwrap()
+}
+
+fn validate_dealer_stick_min(v: String) -> Result<(), String> {
+    if validators::dealer_stick_min(v.parse::<i32>().unwrap_or(-1)) {
+        Ok(())
+    } else {
+        Err(format!("Dealer stick minimum must be a number from {} to {}.",
+                    MAX_CARD + 1, MAX_SUM))
+    }
+}
+
+fn validate_red_probability(v: String) -> Result<(), String> {
+    if validators::red_probability(v.parse::<f32>().unwrap_or(-1.0)) {
+        Ok(())
+    } else {
+        Err(String::from("Red probability must be a number from 0 to 1."))
+    }
+}
+
+fn get_rules(m: &ArgMatches) -> Rules {
+    let mut rules = Rules::default();
+    if let Some(v) = m.value_of("dealer_stick_min") {
+        rules.dealer_stick_min = v.parse::<i32>().unwrap();
+    }
+    if let Some(v) = m.value_of("red_probability") {
+        rules.red_probability = Some(v.parse::<f32>().unwrap());
+    }
+    rules
+}
+
+// Parses a comma-separated list, where "varying" stands for None.
+fn parse_list<T: std::str::FromStr>(v: &str) -> Option<Vec<Option<T>>> {
+    v.split(',').map(|part| match part.trim() {
+        "varying" => Some(None),
+        part => part.parse::<T>().ok().map(Some),
+    }).collect()
+}
+
+fn validate_list<T: std::str::FromStr, F: Fn(T) -> bool>(
+    v: &str,
+    allow_varying: bool,
+    is_valid: F
+) -> bool {
+    match parse_list::<T>(v) {
+        None => false,
+        Some(items) => items.into_iter().all(|item| match item {
+            None => allow_varying,
+            Some(item) => is_valid(item),
+        }),
+    }
+}
+
+fn get_list<T: std::str::FromStr>(m: &ArgMatches, name: &str) -> Vec<Option<T>> {
+    parse_list(m.value_of(name).unwrap()).unwrap()
+}
+
+fn validate_algs(v: String) -> Result<(), String> {
+    if v.split(',').all(|name| SweepAlg::from_name(name.trim()).is_some()) {
+        Ok(())
+    } else {
+        Err(String::from("Algorithms must be a comma-separated list of \
+                          mc, off-policy-mc, ordinary-off-policy-mc, sarsa, \
+                          qlearning, naive-qlearning, \
+                          expected-sarsa, double-qlearning, nstep-sarsa, \
+                          tree-backup, lfa and true-online-lfa."))
+    }
+}
+
+fn validate_lambdas(v: String) -> Result<(), String> {
+    if validate_list(&v, false, validators::lambda) {
+        Ok(())
+    } else {
+        Err(String::from("Lambdas must be a comma-separated list of \
+                          numbers from 0 to 1."))
+    }
+}
+
+fn validate_ns(v: String) -> Result<(), String> {
+    if validate_list(&v, false, validators::n) {
+        Ok(())
+    } else {
+        Err(String::from("N settings must be a comma-separated list of \
+                          numbers greater than 0."))
+    }
+}
+
+fn validate_epsilons(v: String) -> Result<(), String> {
+    if validate_list(&v, true, validators::epsilon) {
+        Ok(())
+    } else {
+        Err(String::from("Epsilons must be a comma-separated list of \
+                          'varying' or numbers from 0 to 1."))
+    }
+}
+
+fn validate_step_sizes(v: String) -> Result<(), String> {
+    if validate_list(&v, true, validators::step_size) {
+        Ok(())
+    } else {
+        Err(String::from("Step sizes must be a comma-separated list of \
+                          'varying' or numbers greater than 0."))
+    }
+}
+
+fn validate_seeds(v: String) -> Result<(), String> {
+    if validate_list(&v, false, |seed: usize| validators::seed(&[seed])) {
+        Ok(())
+    } else {
+        Err(String::from("Seeds must be a comma-separated list of \
+                          non-negative integers."))
+    }
+}
+
+fn validate_threads(v: String) -> Result<(), String> {
+    if v.parse::<usize>().unwrap_or(0) > 0 {
+        Ok(())
+    } else {
+        Err(String::from("Threads must be a number greater than 0."))
+    }
+}
+
+fn get_threads(m: &ArgMatches) -> usize {
+    m.value_of("threads").unwrap().parse::<usize>().unwrap()
+}
+
+fn get_sweep_grid(m: &ArgMatches) -> SweepGrid {
+    SweepGrid {
+        algs: m.value_of("algs").unwrap().split(',')
+          .map(|name| SweepAlg::from_name(name.trim()).unwrap())
+          .collect(),
+        lambdas: get_list(m, "lambdas").into_iter()
+          .map(Option::unwrap).collect(),
+        trace_types: m.value_of("trace_types").unwrap().split(',')
+          .map(|name| TraceType::from_name(name.trim()).unwrap())
+          .collect(),
+        ns: get_list(m, "ns").into_iter()
+          .map(|n: Option<usize>| n.unwrap()).collect(),
+        epsilons: get_list(m, "epsilons"),
+        step_sizes: get_list(m, "step_sizes"),
+        seeds: get_list(m, "seeds").into_iter()
+          .map(|seed| vec![seed.unwrap()]).collect(),
+        episodes: get_episodes(m),
+        n0: get_n0(m),
+        discount: get_discount(m),
+        rules: get_rules(m),
+    }
+}
+
+fn validate_trace_types(v: String) -> Result<(), String> {
+    if v.split(',').all(|name| TraceType::from_name(name.trim()).is_some()) {
+        Ok(())
+    } else {
+        Err(format!("Trace types must be a comma-separated list of {}.",
+                    TRACE_TYPE_NAMES.join(", ")))
+    }
+}
+
+fn get_trace_type(m: &ArgMatches) -> TraceType {
+    match m.value_of("traces") {
+        Some(name) => TraceType::from_name(name).unwrap(),
+        None => TraceType::default(),
+    }
+}
+
+fn validate_tilings(v: String) -> Result<(), String> {
+    if validators::tilings(v.parse::<i32>().unwrap_or(-1)) {
+        Ok(())
+    } else {
+        Err(String::from("Tilings must be a number greater than 0."))
+    }
+}
+
+fn validate_tile_width(v: String) -> Result<(), String> {
+    if validators::tile_width(v.parse::<i32>().unwrap_or(-1)) {
+        Ok(())
+    } else {
+        Err(String::from("Tile width must be a number greater than 0."))
+    }
+}
+
+fn validate_degree(v: String) -> Result<(), String> {
+    if validators::degree(v.parse::<i32>().unwrap_or(-1)) {
+        Ok(())
+    } else {
+        Err(String::from("Degree must be a number greater than 0."))
+    }
+}
+
+fn get_integer(m: &ArgMatches, name: &str, default: i32) -> i32 {
+    m.value_of(name).map_or(default, |v| v.parse::<i32>().unwrap())
+}
+
+fn get_features(m: &ArgMatches) -> Features {
+    match m.value_of("features").and_then(Features::from_name) {
+        Some(Features::Tiles(_)) => Features::Tiles(TileCoding::new(
+            get_integer(m, "tilings", DEFAULT_NUM_TILINGS as i32) as usize,
+            get_integer(m, "dealer_tile_width", DEFAULT_DEALER_TILE_WIDTH),
+            get_integer(m, "player_tile_width", DEFAULT_PLAYER_TILE_WIDTH)
+        )),
+        Some(Features::Polynomial(_)) => Features::Polynomial(
+            PolynomialFeatures::new(
+                get_integer(m, "degree", DEFAULT_DEGREE as i32) as usize
+            )
+        ),
+        Some(features) => features,
+        None => Features::default(),
+    }
+}
+
+fn validate_hidden_layers(v: String) -> Result<(), String> {
+    if validators::hidden_layers(v.parse::<i32>().unwrap_or(-1)) {
+        Ok(())
+    } else {
+        Err(String::from("Hidden layers must be 1 or 2."))
+    }
+}
+
+fn validate_width(v: String) -> Result<(), String> {
+    if validators::width(v.parse::<i32>().unwrap_or(-1)) {
+        Ok(())
+    } else {
+        Err(String::from("Width must be a number greater than 0."))
+    }
+}
+
+fn get_network(m: &ArgMatches) -> NetworkShape {
+    NetworkShape {
+        hidden_layers: get_integer(m, "hidden_layers",
+                                   DEFAULT_HIDDEN_LAYERS as i32) as usize,
+        width: get_integer(m, "width", DEFAULT_WIDTH as i32) as usize,
+        activation: m.value_of("activation").and_then(Activation::from_name)
+          .unwrap_or_default(),
+    }
+}
+
+fn validate_buffer_size(v: String) -> Result<(), String> {
+    if validators::buffer_size(v.parse::<i32>().unwrap_or(-1)) {
+        Ok(())
+    } else {
+        Err(String::from("Buffer size must be a number greater than 0."))
+    }
+}
+
+fn validate_batch_size(v: String) -> Result<(), String> {
+    if validators::batch_size(v.parse::<i32>().unwrap_or(-1)) {
+        Ok(())
+    } else {
+        Err(String::from("Batch size must be a number greater than 0."))
+    }
+}
+
+fn validate_sync_interval(v: String) -> Result<(), String> {
+    if validators::sync_interval(v.parse::<i32>().unwrap_or(-1)) {
+        Ok(())
+    } else {
+        Err(String::from("Sync interval must be a number greater than 0."))
+    }
+}
+
+fn get_replay(m: &ArgMatches) -> ReplaySettings {
+    ReplaySettings {
+        buffer_size: get_integer(m, "buffer_size",
+                                 DEFAULT_BUFFER_SIZE as i32) as usize,
+        batch_size: get_integer(m, "batch_size",
+                                DEFAULT_BATCH_SIZE as i32) as usize,
+        sync_interval: get_integer(m, "sync_interval",
+                                   DEFAULT_SYNC_INTERVAL as i32) as usize,
+    }
+}
+
+fn get_config(m: &ArgMatches) -> Config {
+    Config {
+        seed: get_seed(m),
+        epsilon: get_epsilon(m),
+        n0: get_n0(m),
+        step_size: get_step_size(m),
+        trace_type: get_trace_type(m),
+        discount: get_discount(m),
+        every_visit: m.is_present("every_visit"),
+        exploring_starts: m.is_present("exploring_starts"),
+        temperature: get_float(m, "temperature"),
+        temperature_decay: get_float(m, "temperature_decay"),
+        ucb_c: get_float(m, "ucb_c"),
+        features: get_features(m),
+        network: get_network(m),
+        replay: get_replay(m),
+        baseline_step_size: None,
+        rules: get_rules(m),
+    }
+}
+
+// REINFORCE has its own policy, so it only needs some of the settings
+// that get_config() reads.
+fn get_reinforce_config(m: &ArgMatches) -> Config {
+    Config {
+        seed: get_seed(m),
+        step_size: get_step_size(m),
+        discount: get_discount(m),
+        features: get_features(m),
+        baseline_step_size: m.value_of("baseline_step_size")
+          .map(|v| v.parse::<f32>().unwrap()),
+        rules: get_rules(m),
+        ..Config::default()
+    }
+}
+
+fn get_train_args(m: &ArgMatches) -> TrainArgs {
+    TrainArgs {
+        curve: m.value_of("curve").map(|filename| CurveArgs {
+            filename: String::from(filename),
+            interval: m.value_of("curve_interval").unwrap()
+              .parse::<i32>().unwrap(),
+        }),
+        load: m.value_of("load").map(String::from),
+        save: m.value_of("save").map(String::from),
+        compare: m.is_present("compare"),
+        policy: get_policy_type(m),
+        rules: get_rules(m),
+        discount: get_discount(m),
+    }
+}
+
 fn main() {
     let episodes_arg = Arg::with_name("episodes")
         .short("e")
@@ -85,26 +976,602 @@ fn main() {
         .takes_value(true)
         .validator(validate_lambda);
 
+    let n_arg = Arg::with_name("n")
+        .short("n")
+        .long("n")
+        .help("number of steps to look ahead")
+        .default_value("4")
+        .takes_value(true)
+        .validator(validate_n);
+
+    let discount_arg = Arg::with_name("discount")
+        .long("discount")
+        .help("discount factor (gamma) for future rewards")
+        .default_value("1")
+        .takes_value(true)
+        .validator(validate_discount);
+
+    let epsilon_arg = Arg::with_name("epsilon")
+        .long("epsilon")
+        .help("constant exploration rate (varies with visits if omitted)")
+        .takes_value(true)
+        .validator(validate_epsilon);
+
+    let step_size_arg = Arg::with_name("step_size")
+        .long("step-size")
+        .help("constant step size (varies with visits if omitted)")
+        .takes_value(true)
+        .validator(validate_step_size);
+
+    let traces_arg = Arg::with_name("traces")
+        .long("traces")
+        .help("kind of eligibility traces")
+        .possible_values(TRACE_TYPE_NAMES)
+        .default_value("accumulating")
+        .takes_value(true);
+
+    let policy_arg = Arg::with_name("policy")
+        .long("policy")
+        .help("how to explore while training")
+        .possible_values(&["epsilon-greedy", "boltzmann", "ucb"])
+        .default_value("epsilon-greedy")
+        .takes_value(true);
+
+    let temperature_arg = Arg::with_name("temperature")
+        .long("temperature")
+        .help("initial temperature of the boltzmann policy")
+        .default_value("1")
+        .takes_value(true)
+        .validator(validate_temperature);
+
+    let temperature_decay_arg = Arg::with_name("temperature_decay")
+        .long("temperature-decay")
+        .help("factor the boltzmann policy's temperature is multiplied by \
+               after every episode")
+        .default_value("1")
+        .takes_value(true)
+        .validator(validate_temperature_decay);
+
+    let ucb_c_arg = Arg::with_name("ucb_c")
+        .long("ucb-c")
+        .help("degree of exploration of the ucb policy")
+        .default_value("1")
+        .takes_value(true)
+        .validator(validate_ucb_c);
+
+    let feature_args = [
+        Arg::with_name("features")
+          .long("features")
+          .help("how to turn states into features")
+          .possible_values(FEATURE_NAMES)
+          .default_value("cuboids")
+          .takes_value(true),
+        Arg::with_name("tilings")
+          .long("tilings")
+          .help("number of tilings, if using tile features")
+          .takes_value(true)
+          .validator(validate_tilings),
+        Arg::with_name("dealer_tile_width")
+          .long("dealer-tile-width")
+          .help("number of dealer sums per tile, if using tile features")
+          .takes_value(true)
+          .validator(validate_tile_width),
+        Arg::with_name("player_tile_width")
+          .long("player-tile-width")
+          .help("number of player sums per tile, if using tile features")
+          .takes_value(true)
+          .validator(validate_tile_width),
+        Arg::with_name("degree")
+          .long("degree")
+          .help("highest power of the sums, if using polynomial features")
+          .takes_value(true)
+          .validator(validate_degree),
+    ];
+
+    let hidden_layers_arg = Arg::with_name("hidden_layers")
+        .long("hidden-layers")
+        .help("number of hidden layers")
+        .default_value("1")
+        .takes_value(true)
+        .validator(validate_hidden_layers);
+
+    let width_arg = Arg::with_name("width")
+        .long("width")
+        .help("number of units in each hidden layer")
+        .default_value("16")
+        .takes_value(true)
+        .validator(validate_width);
+
+    let activation_arg = Arg::with_name("activation")
+        .long("activation")
+        .help("activation function of the hidden layers")
+        .possible_values(ACTIVATION_NAMES)
+        .default_value("tanh")
+        .takes_value(true);
+
+    let game_arg = Arg::with_name("game")
+        .short("g")
+        .long("game")
+        .help("game to play")
+        .possible_values(&["easy21", "blackjack"])
+        .default_value("easy21")
+        .takes_value(true);
+
+    let dealer_stick_min_arg = Arg::with_name("dealer_stick_min")
+        .long("dealer-stick-min")
+        .help("easy21 rule: the dealer sticks on any sum of at least this")
+        .takes_value(true)
+        .validator(validate_dealer_stick_min);
+
+    let red_probability_arg = Arg::with_name("red_probability")
+        .long("red-probability")
+        .help("easy21 rule: the probability of drawing a red card")
+        .takes_value(true)
+        .validator(validate_red_probability);
+
+    let n0_arg = Arg::with_name("n0")
+        .long("n0")
+        .help("constant N0 for varying epsilon, i.e. N0 / (N0 + visits)")
+        .default_value("100")
+        .takes_value(true)
+        .validator(validate_n0);
+
+    let seed_arg = Arg::with_name("seed")
+        .long("seed")
+        .help("comma-separated random number generator seed")
+        .default_value("1,2,3,4")
+        .takes_value(true)
+        .validator(validate_seed);
+
+    let curve_arg = Arg::with_name("curve")
+        .long("curve")
+        .value_name("FILE")
+        .help("write a CSV learning curve comparing against optimal values")
+        .takes_value(true);
+
+    let curve_interval_arg = Arg::with_name("curve_interval")
+        .long("curve-interval")
+        .help("number of episodes between learning curve points")
+        .default_value("1000")
+        .takes_value(true)
+        .validator(validate_episodes);
+
+    let compare_arg = Arg::with_name("compare")
+        .long("compare")
+        .help("compare the learned values against optimal ones");
+
+    let load_arg = Arg::with_name("load")
+        .long("load")
+        .value_name("FILE")
+        .help("resume from a checkpoint saved with --save")
+        .takes_value(true);
+
+    let save_arg = Arg::with_name("save")
+        .long("save")
+        .value_name("FILE")
+        .help("save a checkpoint after training")
+        .takes_value(true);
+
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .to_string();
+
     let matches = App::new("easy21")
       .subcommand(SubCommand::with_name("mc")
         .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(Arg::with_name("every_visit")
+          .long("every-visit")
+          .help("update every visit to a state/action pair, not just the \
+                 first"))
+        .arg(Arg::with_name("exploring_starts")
+          .long("exploring-starts")
+          .help("start every episode at a random state and action"))
+        .arg(game_arg.clone())
+        .arg(epsilon_arg.clone())
+        .arg(step_size_arg.clone())
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone())
         .about("runs monte carlo control"))
+      .subcommand(SubCommand::with_name("off-policy-mc")
+        .about("runs off-policy monte carlo control")
+        .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(Arg::with_name("sampling")
+          .long("sampling")
+          .help("kind of importance sampling")
+          .possible_values(&["ordinary", "weighted"])
+          .default_value("weighted")
+          .takes_value(true))
+        .arg(game_arg.clone())
+        .arg(epsilon_arg.clone())
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone()))
       .subcommand(SubCommand::with_name("sarsa")
         .about("runs sarsa lambda control")
         .arg(episodes_arg.clone())
-        .arg(lambda_arg.clone()))
+        .arg(discount_arg.clone())
+        .arg(lambda_arg.clone())
+        .arg(traces_arg.clone())
+        .arg(game_arg.clone())
+        .arg(epsilon_arg.clone())
+        .arg(step_size_arg.clone())
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone()))
+      .subcommand(SubCommand::with_name("qlearning")
+        .about("runs q-learning lambda control")
+        .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(lambda_arg.clone())
+        .arg(traces_arg.clone())
+        .arg(Arg::with_name("naive")
+          .long("naive")
+          .help("never cut eligibility traces, as older versions did"))
+        .arg(game_arg.clone())
+        .arg(epsilon_arg.clone())
+        .arg(step_size_arg.clone())
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone()))
+      .subcommand(SubCommand::with_name("expected-sarsa")
+        .about("runs expected sarsa lambda control")
+        .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(lambda_arg.clone())
+        .arg(traces_arg.clone())
+        .arg(game_arg.clone())
+        .arg(epsilon_arg.clone())
+        .arg(step_size_arg.clone())
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone()))
+      .subcommand(SubCommand::with_name("double-qlearning")
+        .about("runs double q-learning control")
+        .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(game_arg.clone())
+        .arg(epsilon_arg.clone())
+        .arg(step_size_arg.clone())
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone()))
+      .subcommand(SubCommand::with_name("nstep-sarsa")
+        .about("runs n-step sarsa control")
+        .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(n_arg.clone())
+        .arg(game_arg.clone())
+        .arg(epsilon_arg.clone())
+        .arg(step_size_arg.clone())
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone()))
+      .subcommand(SubCommand::with_name("tree-backup")
+        .about("runs n-step tree backup control")
+        .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(n_arg.clone())
+        .arg(game_arg.clone())
+        .arg(epsilon_arg.clone())
+        .arg(step_size_arg.clone())
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone()))
       .subcommand(SubCommand::with_name("lfa")
         .about("runs linear function approximation control")
         .arg(episodes_arg.clone())
-        .arg(lambda_arg.clone()))
+        .arg(discount_arg.clone())
+        .arg(lambda_arg.clone())
+        .arg(traces_arg.clone())
+        .arg(Arg::with_name("true-online")
+          .long("true-online")
+          .help("use true online Sarsa(lambda), which ignores --traces"))
+        .args(&feature_args)
+        .arg(epsilon_arg.clone().default_value("0.05"))
+        .arg(step_size_arg.clone().default_value("0.01"))
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone()))
+      .subcommand(SubCommand::with_name("mlp")
+        .about("runs neural network function approximation control")
+        .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(Arg::with_name("q-learning")
+          .long("q-learning")
+          .help("train via Q-learning instead of Sarsa"))
+        .arg(hidden_layers_arg.clone())
+        .arg(width_arg.clone())
+        .arg(activation_arg.clone())
+        .arg(epsilon_arg.clone().default_value("0.05"))
+        .arg(step_size_arg.clone().default_value("0.01"))
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone()))
+      .subcommand(SubCommand::with_name("dqn")
+        .about("runs neural network Q-learning with experience replay")
+        .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(Arg::with_name("buffer_size")
+          .long("buffer-size")
+          .help("number of recent steps to replay")
+          .default_value("10000")
+          .takes_value(true)
+          .validator(validate_buffer_size))
+        .arg(Arg::with_name("batch_size")
+          .long("batch-size")
+          .help("number of replayed steps to train on after every step")
+          .default_value("32")
+          .takes_value(true)
+          .validator(validate_batch_size))
+        .arg(Arg::with_name("sync_interval")
+          .long("sync-interval")
+          .help("number of steps between syncs of the target network")
+          .default_value("500")
+          .takes_value(true)
+          .validator(validate_sync_interval))
+        .arg(hidden_layers_arg.clone())
+        .arg(width_arg.clone())
+        .arg(activation_arg.clone())
+        .arg(epsilon_arg.clone().default_value("0.05"))
+        .arg(step_size_arg.clone().default_value("0.01"))
+        .arg(n0_arg.clone())
+        .arg(seed_arg.clone())
+        .arg(policy_arg.clone())
+        .arg(temperature_arg.clone())
+        .arg(temperature_decay_arg.clone())
+        .arg(ucb_c_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(curve_arg.clone())
+        .arg(curve_interval_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone())
+        .arg(compare_arg.clone()))
+      .subcommand(SubCommand::with_name("reinforce")
+        .about("runs REINFORCE policy gradient control")
+        .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(step_size_arg.clone().default_value("0.01"))
+        .arg(Arg::with_name("baseline_step_size")
+          .long("baseline-step-size")
+          .help("learn a state-value baseline with this step size")
+          .takes_value(true)
+          .validator(validate_step_size))
+        .args(&feature_args)
+        .arg(seed_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone())
+        .arg(load_arg.clone())
+        .arg(save_arg.clone()))
+      .subcommand(SubCommand::with_name("sweep")
+        .about("runs a grid of settings in parallel and compares results")
+        .arg(episodes_arg.clone())
+        .arg(discount_arg.clone())
+        .arg(Arg::with_name("algs")
+          .long("algs")
+          .help("comma-separated algorithms (mc, off-policy-mc, \
+                 ordinary-off-policy-mc, sarsa, qlearning, \
+                 naive-qlearning, expected-sarsa, double-qlearning, \
+                 nstep-sarsa, tree-backup, lfa, true-online-lfa)")
+          .default_value("sarsa")
+          .takes_value(true)
+          .validator(validate_algs))
+        .arg(Arg::with_name("lambdas")
+          .long("lambdas")
+          .help("comma-separated lambda settings")
+          .default_value("0,0.1,0.2,0.3,0.4,0.5,0.6,0.7,0.8,0.9,1")
+          .takes_value(true)
+          .validator(validate_lambdas))
+        .arg(Arg::with_name("ns")
+          .long("ns")
+          .help("comma-separated n settings for the n-step learners")
+          .default_value("4")
+          .takes_value(true)
+          .validator(validate_ns))
+        .arg(Arg::with_name("trace_types")
+          .long("trace-types")
+          .help("comma-separated kinds of eligibility traces")
+          .default_value("accumulating")
+          .takes_value(true)
+          .validator(validate_trace_types))
+        .arg(Arg::with_name("epsilons")
+          .long("epsilons")
+          .help("comma-separated constant exploration rates or 'varying'")
+          .default_value("varying")
+          .takes_value(true)
+          .validator(validate_epsilons))
+        .arg(Arg::with_name("step_sizes")
+          .long("step-sizes")
+          .help("comma-separated constant step sizes or 'varying'")
+          .default_value("varying")
+          .takes_value(true)
+          .validator(validate_step_sizes))
+        .arg(Arg::with_name("seeds")
+          .long("seeds")
+          .help("comma-separated random number generator seeds")
+          .default_value("1")
+          .takes_value(true)
+          .validator(validate_seeds))
+        .arg(Arg::with_name("threads")
+          .long("threads")
+          .help("number of threads to run settings on")
+          .default_value(&threads)
+          .takes_value(true)
+          .validator(validate_threads))
+        .arg(n0_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone()))
+      .subcommand(SubCommand::with_name("dp")
+        .about("computes optimal values via dynamic programming")
+        .arg(discount_arg.clone())
+        .arg(dealer_stick_min_arg.clone())
+        .arg(red_probability_arg.clone()))
       .get_matches();
 
     if let Some(submatches) = matches.subcommand_matches("mc") {
-        run_monte_carlo(get_episodes(&submatches));
+        run_monte_carlo(get_episodes(submatches), get_game(submatches),
+                        &get_config(submatches), get_train_args(submatches));
+    } else if let Some(submatches) =
+                matches.subcommand_matches("off-policy-mc") {
+        run_off_policy_monte_carlo(get_episodes(submatches),
+                                   get_sampling(submatches),
+                                   get_game(submatches),
+                                   &get_config(submatches),
+                                   get_train_args(submatches));
     } else if let Some(submatches) = matches.subcommand_matches("sarsa") {
-        run_sarsa(get_episodes(&submatches), get_lambda(&submatches));
+        run_sarsa(get_episodes(submatches), get_lambda(submatches),
+                  get_game(submatches), &get_config(submatches),
+                  get_train_args(submatches));
+    } else if let Some(submatches) =
+                matches.subcommand_matches("qlearning") {
+        run_q_learning(get_episodes(submatches), get_lambda(submatches),
+                       submatches.is_present("naive"), get_game(submatches),
+                       &get_config(submatches), get_train_args(submatches));
+    } else if let Some(submatches) =
+                matches.subcommand_matches("expected-sarsa") {
+        run_expected_sarsa(get_episodes(submatches), get_lambda(submatches),
+                           get_game(submatches), &get_config(submatches),
+                           get_train_args(submatches));
+    } else if let Some(submatches) =
+                matches.subcommand_matches("double-qlearning") {
+        run_double_q_learning(get_episodes(submatches), get_game(submatches),
+                              &get_config(submatches),
+                              get_train_args(submatches));
+    } else if let Some(submatches) =
+                matches.subcommand_matches("nstep-sarsa") {
+        run_n_step(get_episodes(submatches), get_n(submatches), false,
+                   get_game(submatches), &get_config(submatches),
+                   get_train_args(submatches));
+    } else if let Some(submatches) =
+                matches.subcommand_matches("tree-backup") {
+        run_n_step(get_episodes(submatches), get_n(submatches), true,
+                   get_game(submatches), &get_config(submatches),
+                   get_train_args(submatches));
     } else if let Some(submatches) = matches.subcommand_matches("lfa") {
-        run_lfa(get_episodes(&submatches), get_lambda(&submatches));
+        run_lfa(get_episodes(submatches), get_lambda(submatches),
+                submatches.is_present("true-online"),
+                &get_config(submatches), get_train_args(submatches));
+    } else if let Some(submatches) = matches.subcommand_matches("mlp") {
+        run_mlp(get_episodes(submatches),
+                submatches.is_present("q-learning"),
+                &get_config(submatches), get_train_args(submatches));
+    } else if let Some(submatches) = matches.subcommand_matches("dqn") {
+        run_dqn(get_episodes(submatches), &get_config(submatches),
+                get_train_args(submatches));
+    } else if let Some(submatches) =
+                matches.subcommand_matches("reinforce") {
+        run_reinforce(get_episodes(submatches),
+                      &get_reinforce_config(submatches),
+                      submatches.value_of("load").map(String::from),
+                      submatches.value_of("save").map(String::from));
+    } else if let Some(submatches) = matches.subcommand_matches("sweep") {
+        run_sweep(&get_sweep_grid(submatches), get_threads(submatches));
+    } else if let Some(submatches) = matches.subcommand_matches("dp") {
+        run_dp(&get_rules(submatches), get_discount(submatches));
     } else {
         eprintln!("error: Invalid subcommand\n\n{}\n", matches.usage());
         eprintln!("For more information try --help");
@@ -0,0 +1,492 @@
+// This module contains a small multilayer perceptron that approximates
+// the value of every action at a state, as described in Section 9.7 of
+// Sutton & Barto.
+
+use rand::Rng;
+
+use game::{State, Action, Reward, Easy21, MIN_CARD, MAX_CARD, MIN_SUM,
+           MAX_SUM, NUM_ACTIONS};
+use environment::Environment;
+use gpi::Alg;
+use util::best_action;
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+
+pub const DEFAULT_STEP_SIZE: f32 = 0.01;
+
+pub const DEFAULT_HIDDEN_LAYERS: usize = 1;
+
+pub const MAX_HIDDEN_LAYERS: usize = 2;
+
+pub const DEFAULT_WIDTH: usize = 16;
+
+// The dealer and player sums.
+pub const NUM_INPUTS: usize = 2;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Activation {
+    #[default]
+    Tanh,
+    Relu,
+    Sigmoid,
+}
+
+pub const ACTIVATION_NAMES: &[&str] = &["tanh", "relu", "sigmoid"];
+
+impl Activation {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Activation::Tanh => ACTIVATION_NAMES[0],
+            Activation::Relu => ACTIVATION_NAMES[1],
+            Activation::Sigmoid => ACTIVATION_NAMES[2],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tanh" => Some(Activation::Tanh),
+            "relu" => Some(Activation::Relu),
+            "sigmoid" => Some(Activation::Sigmoid),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, x: f32) -> f32 {
+        match *self {
+            Activation::Tanh => x.tanh(),
+            Activation::Relu => x.max(0.0),
+            Activation::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+        }
+    }
+
+    // Returns the derivative of the activation, given its output.
+    fn derivative(&self, y: f32) -> f32 {
+        match *self {
+            Activation::Tanh => 1.0 - y * y,
+            Activation::Relu => if y > 0.0 { 1.0 } else { 0.0 },
+            Activation::Sigmoid => y * (1.0 - y),
+        }
+    }
+}
+
+// The shape of a network: how many hidden layers it has, how many units
+// each of them has, and their activation. The output layer is linear.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NetworkShape {
+    pub hidden_layers: usize,
+    pub width: usize,
+    pub activation: Activation,
+}
+
+impl NetworkShape {
+    // Returns how many weights and biases a network of this shape has,
+    // i.e. the length of Network::to_values().
+    pub fn num_values(&self, inputs: usize, outputs: usize) -> usize {
+        let mut count = 0;
+        let mut size = inputs;
+        for _ in 0..self.hidden_layers {
+            count += (size + 1) * self.width;
+            size = self.width;
+        }
+        count + (size + 1) * outputs
+    }
+}
+
+impl Default for NetworkShape {
+    fn default() -> Self {
+        NetworkShape {
+            hidden_layers: DEFAULT_HIDDEN_LAYERS,
+            width: DEFAULT_WIDTH,
+            activation: Activation::default(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Layer {
+    inputs: usize,
+
+    // The weights of each unit's inputs, one unit after another.
+    weights: Vec<f32>,
+    biases: Vec<f32>,
+}
+
+impl Layer {
+    // Initializes the weights uniformly within +/- 1 / sqrt(inputs), so
+    // that every unit starts out with a similar range of outputs.
+    fn new<R: Rng>(inputs: usize, outputs: usize, rng: &mut R) -> Self {
+        let bound = 1.0 / (inputs as f32).sqrt();
+        Layer {
+            inputs,
+            weights: (0..inputs * outputs)
+              .map(|_| rng.gen_range(-bound, bound)).collect(),
+            biases: vec![0.0; outputs],
+        }
+    }
+
+    fn forward(&self, inputs: &[f32]) -> Vec<f32> {
+        self.biases.iter().enumerate().map(|(unit, &bias)| {
+            let weights = &self.weights[unit * self.inputs..];
+            inputs.iter().zip(weights.iter())
+              .fold(bias, |sum, (input, weight)| sum + input * weight)
+        }).collect()
+    }
+}
+
+// A training example: the network's inputs, which of its outputs to
+// train, and the value that output should have.
+pub type Sample = (Vec<f32>, usize, f32);
+
+#[derive(Clone)]
+pub struct Network {
+    layers: Vec<Layer>,
+    activation: Activation,
+}
+
+impl Network {
+    pub fn new<R: Rng>(inputs: usize, outputs: usize, shape: NetworkShape,
+                       rng: &mut R) -> Self {
+        assert!(shape.hidden_layers > 0 &&
+                shape.hidden_layers <= MAX_HIDDEN_LAYERS,
+                "networks must have one or two hidden layers");
+        assert!(shape.width > 0, "hidden layers must have at least one unit");
+        let mut layers = Vec::new();
+        let mut size = inputs;
+        for _ in 0..shape.hidden_layers {
+            layers.push(Layer::new(size, shape.width, rng));
+            size = shape.width;
+        }
+        layers.push(Layer::new(size, outputs, rng));
+        Network { layers, activation: shape.activation }
+    }
+
+    // Returns the outputs of every layer, starting with the inputs.
+    fn activations(&self, inputs: &[f32]) -> Vec<Vec<f32>> {
+        let mut activations = vec![inputs.to_vec()];
+        let last = self.layers.len() - 1;
+        for (i, layer) in self.layers.iter().enumerate() {
+            let mut outputs = layer.forward(&activations[i]);
+            if i < last {
+                for output in outputs.iter_mut() {
+                    *output = self.activation.apply(*output);
+                }
+            }
+            activations.push(outputs);
+        }
+        activations
+    }
+
+    pub fn outputs(&self, inputs: &[f32]) -> Vec<f32> {
+        self.activations(inputs).pop().unwrap()
+    }
+
+    // Takes a step of gradient descent on the mean squared error of the
+    // given samples, using backpropagation.
+    pub fn train(&mut self, samples: &[Sample], step_size: f32) {
+        let mut weight_gradients: Vec<Vec<f32>> = self.layers.iter()
+          .map(|layer| vec![0.0; layer.weights.len()]).collect();
+        let mut bias_gradients: Vec<Vec<f32>> = self.layers.iter()
+          .map(|layer| vec![0.0; layer.biases.len()]).collect();
+
+        for &(ref inputs, output, target) in samples.iter() {
+            let activations = self.activations(inputs);
+            let mut errors = vec![0.0; activations.last().unwrap().len()];
+            errors[output] = activations.last().unwrap()[output] - target;
+
+            for i in (0..self.layers.len()).rev() {
+                let layer = &self.layers[i];
+                let layer_inputs = &activations[i];
+                let mut input_errors = vec![0.0; layer.inputs];
+                for (unit, &error) in errors.iter().enumerate() {
+                    if error == 0.0 {
+                        continue;
+                    }
+                    bias_gradients[i][unit] += error;
+                    let offset = unit * layer.inputs;
+                    for (j, &input) in layer_inputs.iter().enumerate() {
+                        weight_gradients[i][offset + j] += error * input;
+                        input_errors[j] += error * layer.weights[offset + j];
+                    }
+                }
+                if i > 0 {
+                    for (error, &input) in input_errors.iter_mut()
+                                                       .zip(layer_inputs) {
+                        *error *= self.activation.derivative(input);
+                    }
+                }
+                errors = input_errors;
+            }
+        }
+
+        let scale = step_size / samples.len() as f32;
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            for (weight, gradient) in layer.weights.iter_mut()
+                                           .zip(weight_gradients[i].iter()) {
+                *weight -= scale * gradient;
+            }
+            for (bias, gradient) in layer.biases.iter_mut()
+                                         .zip(bias_gradients[i].iter()) {
+                *bias -= scale * gradient;
+            }
+        }
+    }
+
+    // Returns every weight and bias, layer by layer, e.g. for
+    // checkpointing.
+    pub fn to_values(&self) -> Vec<f32> {
+        let mut values = Vec::new();
+        for layer in self.layers.iter() {
+            values.extend_from_slice(&layer.weights);
+            values.extend_from_slice(&layer.biases);
+        }
+        values
+    }
+
+    pub fn load_values(&mut self, values: &[f32]) -> Result<(), CheckpointError> {
+        let expected = self.to_values().len();
+        if values.len() != expected {
+            return Err(CheckpointError::Parse(format!(
+                "expected {} weights, found {}", expected, values.len()
+            )));
+        }
+        let mut rest = values;
+        for layer in self.layers.iter_mut() {
+            let (weights, after) = rest.split_at(layer.weights.len());
+            let (biases, after) = after.split_at(layer.biases.len());
+            layer.weights.copy_from_slice(weights);
+            layer.biases.copy_from_slice(biases);
+            rest = after;
+        }
+        Ok(())
+    }
+}
+
+// Scales the dealer and player sums to lie between -1 and 1.
+pub fn to_inputs(state: State) -> Vec<f32> {
+    let dealer = (state.dealer - MIN_CARD) as f32 /
+                 (MAX_CARD - MIN_CARD) as f32;
+    let player = (state.player - MIN_SUM) as f32 /
+                 (MAX_SUM - MIN_SUM) as f32;
+    vec![dealer * 2.0 - 1.0, player * 2.0 - 1.0]
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UpdateMode {
+    // Semi-gradient Sarsa, as described in Section 10.1 of Sutton &
+    // Barto, which bootstraps from the next action actually taken.
+    Sarsa,
+
+    // Semi-gradient Q-learning, which bootstraps from the best next
+    // action instead.
+    QLearning,
+}
+
+// A network with an output for the value of every action, trained
+// online after every step.
+pub struct MultilayerPerceptron {
+    network: Network,
+    update_mode: UpdateMode,
+    discount: f32,
+    step_size: f32,
+}
+
+impl MultilayerPerceptron {
+    pub fn new<R: Rng>(shape: NetworkShape, step_size: f32,
+                       rng: &mut R) -> Self {
+        MultilayerPerceptron {
+            network: Network::new(NUM_INPUTS, NUM_ACTIONS, shape, rng),
+            update_mode: UpdateMode::Sarsa,
+            discount: 1.0,
+            step_size,
+        }
+    }
+
+    pub fn with_q_learning(mut self) -> Self {
+        self.update_mode = UpdateMode::QLearning;
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+}
+
+// Returns the value of every action at the state according to the
+// network. Terminal states are worth nothing.
+pub fn action_values(network: &Network, state: State) -> Vec<Reward> {
+    match Easy21::state_index(state) {
+        Some(_) => network.outputs(&to_inputs(state)),
+        None => vec![0.0; NUM_ACTIONS],
+    }
+}
+
+// Returns the action with the highest value according to the network.
+pub fn best_network_action(network: &Network, state: State) -> Action {
+    let values = action_values(network, state);
+    best_action(Easy21::actions(), |action| {
+        values[Easy21::action_index(action)]
+    })
+}
+
+impl Alg for MultilayerPerceptron {
+    fn choose_best_action(&self, state: State) -> Action {
+        best_network_action(&self.network, state)
+    }
+
+    fn get_expected_reward(&self, state: State, action: Action) -> Reward {
+        action_values(&self.network, state)[Easy21::action_index(action)]
+    }
+
+    fn needs_next_action(&self) -> bool {
+        self.update_mode == UpdateMode::Sarsa
+    }
+
+    fn on_episode_step(&mut self, state: State, action: Action,
+                       reward: Reward, next_state: State,
+                       next_action: Option<Action>) -> Option<Action> {
+        let next_value = match self.update_mode {
+            UpdateMode::Sarsa => {
+                self.get_expected_reward(next_state, next_action.unwrap())
+            },
+            UpdateMode::QLearning => {
+                let best = self.choose_best_action(next_state);
+                self.get_expected_reward(next_state, best)
+            },
+        };
+        let target = reward + self.discount * next_value;
+        let sample = (to_inputs(state), Easy21::action_index(action), target);
+        self.network.train(&[sample], self.step_size);
+        next_action
+    }
+}
+
+impl Checkpointable for MultilayerPerceptron {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg("mlp");
+        checkpoint.set_values("weights", &self.network.to_values());
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg("mlp")?;
+        self.network.load_values(&checkpoint.get_values("weights")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, StdRng, thread_rng};
+
+    use mlp::*;
+    use game::Action::*;
+
+    fn rng() -> StdRng {
+        SeedableRng::from_seed(&[1, 2, 3, 4][..])
+    }
+
+    fn shape(hidden_layers: usize, activation: Activation) -> NetworkShape {
+        NetworkShape { hidden_layers, width: 8, activation }
+    }
+
+    #[test]
+    fn test_training_fits_targets() {
+        for &activation in [Activation::Tanh, Activation::Relu,
+                            Activation::Sigmoid].iter() {
+            for hidden_layers in 1..MAX_HIDDEN_LAYERS + 1 {
+                let mut network = Network::new(2, 2, shape(hidden_layers,
+                                                           activation),
+                                               &mut rng());
+                let samples = vec![
+                    (vec![-1.0, 0.5], 0, 1.0),
+                    (vec![0.5, -1.0], 1, -1.0),
+                ];
+                for _ in 0..2000 {
+                    network.train(&samples, 0.1);
+                }
+
+                assert!((network.outputs(&samples[0].0)[0] - 1.0).abs() < 0.05);
+                assert!((network.outputs(&samples[1].0)[1] + 1.0).abs() < 0.05);
+            }
+        }
+    }
+
+    #[test]
+    fn test_gradients_match_finite_differences() {
+        let network = Network::new(2, 2, shape(2, Activation::Tanh),
+                                   &mut rng());
+        let inputs = vec![0.3, -0.7];
+        let loss = |network: &Network| {
+            let error = network.outputs(&inputs)[1] - 0.5;
+            0.5 * error * error
+        };
+        let values = network.to_values();
+
+        // A step of 1 changes every value by minus its gradient.
+        let mut trained = network.clone();
+        trained.train(&[(inputs.clone(), 1, 0.5)], 1.0);
+        let gradients: Vec<f32> = values.iter().zip(trained.to_values())
+          .map(|(before, after)| before - after).collect();
+
+        let epsilon = 1e-2;
+        for i in 0..values.len() {
+            let mut nudged = network.clone();
+            let mut nudged_values = values.clone();
+            nudged_values[i] += epsilon;
+            nudged.load_values(&nudged_values).unwrap();
+            let estimate = (loss(&nudged) - loss(&network)) / epsilon;
+            assert!((estimate - gradients[i]).abs() < 1e-2,
+                    "value {}: {} != {}", i, estimate, gradients[i]);
+        }
+    }
+
+    #[test]
+    fn test_terminal_states_are_worth_nothing() {
+        let alg = MultilayerPerceptron::new(NetworkShape::default(),
+                                            DEFAULT_STEP_SIZE, &mut rng());
+        let state = State { dealer: 5, player: 22 };
+
+        assert_eq!(alg.get_expected_reward(state, Hit), 0.0);
+        assert_eq!(alg.get_expected_reward(state, Stick), 0.0);
+    }
+
+    #[test]
+    fn test_checkpoints_work() {
+        let alg = MultilayerPerceptron::new(NetworkShape::default(),
+                                            DEFAULT_STEP_SIZE, &mut rng());
+        let mut checkpoint = Checkpoint::new();
+        alg.save(&mut checkpoint);
+
+        let mut loaded = MultilayerPerceptron::new(NetworkShape::default(),
+                                                   DEFAULT_STEP_SIZE,
+                                                   &mut thread_rng());
+        loaded.load(&checkpoint).unwrap();
+        assert_eq!(loaded.network.to_values(), alg.network.to_values());
+
+        let mut wider = MultilayerPerceptron::new(NetworkShape {
+            width: 4,
+            ..NetworkShape::default()
+        }, DEFAULT_STEP_SIZE, &mut rng());
+        assert!(wider.load(&checkpoint).is_err());
+    }
+
+    #[test]
+    fn test_num_values_matches_networks() {
+        for hidden_layers in 1..MAX_HIDDEN_LAYERS + 1 {
+            let shape = shape(hidden_layers, Activation::Tanh);
+            let network = Network::new(NUM_INPUTS, 2, shape, &mut rng());
+            assert_eq!(shape.num_values(NUM_INPUTS, 2),
+                       network.to_values().len());
+        }
+    }
+
+    #[test]
+    fn test_activation_names_work() {
+        for &name in ACTIVATION_NAMES {
+            assert_eq!(Activation::from_name(name).unwrap().name(), name);
+        }
+        assert_eq!(Activation::from_name("boop"), None);
+    }
+}
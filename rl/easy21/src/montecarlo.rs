@@ -1,64 +1,174 @@
-use std::collections::HashMap;
-
-use game::{State, Action, Reward};
-use game::Action::*;
+use game::{Reward, Easy21};
+use environment::Environment;
 use gpi::Alg;
-use util::VaryingStepSizer;
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+use util::{best_action, StepSizer, ValueTable};
+
+pub struct MonteCarlo<E: Environment = Easy21> {
+    value_fn: ValueTable<E>,
+    step_sizer: StepSizer<E>,
+    discount: f32,
 
-type ValueFn = HashMap<(State, Action), Reward>;
+    // Whether to update a state/action pair every time it's visited in
+    // an episode, rather than only the first time.
+    every_visit: bool,
 
-pub struct MonteCarlo {
-    value_fn: ValueFn,
-    step_sizer: VaryingStepSizer,
-    reward_this_episode: Reward,
-    visited_this_episode: HashMap<(State, Action), bool>,
+    // Every step of the current episode, along with the reward that
+    // followed it. Episodes are short, so a list is faster than a set
+    // for finding first visits.
+    steps_this_episode: Vec<(E::State, E::Action, Reward)>,
 }
 
-impl MonteCarlo {
+impl<E: Environment> MonteCarlo<E> {
     pub fn new() -> Self {
         MonteCarlo {
-            value_fn: HashMap::new(),
-            step_sizer: VaryingStepSizer::new(),
-            reward_this_episode: 0.0,
-            visited_this_episode: HashMap::new(),
+            value_fn: ValueTable::new(),
+            step_sizer: StepSizer::new(None),
+            discount: 1.0,
+            every_visit: false,
+            steps_this_episode: Vec::new(),
         }
     }
+
+    pub fn with_constant_step_size(mut self, value: f32) -> Self {
+        self.step_sizer = StepSizer::new(Some(value));
+        self
+    }
+
+    pub fn with_every_visit(mut self) -> Self {
+        self.every_visit = true;
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
 }
 
-impl Alg for MonteCarlo {
-    fn choose_best_action(&self, state: State) -> Action {
-        let hit = *self.value_fn.get(&(state, Hit)).unwrap_or(&0.0);
-        let stick = *self.value_fn.get(&(state, Stick)).unwrap_or(&0.0);
-        if hit > stick { Hit } else { Stick }
+impl<E: Environment> Default for MonteCarlo<E> {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    fn get_expected_reward(&self, state: State, action: Action) -> Reward {
-        *self.value_fn.get(&(state, action)).unwrap_or(&0.0)
+impl<E: Environment> Alg<E> for MonteCarlo<E> {
+    fn choose_best_action(&self, state: E::State) -> E::Action {
+        best_action(E::actions(), |action| {
+            self.get_expected_reward(state, action)
+        })
+    }
+
+    fn get_expected_reward(&self, state: E::State,
+                           action: E::Action) -> Reward {
+        self.value_fn.get(state, action)
     }
 
     fn on_episode_begin(&mut self) {
-        self.reward_this_episode = 0.0;
-        self.visited_this_episode.drain();
+        self.steps_this_episode.clear();
     }
 
-    fn on_episode_step(&mut self, state: State, action: Action,
-                       reward: Reward, _next_state: State,
-                       _next_action: Option<Action>) -> Option<Action> {
-        // We only care about the *first* time a state/action pair
-        // was visited in an episode.
-        self.visited_this_episode.entry((state, action)).or_insert(true);
-        self.reward_this_episode += reward;
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward, _next_state: E::State,
+                       _next_action: Option<E::Action>) -> Option<E::Action> {
+        self.steps_this_episode.push((state, action, reward));
         None
     }
 
     fn on_episode_end(&mut self) {
-        for &(state, action) in self.visited_this_episode.keys() {
-            let old_value = *self.value_fn.get(&(state, action))
-              .unwrap_or(&0.0);
+        let steps = &self.steps_this_episode;
+
+        // The discounted return following each step.
+        let mut returns = vec![0.0; steps.len()];
+        let mut reward = 0.0;
+        for (i, &(_, _, step_reward)) in steps.iter().enumerate().rev() {
+            reward = step_reward + self.discount * reward;
+            returns[i] = reward;
+        }
+
+        for (i, &(state, action, _)) in steps.iter().enumerate() {
+            // Unless we're doing every-visit updates, we only care about
+            // the *first* time a state/action pair was visited in an
+            // episode.
+            if !self.every_visit && steps[..i].iter().any(|step| {
+                step.0 == state && step.1 == action
+            }) {
+                continue;
+            }
+            let old_value = self.value_fn.get(state, action);
             let step_size = self.step_sizer.update(state, action);
-            let new_value = old_value + step_size *
-                            (self.reward_this_episode - old_value);
-            self.value_fn.insert((state, action), new_value);
+            let new_value = old_value + step_size * (returns[i] - old_value);
+            self.value_fn.set(state, action, new_value);
         }
     }
 }
+
+impl Checkpointable for MonteCarlo {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg("mc");
+        checkpoint.set_value_table("value_fn", &self.value_fn.to_hash_map());
+        self.step_sizer.save(checkpoint);
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg("mc")?;
+        self.value_fn = ValueTable::from_hash_map(
+            &checkpoint.get_value_table("value_fn")?
+        )?;
+        self.step_sizer.load(checkpoint)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game::{State, Easy21};
+    use game::Action::*;
+    use gpi::Alg;
+    use montecarlo::*;
+
+    fn play_revisiting_episode(alg: &mut MonteCarlo) {
+        let state = State { dealer: 5, player: 10 };
+        let next = State { dealer: 5, player: 15 };
+
+        alg.on_episode_begin();
+        alg.on_episode_step(state, Hit, 0.0, next, None);
+        alg.on_episode_step(next, Hit, 0.0, state, None);
+        alg.on_episode_step(state, Hit, 1.0, next, None);
+        alg.on_episode_end();
+    }
+
+    #[test]
+    fn test_first_visit_works() {
+        let mut alg: MonteCarlo<Easy21> = MonteCarlo::new()
+          .with_constant_step_size(0.5);
+        play_revisiting_episode(&mut alg);
+
+        assert_eq!(alg.get_expected_reward(State { dealer: 5, player: 10 },
+                                           Hit), 0.5);
+    }
+
+    #[test]
+    fn test_discount_works() {
+        let mut alg: MonteCarlo<Easy21> = MonteCarlo::new()
+          .with_constant_step_size(1.0)
+          .with_discount(0.5);
+        play_revisiting_episode(&mut alg);
+
+        assert_eq!(alg.get_expected_reward(State { dealer: 5, player: 10 },
+                                           Hit), 0.25);
+        assert_eq!(alg.get_expected_reward(State { dealer: 5, player: 15 },
+                                           Hit), 0.5);
+    }
+
+    #[test]
+    fn test_every_visit_works() {
+        let mut alg: MonteCarlo<Easy21> = MonteCarlo::new()
+          .with_constant_step_size(0.5)
+          .with_every_visit();
+        play_revisiting_episode(&mut alg);
+
+        assert_eq!(alg.get_expected_reward(State { dealer: 5, player: 10 },
+                                           Hit), 0.75);
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::VecDeque;
+
+use game::{Reward, Easy21};
+use environment::Environment;
+use gpi::Alg;
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+use util::{best_action, StepSizer, ValueTable};
+
+pub const DEFAULT_N: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Backup {
+    // n-step Sarsa, as described in Section 7.2 of Sutton & Barto, whose
+    // target is the next n rewards plus the value of the state/action
+    // pair the behavior policy reached after them.
+    Sarsa,
+
+    // n-step Tree Backup, as described in Section 7.5 of Sutton & Barto,
+    // which learns the values of the greedy policy without importance
+    // sampling by backing up the values of the actions that weren't
+    // taken along the way.
+    TreeBackup,
+}
+
+// A state/action pair that's waiting for the n rewards that follow it
+// before its value can be updated.
+struct Transition<E: Environment> {
+    state: E::State,
+    action: E::Action,
+    reward: Reward,
+}
+
+pub struct NStep<E: Environment = Easy21> {
+    value_fn: ValueTable<E>,
+    step_sizer: StepSizer<E>,
+    backup: Backup,
+    n: usize,
+    discount: f32,
+
+    // The last n transitions of the current episode, oldest first.
+    transitions: VecDeque<Transition<E>>,
+}
+
+impl<E: Environment> NStep<E> {
+    pub fn new(backup: Backup, n: usize) -> Self {
+        assert!(n > 0, "n must be greater than 0");
+        NStep {
+            value_fn: ValueTable::new(),
+            step_sizer: StepSizer::new(None),
+            backup,
+            n,
+            discount: 1.0,
+            transitions: VecDeque::with_capacity(n),
+        }
+    }
+
+    pub fn sarsa(n: usize) -> Self {
+        Self::new(Backup::Sarsa, n)
+    }
+
+    pub fn tree_backup(n: usize) -> Self {
+        Self::new(Backup::TreeBackup, n)
+    }
+
+    pub fn with_constant_step_size(mut self, value: f32) -> Self {
+        self.step_sizer = StepSizer::new(Some(value));
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    fn max_value(&self, state: E::State) -> Reward {
+        let action = self.choose_best_action(state);
+        self.get_expected_reward(state, action)
+    }
+
+    // Returns the n-step return of the oldest buffered transition, given
+    // the value of the state/action pair that follows the newest one
+    // (zero if the episode has ended).
+    fn sarsa_return(&self, bootstrap: Reward) -> Reward {
+        self.transitions.iter().rev().fold(bootstrap, |result, t| {
+            t.reward + self.discount * result
+        })
+    }
+
+    // Like sarsa_return(), but following equation 7.16. Since our target
+    // policy is greedy, the return is only carried back through a
+    // transition if its action was the best one; otherwise it's cut off
+    // and replaced with the best action's value.
+    fn tree_backup_return(&self, bootstrap: Reward) -> Reward {
+        let mut transitions = self.transitions.iter().rev();
+        let mut result = transitions.next().unwrap().reward +
+                         self.discount * bootstrap;
+        let mut later = self.transitions.back().unwrap();
+        for earlier in transitions {
+            let best = self.choose_best_action(later.state);
+            result = earlier.reward + self.discount * if later.action == best {
+                result
+            } else {
+                self.get_expected_reward(later.state, best)
+            };
+            later = earlier;
+        }
+        result
+    }
+
+    fn update_oldest(&mut self, bootstrap: Reward) {
+        let target = match self.backup {
+            Backup::Sarsa => self.sarsa_return(bootstrap),
+            Backup::TreeBackup => self.tree_backup_return(bootstrap),
+        };
+        let oldest = self.transitions.pop_front().unwrap();
+        let step_size = self.step_sizer.update(oldest.state, oldest.action);
+        let error = target - self.get_expected_reward(oldest.state,
+                                                      oldest.action);
+        self.value_fn.increment(oldest.state, oldest.action,
+                                step_size * error);
+    }
+}
+
+impl<E: Environment> Alg<E> for NStep<E> {
+    fn choose_best_action(&self, state: E::State) -> E::Action {
+        best_action(E::actions(), |action| {
+            self.get_expected_reward(state, action)
+        })
+    }
+
+    fn get_expected_reward(&self, state: E::State,
+                           action: E::Action) -> Reward {
+        self.value_fn.get(state, action)
+    }
+
+    fn on_episode_begin(&mut self) {
+        self.transitions.clear();
+    }
+
+    fn needs_next_action(&self) -> bool {
+        self.backup == Backup::Sarsa
+    }
+
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward, next_state: E::State,
+                       next_action: Option<E::Action>) -> Option<E::Action> {
+        self.transitions.push_back(Transition { state, action, reward });
+        if self.transitions.len() == self.n {
+            let bootstrap = match self.backup {
+                Backup::Sarsa => {
+                    self.get_expected_reward(next_state, next_action.unwrap())
+                },
+                Backup::TreeBackup => self.max_value(next_state),
+            };
+            self.update_oldest(bootstrap);
+        }
+        next_action
+    }
+
+    fn on_episode_end(&mut self) {
+        while !self.transitions.is_empty() {
+            self.update_oldest(0.0);
+        }
+    }
+}
+
+impl Checkpointable for NStep {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg(match self.backup {
+            Backup::Sarsa => "nstep_sarsa",
+            Backup::TreeBackup => "nstep_tree_backup",
+        });
+        checkpoint.set_value_table("value_fn", &self.value_fn.to_hash_map());
+        self.step_sizer.save(checkpoint);
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg(match self.backup {
+            Backup::Sarsa => "nstep_sarsa",
+            Backup::TreeBackup => "nstep_tree_backup",
+        })?;
+        self.value_fn = ValueTable::from_hash_map(
+            &checkpoint.get_value_table("value_fn")?
+        )?;
+        self.step_sizer.load(checkpoint)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game::State;
+    use game::Action::*;
+    use nstep::*;
+
+    const A: State = State { dealer: 5, player: 10 };
+    const B: State = State { dealer: 5, player: 15 };
+    const C: State = State { dealer: 5, player: 18 };
+    const END: State = State { dealer: 5, player: 25 };
+
+    #[test]
+    fn test_sarsa_waits_for_n_rewards() {
+        let mut alg: NStep = NStep::sarsa(2).with_constant_step_size(1.0);
+        alg.value_fn.set(C, Stick, 0.5);
+
+        alg.on_episode_begin();
+        alg.on_episode_step(A, Hit, 1.0, B, Some(Hit));
+        assert_eq!(alg.get_expected_reward(A, Hit), 0.0);
+
+        alg.on_episode_step(B, Hit, 2.0, C, Some(Stick));
+        assert_eq!(alg.get_expected_reward(A, Hit), 3.5);
+        assert_eq!(alg.get_expected_reward(B, Hit), 0.0);
+
+        alg.on_episode_step(C, Stick, -1.0, END, Some(Hit));
+        alg.on_episode_end();
+        assert_eq!(alg.get_expected_reward(B, Hit), 1.0);
+        assert_eq!(alg.get_expected_reward(C, Stick), -1.0);
+    }
+
+    #[test]
+    fn test_sarsa_discounts_rewards() {
+        let mut alg: NStep = NStep::sarsa(2).with_constant_step_size(1.0)
+          .with_discount(0.5);
+        alg.value_fn.set(C, Stick, 0.5);
+
+        alg.on_episode_begin();
+        alg.on_episode_step(A, Hit, 1.0, B, Some(Hit));
+        alg.on_episode_step(B, Hit, 2.0, C, Some(Stick));
+
+        assert_eq!(alg.get_expected_reward(A, Hit), 2.125);
+    }
+
+    #[test]
+    fn test_tree_backup_cuts_off_non_greedy_actions() {
+        let mut alg: NStep = NStep::tree_backup(3)
+          .with_constant_step_size(1.0);
+        alg.value_fn.set(B, Stick, 0.25);
+        alg.value_fn.set(C, Stick, 0.5);
+
+        alg.on_episode_begin();
+        alg.on_episode_step(A, Hit, 1.0, B, None);
+        alg.on_episode_step(B, Hit, 2.0, C, None);
+        alg.on_episode_step(C, Stick, -1.0, END, None);
+
+        // Hitting at B wasn't greedy, so the return of A is cut off there
+        // and uses the value of sticking instead.
+        assert_eq!(alg.get_expected_reward(A, Hit), 1.25);
+
+        alg.on_episode_end();
+
+        // Sticking at C was greedy, so B's return includes its reward.
+        assert_eq!(alg.get_expected_reward(B, Hit), 1.0);
+        assert_eq!(alg.get_expected_reward(C, Stick), -1.0);
+    }
+}
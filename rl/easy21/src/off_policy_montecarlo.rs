@@ -0,0 +1,234 @@
+use game::{Reward, Easy21};
+use environment::Environment;
+use gpi::Alg;
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+use util::{best_action, ValueTable};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Sampling {
+    // Each return is scaled by its importance sampling ratio and the
+    // results are simply averaged. This is unbiased, but its variance
+    // can be unbounded.
+    Ordinary,
+
+    // The scaled returns are averaged weighted by their ratios, as in
+    // equation 5.6 of Sutton & Barto. This is biased towards the
+    // behavior policy's values at first, but has far lower variance.
+    Weighted,
+}
+
+impl Sampling {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Sampling::Ordinary => "ordinary",
+            Sampling::Weighted => "weighted",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ordinary" => Some(Sampling::Ordinary),
+            "weighted" => Some(Sampling::Weighted),
+            _ => None,
+        }
+    }
+}
+
+struct Step<E: Environment> {
+    state: E::State,
+    action: E::Action,
+    reward: Reward,
+    behavior_probability: f32,
+}
+
+// Off-policy every-visit Monte Carlo control, as described in Section
+// 5.7 of Sutton & Barto. It learns the values of the greedy target
+// policy from episodes generated by the (e.g. epsilon-greedy) behavior
+// policy, correcting for the difference between them via importance
+// sampling.
+pub struct OffPolicyMonteCarlo<E: Environment = Easy21> {
+    value_fn: ValueTable<E>,
+    sampling: Sampling,
+    discount: f32,
+
+    // For weighted importance sampling, the sum of the importance
+    // sampling ratios of every return seen for each state/action pair.
+    // For ordinary importance sampling, the number of returns seen.
+    cumulative_weights: ValueTable<E>,
+
+    next_behavior_probability: f32,
+    steps_this_episode: Vec<Step<E>>,
+}
+
+impl<E: Environment> OffPolicyMonteCarlo<E> {
+    pub fn new(sampling: Sampling) -> Self {
+        OffPolicyMonteCarlo {
+            value_fn: ValueTable::new(),
+            sampling,
+            discount: 1.0,
+            cumulative_weights: ValueTable::new(),
+            next_behavior_probability: 1.0,
+            steps_this_episode: Vec::new(),
+        }
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    fn update(&mut self, state: E::State, action: E::Action, reward: Reward,
+              weight: f32) {
+        let (count, target) = match self.sampling {
+            Sampling::Ordinary => (1.0, weight * reward),
+            Sampling::Weighted => (weight, reward),
+        };
+        let total = self.cumulative_weights.increment(state, action, count);
+        let old_value = self.value_fn.get(state, action);
+        self.value_fn.set(state, action,
+                          old_value + count / total * (target - old_value));
+    }
+}
+
+impl<E: Environment> Alg<E> for OffPolicyMonteCarlo<E> {
+    fn choose_best_action(&self, state: E::State) -> E::Action {
+        best_action(E::actions(), |action| {
+            self.get_expected_reward(state, action)
+        })
+    }
+
+    fn get_expected_reward(&self, state: E::State,
+                           action: E::Action) -> Reward {
+        self.value_fn.get(state, action)
+    }
+
+    fn on_episode_begin(&mut self) {
+        self.steps_this_episode.clear();
+    }
+
+    fn needs_behavior_probability(&self) -> bool {
+        true
+    }
+
+    fn on_behavior_probability(&mut self, probability: f32) {
+        self.next_behavior_probability = probability;
+    }
+
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward, _next_state: E::State,
+                       _next_action: Option<E::Action>) -> Option<E::Action> {
+        self.steps_this_episode.push(Step {
+            state,
+            action,
+            reward,
+            behavior_probability: self.next_behavior_probability,
+        });
+        None
+    }
+
+    fn on_episode_end(&mut self) {
+        let steps = std::mem::take(&mut self.steps_this_episode);
+        let mut reward = 0.0;
+        let mut weight = 1.0;
+
+        for step in steps.iter().rev() {
+            reward = step.reward + self.discount * reward;
+            if weight == 0.0 && self.sampling == Sampling::Weighted {
+                // Nothing earlier in the episode has any weight, so it
+                // can't change any values.
+                break;
+            }
+            self.update(step.state, step.action, reward, weight);
+            weight = if step.action == self.choose_best_action(step.state) {
+                weight / step.behavior_probability
+            } else {
+                0.0
+            };
+        }
+
+        self.steps_this_episode = steps;
+    }
+}
+
+impl Checkpointable for OffPolicyMonteCarlo {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg(match self.sampling {
+            Sampling::Ordinary => "ordinary_off_policy_mc",
+            Sampling::Weighted => "weighted_off_policy_mc",
+        });
+        checkpoint.set_value_table("value_fn", &self.value_fn.to_hash_map());
+        checkpoint.set_value_table("cumulative_weights",
+                                   &self.cumulative_weights.to_hash_map());
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg(match self.sampling {
+            Sampling::Ordinary => "ordinary_off_policy_mc",
+            Sampling::Weighted => "weighted_off_policy_mc",
+        })?;
+        self.value_fn = ValueTable::from_hash_map(
+            &checkpoint.get_value_table("value_fn")?
+        )?;
+        self.cumulative_weights = ValueTable::from_hash_map(
+            &checkpoint.get_value_table("cumulative_weights")?
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game::{State, Action};
+    use game::Action::*;
+    use off_policy_montecarlo::*;
+
+    const A: State = State { dealer: 5, player: 10 };
+    const B: State = State { dealer: 5, player: 15 };
+    const END: State = State { dealer: 5, player: 25 };
+
+    fn play(alg: &mut OffPolicyMonteCarlo, steps: &[(State, Action, f32)],
+            reward: Reward) {
+        alg.on_episode_begin();
+        for (i, &(state, action, probability)) in steps.iter().enumerate() {
+            let next_state = steps.get(i + 1).map_or(END, |step| step.0);
+            let step_reward = if i + 1 == steps.len() { reward } else { 0.0 };
+            alg.on_behavior_probability(probability);
+            alg.on_episode_step(state, action, step_reward, next_state, None);
+        }
+        alg.on_episode_end();
+    }
+
+    #[test]
+    fn test_ordinary_sampling_scales_returns() {
+        let mut alg = OffPolicyMonteCarlo::new(Sampling::Ordinary);
+        alg.value_fn.set(B, Stick, 0.5);
+        alg.cumulative_weights.set(B, Stick, 1.0);
+
+        play(&mut alg, &[(A, Hit, 0.5), (B, Stick, 0.5)], 1.0);
+
+        assert_eq!(alg.get_expected_reward(B, Stick), 0.75);
+        assert_eq!(alg.get_expected_reward(A, Hit), 2.0);
+    }
+
+    #[test]
+    fn test_weighted_sampling_averages_by_weight() {
+        let mut alg = OffPolicyMonteCarlo::new(Sampling::Weighted);
+
+        play(&mut alg, &[(B, Stick, 0.25)], 1.0);
+        play(&mut alg, &[(A, Hit, 0.5), (B, Stick, 0.25)], -1.0);
+
+        assert_eq!(alg.get_expected_reward(B, Stick), 0.0);
+        assert_eq!(alg.get_expected_reward(A, Hit), -1.0);
+    }
+
+    #[test]
+    fn test_non_greedy_actions_cut_off_earlier_returns() {
+        let mut alg = OffPolicyMonteCarlo::new(Sampling::Weighted);
+        alg.value_fn.set(B, Stick, 0.5);
+
+        play(&mut alg, &[(A, Hit, 0.5), (B, Hit, 0.25)], -1.0);
+
+        assert_eq!(alg.get_expected_reward(B, Hit), -1.0);
+        assert_eq!(alg.get_expected_reward(A, Hit), 0.0);
+    }
+}
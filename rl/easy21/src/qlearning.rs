@@ -1,50 +1,95 @@
-use std::collections::HashMap;
-
-use game::{State, Action, Reward};
-use game::Action::*;
+use game::{Reward, Easy21};
+use environment::Environment;
 use gpi::Alg;
-use util::{increment, VaryingStepSizer};
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+use util::{best_action, StepSizer, ValueTable, Traces};
+use traces::TraceType;
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TraceMode {
+    // Watkins's Q(lambda), which cuts the eligibility traces whenever
+    // the behavior policy takes a non-greedy action, since the
+    // preceding state/action pairs aren't responsible for what happens
+    // after it under the greedy target policy.
+    Watkins,
 
-type EligibilityHash = HashMap<(State, Action), f32>;
-type ValueFn = HashMap<(State, Action), Reward>;
+    // The behavior of earlier versions of this module, kept so that old
+    // results can be reproduced. Its traces are never cut, and its TD
+    // target evaluates the next state at the action that's best at the
+    // *current* state, rather than at the next one.
+    Naive,
+}
 
-pub struct QLearning {
-    value_fn: ValueFn,
-    step_sizer: VaryingStepSizer,
-    traces: EligibilityHash,
+pub struct QLearning<E: Environment = Easy21> {
+    value_fn: ValueTable<E>,
+    step_sizer: StepSizer<E>,
+    traces: Traces<E>,
+    trace_type: TraceType,
     lambda: f32,
+    discount: f32,
+    trace_mode: TraceMode,
+    cut_traces: bool,
 }
 
-impl QLearning {
+impl<E: Environment> QLearning<E> {
     pub fn new(lambda: f32) -> Self {
         QLearning {
-            value_fn: HashMap::new(),
-            step_sizer: VaryingStepSizer::new(),
-            traces: HashMap::new(),
+            value_fn: ValueTable::new(),
+            step_sizer: StepSizer::new(None),
+            traces: Traces::new(),
+            trace_type: TraceType::default(),
             lambda,
+            discount: 1.0,
+            trace_mode: TraceMode::Watkins,
+            cut_traces: false,
         }
     }
+
+    pub fn with_naive_traces(mut self) -> Self {
+        self.trace_mode = TraceMode::Naive;
+        self
+    }
+
+    pub fn with_constant_step_size(mut self, value: f32) -> Self {
+        self.step_sizer = StepSizer::new(Some(value));
+        self
+    }
+
+    pub fn with_trace_type(mut self, trace_type: TraceType) -> Self {
+        self.trace_type = trace_type;
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
 }
 
-impl Alg for QLearning {
-    fn choose_best_action(&self, state: State) -> Action {
-        let hit = *self.value_fn.get(&(state, Hit)).unwrap_or(&0.0);
-        let stick = *self.value_fn.get(&(state, Stick)).unwrap_or(&0.0);
-        if hit > stick { Hit } else { Stick }
+impl<E: Environment> Alg<E> for QLearning<E> {
+    fn choose_best_action(&self, state: E::State) -> E::Action {
+        best_action(E::actions(), |action| {
+            self.get_expected_reward(state, action)
+        })
     }
 
-    fn get_expected_reward(&self, state: State, action: Action) -> Reward {
-        *self.value_fn.get(&(state, action)).unwrap_or(&0.0)
+    fn get_expected_reward(&self, state: E::State,
+                           action: E::Action) -> Reward {
+        self.value_fn.get(state, action)
     }
 
     fn on_episode_begin(&mut self) {
-        self.traces.drain();
+        self.traces.clear();
+        self.cut_traces = false;
+    }
+
+    fn on_exploratory_step(&mut self) {
+        self.cut_traces = self.trace_mode == TraceMode::Watkins;
     }
 
-    fn on_episode_step(&mut self, state: State, action: Action,
-                       reward: Reward, next_state: State,
-                       _next_action: Option<Action>) -> Option<Action> {
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward, next_state: E::State,
+                       _next_action: Option<E::Action>) -> Option<E::Action> {
         let step_size = self.step_sizer.update(state, action);
 
         // I believe this is a key distinction between Sarsa and
@@ -52,19 +97,78 @@ impl Alg for QLearning {
         // policy's next action to calculate its TD-error, but
         // Q-Learning is off-policy, so it uses the target policy
         // (i.e., the one without epsilon-greedy behavior).
-        let my_next_action = self.choose_best_action(state);
+        let my_next_action = match self.trace_mode {
+            TraceMode::Watkins => self.choose_best_action(next_state),
+            TraceMode::Naive => self.choose_best_action(state),
+        };
+
+        if self.cut_traces {
+            self.traces.clear();
+            self.cut_traces = false;
+        }
 
         let td_error = reward +
+                       self.discount *
                        self.get_expected_reward(next_state,
                                                 my_next_action) -
                        self.get_expected_reward(state, action);
-        increment(&mut self.traces, (state, action), 1.0);
-        for (&(state, action), trace) in self.traces.iter_mut() {
-            let eligibility_trace = *trace;
-            increment(&mut self.value_fn, (state, action),
-                      step_size * td_error * eligibility_trace);
-            *trace = self.lambda * eligibility_trace;
-        }
+        self.traces.visit(state, action, self.trace_type, step_size);
+        self.value_fn.add_traces(&self.traces, step_size * td_error);
+        self.traces.decay(self.discount * self.lambda);
         None
     }
 }
+
+impl Checkpointable for QLearning {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg("qlearning");
+        checkpoint.set_value_table("value_fn", &self.value_fn.to_hash_map());
+        self.step_sizer.save(checkpoint);
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg("qlearning")?;
+        self.value_fn = ValueTable::from_hash_map(
+            &checkpoint.get_value_table("value_fn")?
+        )?;
+        self.step_sizer.load(checkpoint)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game::State;
+    use game::Action::*;
+    use gpi::Alg;
+    use qlearning::*;
+
+    fn trace_test(alg: QLearning) -> QLearning {
+        let mut alg = alg.with_constant_step_size(1.0);
+        let first = State { dealer: 5, player: 10 };
+        let second = State { dealer: 5, player: 15 };
+        let end = State { dealer: 5, player: 30 };
+
+        alg.on_episode_begin();
+        alg.on_episode_step(first, Hit, 0.0, second, None);
+        alg.on_exploratory_step();
+        alg.on_episode_step(second, Hit, 1.0, end, None);
+        alg
+    }
+
+    #[test]
+    fn test_watkins_cuts_traces_on_exploratory_steps() {
+        let alg = trace_test(QLearning::new(1.0));
+        let first = State { dealer: 5, player: 10 };
+
+        assert_eq!(alg.get_expected_reward(first, Hit), 0.0);
+    }
+
+    #[test]
+    fn test_naive_never_cuts_traces() {
+        let alg = trace_test(QLearning::new(1.0).with_naive_traces());
+        let first = State { dealer: 5, player: 10 };
+
+        assert_eq!(alg.get_expected_reward(first, Hit), 1.0);
+    }
+}
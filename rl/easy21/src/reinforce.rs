@@ -0,0 +1,315 @@
+// This module contains REINFORCE, a policy gradient method, as described
+// in Sections 13.3 and 13.4 of Sutton & Barto. Unlike every other
+// learner, it learns a policy directly rather than action values, so it
+// implements Policy itself instead of Alg.
+
+use rand::Rng;
+
+use game::{State, Action, Reward, Easy21};
+use environment::Environment;
+use gpi::Policy;
+use features::{FeatureExtractor, CuboidFeatures};
+use lfa::dot_product;
+use util::sample_action;
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+
+pub const DEFAULT_STEP_SIZE: f32 = 0.01;
+
+// Chooses actions via a softmax over linear action preferences, i.e. the
+// dot products of their features with a vector of weights. At the end
+// of every episode, the weights are moved along the gradient of the
+// log probability of every action taken, scaled by the return that
+// followed it.
+//
+// Optionally, a linear state-value baseline is learned alongside the
+// policy and subtracted from the returns, which doesn't bias the
+// gradient but can greatly reduce its variance.
+pub struct Reinforce<T: Rng> {
+    rng: T,
+    features: Box<dyn FeatureExtractor>,
+    weights: Vec<f32>,
+    step_size: f32,
+    discount: f32,
+
+    // The weights of the baseline, if any, and their step size.
+    baseline: Option<Vec<f32>>,
+    baseline_step_size: f32,
+
+    // The steps of the current episode.
+    steps: Vec<(State, Action, Reward)>,
+}
+
+impl<T: Rng> Reinforce<T> {
+    pub fn new(rng: T, step_size: f32) -> Self {
+        let features = CuboidFeatures;
+        let num_features = features.num_features();
+        Reinforce {
+            rng,
+            features: Box::new(features),
+            weights: vec![0.0; num_features],
+            step_size,
+            discount: 1.0,
+            baseline: None,
+            baseline_step_size: 0.0,
+            steps: Vec::new(),
+        }
+    }
+
+    // Uses the given features instead of the default cuboids. This
+    // resets the weights.
+    pub fn with_features<F: FeatureExtractor + 'static>(mut self,
+                                                        features: F) -> Self {
+        let num_features = features.num_features();
+        self.features = Box::new(features);
+        self.weights = vec![0.0; num_features];
+        if self.baseline.is_some() {
+            self.baseline = Some(vec![0.0; num_features]);
+        }
+        self
+    }
+
+    pub fn with_baseline(mut self, step_size: f32) -> Self {
+        self.baseline = Some(vec![0.0; self.features.num_features()]);
+        self.baseline_step_size = step_size;
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    // Returns the probability that we'll take each of Easy21::actions()
+    // at the given state.
+    pub fn action_probabilities(&self, state: State) -> Vec<f32> {
+        let preferences: Vec<f32> = Easy21::actions().iter().map(|&action| {
+            let features = self.features.to_feature_vector(state, action);
+            dot_product(&features, &self.weights)
+        }).collect();
+
+        // Subtracting the largest preference keeps the exponentials from
+        // overflowing without changing the resulting probabilities.
+        let max = preferences.iter().cloned().fold(f32::MIN, f32::max);
+        let weights: Vec<f32> = preferences.iter().map(|p| (p - max).exp())
+          .collect();
+        let total: f32 = weights.iter().sum();
+
+        weights.iter().map(|w| w / total).collect()
+    }
+
+    // Returns the baseline's estimate of the state's value, or 0 if
+    // there's no baseline. The baseline can't depend on the action, so
+    // it uses the features of every action at the state.
+    pub fn state_value(&self, state: State) -> Reward {
+        match self.baseline {
+            Some(ref baseline) => {
+                Easy21::actions().iter().map(|&action| {
+                    let features = self.features.to_feature_vector(state,
+                                                                   action);
+                    dot_product(&features, baseline)
+                }).sum()
+            },
+            None => 0.0,
+        }
+    }
+
+    // Updates the baseline and the policy towards the return that
+    // followed the given step. `discounting` is the discount raised to
+    // the number of steps that preceded it in the episode.
+    fn update(&mut self, state: State, action: Action, episode_return: Reward,
+              discounting: f32) {
+        let error = episode_return - self.state_value(state);
+        if let Some(ref mut baseline) = self.baseline {
+            let scale = self.baseline_step_size * error;
+            for &other in Easy21::actions().iter() {
+                for &(index, feature) in self.features
+                                             .to_feature_vector(state, other)
+                                             .iter() {
+                    baseline[index] += scale * feature;
+                }
+            }
+        }
+
+        // The gradient of the log probability of the action is its
+        // features minus the features of every action, weighted by their
+        // probabilities, as in equation 13.9.
+        let probabilities = self.action_probabilities(state);
+        let scale = self.step_size * discounting * error;
+        for (i, &other) in Easy21::actions().iter().enumerate() {
+            let chosen = if other == action { 1.0 } else { 0.0 };
+            let coefficient = scale * (chosen - probabilities[i]);
+            for &(index, feature) in self.features
+                                         .to_feature_vector(state, other)
+                                         .iter() {
+                self.weights[index] += coefficient * feature;
+            }
+        }
+    }
+}
+
+impl<T: Rng> Policy for Reinforce<T> {
+    fn choose_action(&mut self, state: State) -> Action {
+        let probabilities = self.action_probabilities(state);
+        Easy21::actions()[sample_action(&probabilities, &mut self.rng)]
+    }
+
+    fn on_episode_begin(&mut self) {
+        self.steps.clear();
+    }
+
+    fn on_episode_step(&mut self, state: State, action: Action,
+                       reward: Reward, _: State) -> Option<Action> {
+        self.steps.push((state, action, reward));
+        None
+    }
+
+    fn on_episode_end(&mut self) {
+        let mut returns = vec![0.0; self.steps.len()];
+        let mut episode_return = 0.0;
+        for (i, &(_, _, reward)) in self.steps.iter().enumerate().rev() {
+            episode_return = reward + self.discount * episode_return;
+            returns[i] = episode_return;
+        }
+
+        let steps = std::mem::take(&mut self.steps);
+        let mut discounting = 1.0;
+        for (&(state, action, _), &episode_return) in steps.iter()
+                                                           .zip(returns.iter()) {
+            self.update(state, action, episode_return, discounting);
+            discounting *= self.discount;
+        }
+    }
+}
+
+impl<T: Rng> Checkpointable for Reinforce<T> {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg("reinforce");
+        checkpoint.set_values("weights", &self.weights);
+        if let Some(ref baseline) = self.baseline {
+            checkpoint.set_values("baseline_weights", baseline);
+        }
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg("reinforce")?;
+        let num_features = self.weights.len();
+        let weights = get_weights(checkpoint, "weights", num_features)?;
+        if self.baseline.is_some() {
+            self.baseline = Some(get_weights(checkpoint, "baseline_weights",
+                                             num_features)?);
+        }
+        self.weights = weights;
+        Ok(())
+    }
+}
+
+fn get_weights(checkpoint: &Checkpoint, name: &str,
+               num_features: usize) -> Result<Vec<f32>, CheckpointError> {
+    let weights = checkpoint.get_values(name)?;
+    if weights.len() != num_features {
+        return Err(CheckpointError::Parse(format!(
+            "expected {} weights, found {}", num_features, weights.len()
+        )));
+    }
+    Ok(weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, StdRng};
+
+    use reinforce::*;
+    use game::Action::*;
+    use features::OneHotFeatures;
+
+    const A: State = State { dealer: 5, player: 10 };
+    const END: State = State { dealer: 5, player: 25 };
+
+    fn rng() -> StdRng {
+        SeedableRng::from_seed(&[1, 2, 3, 4][..])
+    }
+
+    // Plays an episode in which we stick at A and get the given reward.
+    fn stick<T: Rng>(policy: &mut Reinforce<T>, reward: Reward) {
+        policy.on_episode_begin();
+        policy.on_episode_step(A, Stick, reward, END);
+        policy.on_episode_end();
+    }
+
+    #[test]
+    fn test_actions_start_out_equally_likely() {
+        let policy = Reinforce::new(rng(), DEFAULT_STEP_SIZE);
+
+        assert_eq!(policy.action_probabilities(A), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_rewarded_actions_become_more_likely() {
+        let mut policy = Reinforce::new(rng(), 0.5)
+          .with_features(OneHotFeatures);
+
+        stick(&mut policy, 1.0);
+
+        // The gradient of ln pi(Stick) is +/- 0.5 for each action's
+        // preference, so they move apart by 0.5.
+        let probabilities = policy.action_probabilities(A);
+        assert!((probabilities[1] - 1.0 / (1.0 + (-0.5f32).exp())).abs()
+                < 1e-6);
+
+        stick(&mut policy, -1.0);
+        stick(&mut policy, -1.0);
+        assert!(policy.action_probabilities(A)[1] < 0.5);
+    }
+
+    #[test]
+    fn test_baseline_learns_state_values() {
+        let mut policy = Reinforce::new(rng(), 0.0)
+          .with_features(OneHotFeatures)
+          .with_baseline(0.1);
+
+        for _ in 0..200 {
+            stick(&mut policy, 1.0);
+        }
+
+        assert!((policy.state_value(A) - 1.0).abs() < 1e-3);
+
+        // The baseline now predicts the return, so the policy doesn't
+        // change even with a nonzero step size.
+        policy.step_size = 0.5;
+        stick(&mut policy, 1.0);
+        assert!((policy.action_probabilities(A)[1] - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_earlier_steps_get_whole_return() {
+        let mut policy = Reinforce::new(rng(), 0.5)
+          .with_features(OneHotFeatures);
+        let next = State { dealer: 5, player: 15 };
+
+        policy.on_episode_begin();
+        policy.on_episode_step(A, Hit, 0.0, next);
+        policy.on_episode_step(next, Stick, 1.0, END);
+        policy.on_episode_end();
+
+        assert!(policy.action_probabilities(A)[0] > 0.5);
+        assert!(policy.action_probabilities(next)[1] > 0.5);
+    }
+
+    #[test]
+    fn test_checkpoints_work() {
+        let mut policy = Reinforce::new(rng(), 0.5).with_baseline(0.1);
+        stick(&mut policy, 1.0);
+        let mut checkpoint = Checkpoint::new();
+        policy.save(&mut checkpoint);
+
+        let mut loaded = Reinforce::new(rng(), 0.5).with_baseline(0.1);
+        loaded.load(&checkpoint).unwrap();
+
+        assert_eq!(loaded.weights, policy.weights);
+        assert_eq!(loaded.baseline, policy.baseline);
+
+        let mut one_hot = Reinforce::new(rng(), 0.5)
+          .with_features(OneHotFeatures);
+        assert!(one_hot.load(&checkpoint).is_err());
+    }
+}
@@ -1,66 +1,96 @@
-use std::collections::HashMap;
-
-use game::{State, Action, Reward};
-use game::Action::*;
+use game::{Reward, Easy21};
+use environment::Environment;
 use gpi::Alg;
-use util::{increment, VaryingStepSizer};
-
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+use util::{best_action, StepSizer, ValueTable, Traces};
+use traces::TraceType;
 
-type EligibilityHash = HashMap<(State, Action), f32>;
-type ValueFn = HashMap<(State, Action), Reward>;
-
-pub struct SarsaLambda {
-    value_fn: ValueFn,
-    step_sizer: VaryingStepSizer,
-    traces: EligibilityHash,
+pub struct SarsaLambda<E: Environment = Easy21> {
+    value_fn: ValueTable<E>,
+    step_sizer: StepSizer<E>,
+    traces: Traces<E>,
+    trace_type: TraceType,
     lambda: f32,
+    discount: f32,
 }
 
-impl SarsaLambda {
+impl<E: Environment> SarsaLambda<E> {
     pub fn new(lambda: f32) -> Self {
         SarsaLambda {
-            value_fn: HashMap::new(),
-            step_sizer: VaryingStepSizer::new(),
-            traces: HashMap::new(),
+            value_fn: ValueTable::new(),
+            step_sizer: StepSizer::new(None),
+            traces: Traces::new(),
+            trace_type: TraceType::default(),
             lambda,
+            discount: 1.0,
         }
     }
+
+    pub fn with_constant_step_size(mut self, value: f32) -> Self {
+        self.step_sizer = StepSizer::new(Some(value));
+        self
+    }
+
+    pub fn with_trace_type(mut self, trace_type: TraceType) -> Self {
+        self.trace_type = trace_type;
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f32) -> Self {
+        self.discount = discount;
+        self
+    }
 }
 
-impl Alg for SarsaLambda {
-    fn choose_best_action(&self, state: State) -> Action {
-        let hit = *self.value_fn.get(&(state, Hit)).unwrap_or(&0.0);
-        let stick = *self.value_fn.get(&(state, Stick)).unwrap_or(&0.0);
-        if hit > stick { Hit } else { Stick }
+impl<E: Environment> Alg<E> for SarsaLambda<E> {
+    fn choose_best_action(&self, state: E::State) -> E::Action {
+        best_action(E::actions(), |action| {
+            self.get_expected_reward(state, action)
+        })
     }
 
-    fn get_expected_reward(&self, state: State, action: Action) -> Reward {
-        *self.value_fn.get(&(state, action)).unwrap_or(&0.0)
+    fn get_expected_reward(&self, state: E::State,
+                           action: E::Action) -> Reward {
+        self.value_fn.get(state, action)
     }
 
     fn on_episode_begin(&mut self) {
-        self.traces.drain();
+        self.traces.clear();
     }
 
     fn needs_next_action(&self) -> bool {
         true
     }
 
-    fn on_episode_step(&mut self, state: State, action: Action,
-                       reward: Reward, next_state: State,
-                       next_action: Option<Action>) -> Option<Action> {
+    fn on_episode_step(&mut self, state: E::State, action: E::Action,
+                       reward: Reward, next_state: E::State,
+                       next_action: Option<E::Action>) -> Option<E::Action> {
         let step_size = self.step_sizer.update(state, action);
         let td_error = reward +
+                       self.discount *
                        self.get_expected_reward(next_state,
                                                 next_action.unwrap()) -
                        self.get_expected_reward(state, action);
-        increment(&mut self.traces, (state, action), 1.0);
-        for (&(state, action), trace) in self.traces.iter_mut() {
-            let eligibility_trace = *trace;
-            increment(&mut self.value_fn, (state, action),
-                      step_size * td_error * eligibility_trace);
-            *trace = self.lambda * eligibility_trace;
-        }
+        self.traces.visit(state, action, self.trace_type, step_size);
+        self.value_fn.add_traces(&self.traces, step_size * td_error);
+        self.traces.decay(self.discount * self.lambda);
         next_action
     }
 }
+
+impl Checkpointable for SarsaLambda {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        checkpoint.set_alg("sarsa");
+        checkpoint.set_value_table("value_fn", &self.value_fn.to_hash_map());
+        self.step_sizer.save(checkpoint);
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        checkpoint.expect_alg("sarsa")?;
+        self.value_fn = ValueTable::from_hash_map(
+            &checkpoint.get_value_table("value_fn")?
+        )?;
+        self.step_sizer.load(checkpoint)?;
+        Ok(())
+    }
+}
@@ -1,49 +1,144 @@
 use rand::{SeedableRng, StdRng};
 
-use gpi::{Gpi, EpsilonGreedyPolicy};
+use gpi::{Alg, Policy, Gpi, EpsilonGreedyPolicy, DEFAULT_N0};
+use exploration::{BoltzmannPolicy, UcbPolicy, DEFAULT_TEMPERATURE,
+                  DEFAULT_UCB_C};
 use montecarlo::MonteCarlo;
+use off_policy_montecarlo::{OffPolicyMonteCarlo, Sampling};
 use sarsa::SarsaLambda;
 use qlearning::QLearning;
-use lfa::LinearFunctionApproximator;
-use game::RngDeck;
+use expected_sarsa::ExpectedSarsa;
+use double_qlearning::DoubleQLearning;
+use nstep::NStep;
+use lfa::{LinearFunctionApproximator, DEFAULT_STEP_SIZE};
+use mlp::{self, MultilayerPerceptron, NetworkShape};
+use dqn::{Dqn, ReplaySettings};
+use reinforce::{self, Reinforce};
+use game::{RngDeck, Easy21, Rules};
+use blackjack::Blackjack;
+use environment::Environment;
+use traces::TraceType;
+use features::Features;
 
-pub fn run_monte_carlo(episodes: i32) -> Gpi<RngDeck<StdRng>, EpsilonGreedyPolicy<StdRng, MonteCarlo>> {
-    let seed: &[_] = &[1, 2, 3, 4];
-    let rng: StdRng = SeedableRng::from_seed(seed);
-    let deck = RngDeck::new(rng);
-    let mc_alg = MonteCarlo::new();
-    let policy = EpsilonGreedyPolicy::new(rng, mc_alg);
-    let mut gpi = Gpi::new(deck, policy);
+// Settings shared by all the shortcuts.
+pub struct Config {
+    // The seed for the random number generator used by both the deck
+    // and the policy.
+    pub seed: Vec<usize>,
 
-    if episodes > 0 {
-        gpi.play_episodes(episodes);
+    // If None, epsilon will vary based on the number of visits to
+    // each state, using n0.
+    pub epsilon: Option<f32>,
+    pub n0: f32,
+
+    // If None, the step size will vary based on the number of visits
+    // to each state/action pair (or, for function approximation, will
+    // be its DEFAULT_STEP_SIZE).
+    pub step_size: Option<f32>,
+
+    // The kind of eligibility traces used by the lambda-based learners.
+    pub trace_type: TraceType,
+
+    // How much each learner discounts future rewards, i.e. gamma.
+    pub discount: f32,
+
+    // Whether Monte Carlo updates every visit to a state/action pair,
+    // rather than only the first one in each episode.
+    pub every_visit: bool,
+
+    // Whether every episode starts at a random state and action.
+    pub exploring_starts: bool,
+
+    // The initial temperature of Boltzmann exploration, and how much it's
+    // multiplied by after every episode.
+    pub temperature: f32,
+    pub temperature_decay: f32,
+
+    // The degree of exploration of UCB exploration.
+    pub ucb_c: f32,
+
+    // The features used by linear function approximation.
+    pub features: Features,
+
+    // The shape of the networks used by neural function approximation.
+    pub network: NetworkShape,
+
+    // How DQN replays experience and syncs its target network.
+    pub replay: ReplaySettings,
+
+    // If present, REINFORCE learns a state-value baseline with this step
+    // size.
+    pub baseline_step_size: Option<f32>,
+
+    // The rules of Easy21, which must pass Rules::validate(). These are
+    // ignored by Blackjack.
+    pub rules: Rules,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            seed: vec![1, 2, 3, 4],
+            epsilon: None,
+            n0: DEFAULT_N0,
+            step_size: None,
+            trace_type: TraceType::default(),
+            discount: 1.0,
+            every_visit: false,
+            exploring_starts: false,
+            temperature: DEFAULT_TEMPERATURE,
+            temperature_decay: 1.0,
+            ucb_c: DEFAULT_UCB_C,
+            features: Features::default(),
+            network: NetworkShape::default(),
+            replay: ReplaySettings::default(),
+            baseline_step_size: None,
+            rules: Rules::default(),
+        }
     }
+}
 
-    gpi
+fn new_rng(config: &Config) -> StdRng {
+    SeedableRng::from_seed(&config.seed[..])
 }
 
-pub fn run_sarsa(episodes: i32, lambda: f32) -> Gpi<RngDeck<StdRng>, EpsilonGreedyPolicy<StdRng, SarsaLambda>> {
-    let seed: &[_] = &[1, 2, 3, 4];
-    let rng: StdRng = SeedableRng::from_seed(seed);
-    let deck = RngDeck::new(rng);
-    let sarsa_alg = SarsaLambda::new(lambda);
-    let policy = EpsilonGreedyPolicy::new(rng, sarsa_alg);
-    let mut gpi = Gpi::new(deck, policy);
+// Salts appended to the seed of rngs that shouldn't draw the same
+// numbers as the deck and the policy.
+const DOUBLE_Q_LEARNING_SALT: usize = 1;
+const EXPLORING_STARTS_SALT: usize = 2;
 
-    if episodes > 0 {
-        gpi.play_episodes(episodes);
+// Returns an rng whose seed is the config's with the salt appended, so
+// that its draws aren't correlated with those of new_rng().
+fn new_salted_rng(config: &Config, salt: usize) -> StdRng {
+    let mut seed = config.seed.clone();
+    seed.push(salt);
+    SeedableRng::from_seed(&seed[..])
+}
+
+fn epsilon_greedy<E: Environment, T: Alg<E>>(alg: T, config: &Config) -> EpsilonGreedyPolicy<StdRng, T, E> {
+    let policy = EpsilonGreedyPolicy::new(new_rng(config), alg);
+    match config.epsilon {
+        None => policy.with_varying_epsilon(config.n0),
+        Some(epsilon) => policy.with_constant_epsilon(epsilon),
     }
+}
 
-    gpi
+fn boltzmann<E: Environment, T: Alg<E>>(alg: T, config: &Config) -> BoltzmannPolicy<StdRng, T, E> {
+    BoltzmannPolicy::new(new_rng(config), alg)
+      .with_temperature(config.temperature)
+      .with_temperature_decay(config.temperature_decay)
+}
+
+fn ucb<E: Environment, T: Alg<E>>(alg: T, config: &Config) -> UcbPolicy<T, E> {
+    UcbPolicy::new(alg).with_c(config.ucb_c)
 }
 
-pub fn run_q_learning(episodes: i32, lambda: f32) -> Gpi<RngDeck<StdRng>, EpsilonGreedyPolicy<StdRng, QLearning>> {
-    let seed: &[_] = &[1, 2, 3, 4];
-    let rng: StdRng = SeedableRng::from_seed(seed);
-    let deck = RngDeck::new(rng);
-    let qlearning_alg = QLearning::new(lambda);
-    let policy = EpsilonGreedyPolicy::new(rng, qlearning_alg);
-    let mut gpi = Gpi::new(deck, policy);
+fn run_gpi<E: Environment, P: Policy<E>>(episodes: i32, env: E, policy: P, config: &Config) -> Gpi<E, P> {
+    let mut gpi = Gpi::new(env, policy);
+    if config.exploring_starts {
+        let rng = new_salted_rng(config, EXPLORING_STARTS_SALT);
+        gpi = gpi.with_exploring_starts(rng);
+    }
 
     if episodes > 0 {
         gpi.play_episodes(episodes);
@@ -52,27 +147,433 @@ pub fn run_q_learning(episodes: i32, lambda: f32) -> Gpi<RngDeck<StdRng>, Epsilo
     gpi
 }
 
-pub fn run_lfa(episodes: i32, lambda: f32, epsilon: f32, step_size: f32) -> Gpi<RngDeck<StdRng>, EpsilonGreedyPolicy<StdRng, LinearFunctionApproximator>> {
-    let seed: &[_] = &[1, 2, 3, 4];
-    let rng: StdRng = SeedableRng::from_seed(seed);
-    let deck = RngDeck::new(rng);
-    let lfa_alg = LinearFunctionApproximator::new(lambda, step_size);
-    let policy = EpsilonGreedyPolicy::new(rng, lfa_alg).with_constant_epsilon(epsilon);
-    let mut gpi = Gpi::new(deck, policy);
+fn easy21(config: &Config) -> Easy21 {
+    let deck = RngDeck::new(new_rng(config)).with_rules(config.rules)
+      .unwrap_or_else(|err| panic!("invalid rules: {}", err));
+    Easy21::new(deck)
+}
 
-    if episodes > 0 {
-        gpi.play_episodes(episodes);
+fn blackjack(config: &Config) -> Blackjack {
+    Blackjack::new(new_rng(config))
+}
+
+// Runs GPI on Easy21 with the given algorithm, exploring via
+// epsilon-greedy actions.
+pub fn run_easy21<T: Alg>(episodes: i32, alg: T, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, T>> {
+    run_gpi(episodes, easy21(config), epsilon_greedy(alg, config), config)
+}
+
+pub fn run_easy21_boltzmann<T: Alg>(episodes: i32, alg: T, config: &Config) -> Gpi<Easy21, BoltzmannPolicy<StdRng, T>> {
+    run_gpi(episodes, easy21(config), boltzmann(alg, config), config)
+}
+
+pub fn run_easy21_ucb<T: Alg>(episodes: i32, alg: T, config: &Config) -> Gpi<Easy21, UcbPolicy<T>> {
+    run_gpi(episodes, easy21(config), ucb(alg, config), config)
+}
+
+pub fn run_blackjack<T: Alg<Blackjack>>(episodes: i32, alg: T, config: &Config) -> Gpi<Blackjack, EpsilonGreedyPolicy<StdRng, T, Blackjack>> {
+    run_gpi(episodes, blackjack(config), epsilon_greedy(alg, config), config)
+}
+
+pub fn run_blackjack_boltzmann<T: Alg<Blackjack>>(episodes: i32, alg: T, config: &Config) -> Gpi<Blackjack, BoltzmannPolicy<StdRng, T, Blackjack>> {
+    run_gpi(episodes, blackjack(config), boltzmann(alg, config), config)
+}
+
+pub fn run_blackjack_ucb<T: Alg<Blackjack>>(episodes: i32, alg: T, config: &Config) -> Gpi<Blackjack, UcbPolicy<T, Blackjack>> {
+    run_gpi(episodes, blackjack(config), ucb(alg, config), config)
+}
+
+// The algorithms below are configured by `config`, and can be run with
+// any of the policies above.
+pub fn monte_carlo<E: Environment>(config: &Config) -> MonteCarlo<E> {
+    let mut mc_alg = MonteCarlo::new().with_discount(config.discount);
+    if let Some(step_size) = config.step_size {
+        mc_alg = mc_alg.with_constant_step_size(step_size);
     }
+    if config.every_visit {
+        mc_alg = mc_alg.with_every_visit();
+    }
+    mc_alg
+}
 
-    gpi
+pub fn sarsa<E: Environment>(lambda: f32, config: &Config) -> SarsaLambda<E> {
+    let mut sarsa_alg = SarsaLambda::new(lambda)
+      .with_trace_type(config.trace_type)
+      .with_discount(config.discount);
+    if let Some(step_size) = config.step_size {
+        sarsa_alg = sarsa_alg.with_constant_step_size(step_size);
+    }
+    sarsa_alg
+}
+
+pub fn q_learning<E: Environment>(lambda: f32, config: &Config) -> QLearning<E> {
+    let mut qlearning_alg = QLearning::new(lambda)
+      .with_trace_type(config.trace_type)
+      .with_discount(config.discount);
+    if let Some(step_size) = config.step_size {
+        qlearning_alg = qlearning_alg.with_constant_step_size(step_size);
+    }
+    qlearning_alg
+}
+
+pub fn expected_sarsa<E: Environment>(lambda: f32, config: &Config) -> ExpectedSarsa<E> {
+    let mut expected_sarsa_alg = ExpectedSarsa::new(lambda)
+      .with_trace_type(config.trace_type)
+      .with_discount(config.discount);
+    if let Some(step_size) = config.step_size {
+        expected_sarsa_alg = expected_sarsa_alg.with_constant_step_size(step_size);
+    }
+    expected_sarsa_alg
+}
+
+pub fn double_q_learning<E: Environment>(config: &Config) -> DoubleQLearning<E> {
+    let rng = new_salted_rng(config, DOUBLE_Q_LEARNING_SALT);
+    let mut double_qlearning_alg = DoubleQLearning::new(rng)
+      .with_discount(config.discount);
+    if let Some(step_size) = config.step_size {
+        double_qlearning_alg = double_qlearning_alg.with_constant_step_size(step_size);
+    }
+    double_qlearning_alg
+}
+
+pub fn n_step<E: Environment>(alg: NStep<E>, config: &Config) -> NStep<E> {
+    let alg = alg.with_discount(config.discount);
+    match config.step_size {
+        None => alg,
+        Some(step_size) => alg.with_constant_step_size(step_size),
+    }
+}
+
+pub fn run_monte_carlo(episodes: i32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, MonteCarlo>> {
+    run_easy21(episodes, monte_carlo(config), config)
+}
+
+pub fn off_policy_monte_carlo<E: Environment>(sampling: Sampling, config: &Config) -> OffPolicyMonteCarlo<E> {
+    OffPolicyMonteCarlo::new(sampling).with_discount(config.discount)
+}
+
+pub fn run_off_policy_monte_carlo(episodes: i32, sampling: Sampling, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, OffPolicyMonteCarlo>> {
+    run_easy21(episodes, off_policy_monte_carlo(sampling, config), config)
+}
+
+pub fn run_sarsa(episodes: i32, lambda: f32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, SarsaLambda>> {
+    run_easy21(episodes, sarsa(lambda, config), config)
+}
+
+pub fn run_q_learning(episodes: i32, lambda: f32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, QLearning>> {
+    run_easy21(episodes, q_learning(lambda, config), config)
+}
+
+// Q(lambda) as implemented by earlier versions of this crate, which
+// never cuts its eligibility traces.
+pub fn run_naive_q_learning(episodes: i32, lambda: f32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, QLearning>> {
+    run_easy21(episodes, q_learning(lambda, config).with_naive_traces(), config)
+}
+
+pub fn run_expected_sarsa(episodes: i32, lambda: f32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, ExpectedSarsa>> {
+    run_easy21(episodes, expected_sarsa(lambda, config), config)
+}
+
+pub fn run_double_q_learning(episodes: i32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, DoubleQLearning>> {
+    run_easy21(episodes, double_q_learning(config), config)
+}
+
+pub fn run_n_step_sarsa(episodes: i32, n: usize, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, NStep>> {
+    run_easy21(episodes, n_step(NStep::sarsa(n), config), config)
+}
+
+pub fn run_n_step_tree_backup(episodes: i32, n: usize, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, NStep>> {
+    run_easy21(episodes, n_step(NStep::tree_backup(n), config), config)
+}
+
+pub fn lfa(lambda: f32, config: &Config) -> LinearFunctionApproximator {
+    let step_size = config.step_size.unwrap_or(DEFAULT_STEP_SIZE);
+    LinearFunctionApproximator::new(lambda, step_size)
+      .with_trace_type(config.trace_type)
+      .with_discount(config.discount)
+      .with_features(config.features.extractor())
+}
+
+pub fn run_lfa(episodes: i32, lambda: f32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, LinearFunctionApproximator>> {
+    run_easy21(episodes, lfa(lambda, config), config)
+}
+
+pub fn run_true_online_lfa(episodes: i32, lambda: f32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, LinearFunctionApproximator>> {
+    run_easy21(episodes, lfa(lambda, config).with_true_online_updates(), config)
+}
+
+pub fn mlp(config: &Config) -> MultilayerPerceptron {
+    let step_size = config.step_size.unwrap_or(mlp::DEFAULT_STEP_SIZE);
+    MultilayerPerceptron::new(config.network, step_size, &mut new_rng(config))
+      .with_discount(config.discount)
+}
+
+pub fn run_mlp(episodes: i32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, MultilayerPerceptron>> {
+    run_easy21(episodes, mlp(config), config)
+}
+
+pub fn run_mlp_q_learning(episodes: i32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, MultilayerPerceptron>> {
+    run_easy21(episodes, mlp(config).with_q_learning(), config)
+}
+
+pub fn dqn(config: &Config) -> Dqn {
+    let step_size = config.step_size.unwrap_or(mlp::DEFAULT_STEP_SIZE);
+    Dqn::new(config.network, step_size, new_rng(config))
+      .with_replay(config.replay)
+      .with_discount(config.discount)
+}
+
+pub fn run_dqn(episodes: i32, config: &Config) -> Gpi<Easy21, EpsilonGreedyPolicy<StdRng, Dqn>> {
+    run_easy21(episodes, dqn(config), config)
+}
+
+pub fn reinforce(config: &Config) -> Reinforce<StdRng> {
+    let step_size = config.step_size.unwrap_or(reinforce::DEFAULT_STEP_SIZE);
+    let mut policy = Reinforce::new(new_rng(config), step_size)
+      .with_features(config.features.extractor())
+      .with_discount(config.discount);
+    if let Some(baseline_step_size) = config.baseline_step_size {
+        policy = policy.with_baseline(baseline_step_size);
+    }
+    policy
+}
+
+// REINFORCE is its own policy, so it doesn't explore via epsilon-greedy
+// actions or any of the other policies above.
+pub fn run_reinforce(episodes: i32, config: &Config) -> Gpi<Easy21, Reinforce<StdRng>> {
+    run_gpi(episodes, easy21(config), reinforce(config), config)
+}
+
+pub fn run_blackjack_monte_carlo(episodes: i32, config: &Config) -> Gpi<Blackjack, EpsilonGreedyPolicy<StdRng, MonteCarlo<Blackjack>, Blackjack>> {
+    run_blackjack(episodes, monte_carlo(config), config)
+}
+
+pub fn run_blackjack_off_policy_monte_carlo(episodes: i32, sampling: Sampling, config: &Config) -> Gpi<Blackjack, EpsilonGreedyPolicy<StdRng, OffPolicyMonteCarlo<Blackjack>, Blackjack>> {
+    run_blackjack(episodes, off_policy_monte_carlo(sampling, config), config)
+}
+
+pub fn run_blackjack_sarsa(episodes: i32, lambda: f32, config: &Config) -> Gpi<Blackjack, EpsilonGreedyPolicy<StdRng, SarsaLambda<Blackjack>, Blackjack>> {
+    run_blackjack(episodes, sarsa(lambda, config), config)
+}
+
+pub fn run_blackjack_q_learning(episodes: i32, lambda: f32, config: &Config) -> Gpi<Blackjack, EpsilonGreedyPolicy<StdRng, QLearning<Blackjack>, Blackjack>> {
+    run_blackjack(episodes, q_learning(lambda, config), config)
+}
+
+pub fn run_blackjack_naive_q_learning(episodes: i32, lambda: f32, config: &Config) -> Gpi<Blackjack, EpsilonGreedyPolicy<StdRng, QLearning<Blackjack>, Blackjack>> {
+    run_blackjack(episodes, q_learning(lambda, config).with_naive_traces(), config)
+}
+
+pub fn run_blackjack_expected_sarsa(episodes: i32, lambda: f32, config: &Config) -> Gpi<Blackjack, EpsilonGreedyPolicy<StdRng, ExpectedSarsa<Blackjack>, Blackjack>> {
+    run_blackjack(episodes, expected_sarsa(lambda, config), config)
+}
+
+pub fn run_blackjack_double_q_learning(episodes: i32, config: &Config) -> Gpi<Blackjack, EpsilonGreedyPolicy<StdRng, DoubleQLearning<Blackjack>, Blackjack>> {
+    run_blackjack(episodes, double_q_learning(config), config)
+}
+
+pub fn run_blackjack_n_step_sarsa(episodes: i32, n: usize, config: &Config) -> Gpi<Blackjack, EpsilonGreedyPolicy<StdRng, NStep<Blackjack>, Blackjack>> {
+    run_blackjack(episodes, n_step(NStep::sarsa(n), config), config)
+}
+
+pub fn run_blackjack_n_step_tree_backup(episodes: i32, n: usize, config: &Config) -> Gpi<Blackjack, EpsilonGreedyPolicy<StdRng, NStep<Blackjack>, Blackjack>> {
+    run_blackjack(episodes, n_step(NStep::tree_backup(n), config), config)
 }
 
 #[cfg(test)]
 mod tests {
     use shortcuts::*;
+    use game::State;
+    use game::Action::Hit;
+    use checkpoint::{Checkpoint, Checkpointable};
+    use features::{TileCoding, PolynomialFeatures};
+    use mlp::Activation;
 
     #[test]
     fn test_run_sarsa_works() {
-        run_sarsa(3, 0.5);
+        run_sarsa(3, 0.5, &Config::default());
+    }
+
+    #[test]
+    fn test_run_blackjack_works() {
+        run_blackjack_monte_carlo(3, &Config::default());
+        run_blackjack_sarsa(3, 0.5, &Config::default());
+        run_blackjack_q_learning(3, 0.5, &Config::default());
+        run_blackjack_expected_sarsa(3, 0.5, &Config::default());
+        run_blackjack_double_q_learning(3, &Config::default());
+        run_blackjack_off_policy_monte_carlo(3, Sampling::Weighted,
+                                             &Config::default());
+        run_blackjack_n_step_sarsa(3, 2, &Config::default());
+        run_blackjack_n_step_tree_backup(3, 2, &Config::default());
+    }
+
+    #[test]
+    fn test_run_monte_carlo_with_exploring_starts_works() {
+        let config = Config {
+            every_visit: true,
+            exploring_starts: true,
+            ..Config::default()
+        };
+        run_monte_carlo(3, &config);
+        run_blackjack_monte_carlo(3, &config);
+    }
+
+    #[test]
+    fn test_run_off_policy_monte_carlo_works() {
+        run_off_policy_monte_carlo(3, Sampling::Ordinary, &Config::default());
+        run_off_policy_monte_carlo(3, Sampling::Weighted, &Config::default());
+    }
+
+    #[test]
+    fn test_run_n_step_works() {
+        run_n_step_sarsa(3, 4, &Config::default());
+        run_n_step_tree_backup(3, 4, &Config::default());
+    }
+
+    #[test]
+    fn test_run_with_other_policies_works() {
+        let config = Config {
+            temperature: 0.5,
+            temperature_decay: 0.99,
+            ucb_c: 2.0,
+            ..Config::default()
+        };
+        run_easy21_boltzmann(3, sarsa(0.5, &config), &config);
+        run_easy21_ucb(3, off_policy_monte_carlo(Sampling::Weighted, &config),
+                       &config);
+        run_blackjack_boltzmann(3, q_learning(0.5, &config), &config);
+        run_blackjack_ucb(3, expected_sarsa(0.5, &config), &config);
+    }
+
+    #[test]
+    fn test_run_naive_q_learning_works() {
+        run_naive_q_learning(3, 0.5, &Config::default());
+    }
+
+    #[test]
+    fn test_run_expected_sarsa_works() {
+        run_expected_sarsa(3, 0.5, &Config::default());
+    }
+
+    #[test]
+    fn test_run_double_q_learning_works() {
+        run_double_q_learning(3, &Config::default());
+    }
+
+    #[test]
+    fn test_run_q_learning_works() {
+        run_q_learning(3, 0.5, &Config::default());
+        run_q_learning(3, 0.5, &Config {
+            epsilon: Some(0.1),
+            step_size: Some(0.01),
+            trace_type: TraceType::Dutch,
+            ..Config::default()
+        });
+    }
+
+    #[test]
+    fn test_run_true_online_lfa_works() {
+        run_true_online_lfa(3, 0.5, &Config::default());
+    }
+
+    #[test]
+    fn test_run_lfa_with_other_features_works() {
+        run_lfa(3, 0.5, &Config {
+            features: Features::Tiles(TileCoding::new(2, 4, 4)),
+            ..Config::default()
+        });
+        run_true_online_lfa(3, 0.5, &Config {
+            features: Features::OneHot,
+            ..Config::default()
+        });
+        run_lfa(3, 0.5, &Config {
+            features: Features::Polynomial(PolynomialFeatures::new(3)),
+            ..Config::default()
+        });
+    }
+
+    #[test]
+    fn test_run_mlp_works() {
+        run_mlp(3, &Config::default());
+        run_mlp_q_learning(3, &Config {
+            network: NetworkShape {
+                hidden_layers: 2,
+                width: 4,
+                activation: Activation::Relu,
+            },
+            ..Config::default()
+        });
+    }
+
+    #[test]
+    fn test_run_dqn_works() {
+        run_dqn(3, &Config {
+            replay: ReplaySettings {
+                buffer_size: 5,
+                batch_size: 2,
+                sync_interval: 1,
+            },
+            ..Config::default()
+        });
+    }
+
+    #[test]
+    fn test_run_reinforce_works() {
+        run_reinforce(3, &Config::default());
+        run_reinforce(3, &Config {
+            baseline_step_size: Some(0.1),
+            features: Features::OneHot,
+            ..Config::default()
+        });
+    }
+
+    #[test]
+    fn test_discount_works() {
+        let config = Config { discount: 0.5, ..Config::default() };
+        let discounted = run_sarsa(1000, 0.5, &config);
+        let undiscounted = run_sarsa(1000, 0.5, &Config::default());
+        let total = |gpi: &Gpi<_, EpsilonGreedyPolicy<_, SarsaLambda>>| {
+            (1..11).map(|dealer| {
+                gpi.policy.alg.get_expected_reward(
+                    State { dealer, player: 10 }, Hit
+                )
+            }).sum::<f32>()
+        };
+
+        assert!(total(&discounted) != total(&undiscounted));
+    }
+
+    #[test]
+    fn test_seed_works() {
+        let config = Config { seed: vec![5], ..Config::default() };
+        let a = run_sarsa(100, 0.5, &config);
+        let b = run_sarsa(100, 0.5, &config);
+        let c = run_sarsa(100, 0.5, &Config::default());
+        let value = |gpi: &Gpi<_, EpsilonGreedyPolicy<_, SarsaLambda>>| {
+            (1..11).map(|dealer| {
+                gpi.policy.alg.get_expected_reward(
+                    ::game::State { dealer, player: 15 },
+                    ::game::Action::Hit
+                )
+            }).collect::<Vec<_>>()
+        };
+
+        assert_eq!(value(&a), value(&b));
+        assert_ne!(value(&a), value(&c));
+    }
+
+    #[test]
+    fn test_checkpoints_roundtrip() {
+        let trained = run_sarsa(100, 0.5, &Config::default());
+        let mut saved = Checkpoint::new();
+        trained.save(&mut saved);
+
+        let mut resumed = run_sarsa(0, 0.5, &Config::default());
+        resumed.load(&saved).unwrap();
+        let mut resaved = Checkpoint::new();
+        resumed.save(&mut resaved);
+
+        assert_eq!(resumed.episodes(), 100);
+        assert_eq!(saved, resaved);
     }
 }
@@ -0,0 +1,434 @@
+// This module runs a grid of hyperparameter settings in parallel, each
+// on its own thread-local Gpi instance, and summarizes how well each
+// setting did.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+
+use rand::StdRng;
+
+use game::{Reward, Rules, Easy21};
+use gpi::{Alg, Gpi, EpsilonGreedyPolicy, DEFAULT_N0};
+use nstep::DEFAULT_N;
+use dp::ValueIteration;
+use evaluation::{compare, ErrorStats};
+use shortcuts::{self, Config};
+use off_policy_montecarlo::Sampling;
+use traces::TraceType;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SweepAlg {
+    MonteCarlo,
+    OffPolicyMonteCarlo,
+    OrdinaryOffPolicyMonteCarlo,
+    Sarsa,
+    QLearning,
+    NaiveQLearning,
+    ExpectedSarsa,
+    DoubleQLearning,
+    NStepSarsa,
+    NStepTreeBackup,
+    Lfa,
+    TrueOnlineLfa,
+}
+
+impl SweepAlg {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SweepAlg::MonteCarlo => "mc",
+            SweepAlg::OffPolicyMonteCarlo => "off-policy-mc",
+            SweepAlg::OrdinaryOffPolicyMonteCarlo => "ordinary-off-policy-mc",
+            SweepAlg::Sarsa => "sarsa",
+            SweepAlg::QLearning => "qlearning",
+            SweepAlg::NaiveQLearning => "naive-qlearning",
+            SweepAlg::ExpectedSarsa => "expected-sarsa",
+            SweepAlg::DoubleQLearning => "double-qlearning",
+            SweepAlg::NStepSarsa => "nstep-sarsa",
+            SweepAlg::NStepTreeBackup => "tree-backup",
+            SweepAlg::Lfa => "lfa",
+            SweepAlg::TrueOnlineLfa => "true-online-lfa",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mc" => Some(SweepAlg::MonteCarlo),
+            "off-policy-mc" => Some(SweepAlg::OffPolicyMonteCarlo),
+            "ordinary-off-policy-mc" => {
+                Some(SweepAlg::OrdinaryOffPolicyMonteCarlo)
+            },
+            "sarsa" => Some(SweepAlg::Sarsa),
+            "qlearning" => Some(SweepAlg::QLearning),
+            "naive-qlearning" => Some(SweepAlg::NaiveQLearning),
+            "expected-sarsa" => Some(SweepAlg::ExpectedSarsa),
+            "double-qlearning" => Some(SweepAlg::DoubleQLearning),
+            "nstep-sarsa" => Some(SweepAlg::NStepSarsa),
+            "tree-backup" => Some(SweepAlg::NStepTreeBackup),
+            "lfa" => Some(SweepAlg::Lfa),
+            "true-online-lfa" => Some(SweepAlg::TrueOnlineLfa),
+            _ => None,
+        }
+    }
+
+    fn uses_lambda(&self) -> bool {
+        !matches!(*self, SweepAlg::MonteCarlo |
+                         SweepAlg::OffPolicyMonteCarlo |
+                         SweepAlg::OrdinaryOffPolicyMonteCarlo |
+                         SweepAlg::DoubleQLearning) &&
+        !self.uses_n()
+    }
+
+    fn uses_n(&self) -> bool {
+        matches!(*self, SweepAlg::NStepSarsa | SweepAlg::NStepTreeBackup)
+    }
+}
+
+// The settings to sweep over. Every combination of them is run, except
+// that Monte Carlo is only run once per combination of the other
+// settings, since it has no lambda or traces (and likewise for Double
+// Q-learning). The n-step learners are run once per n instead of once
+// per lambda and trace type. True online LFA always uses Dutch traces.
+pub struct SweepGrid {
+    pub algs: Vec<SweepAlg>,
+    pub lambdas: Vec<f32>,
+    pub trace_types: Vec<TraceType>,
+    pub ns: Vec<usize>,
+
+    // None means a varying epsilon or step size, as in shortcuts::Config.
+    pub epsilons: Vec<Option<f32>>,
+    pub step_sizes: Vec<Option<f32>>,
+
+    pub seeds: Vec<Vec<usize>>,
+    pub episodes: i32,
+    pub n0: f32,
+    pub discount: f32,
+    pub rules: Rules,
+}
+
+impl Default for SweepGrid {
+    fn default() -> Self {
+        SweepGrid {
+            algs: vec![SweepAlg::Sarsa],
+            lambdas: (0..11).map(|i| i as f32 / 10.0).collect(),
+            trace_types: vec![TraceType::default()],
+            ns: vec![DEFAULT_N],
+            epsilons: vec![None],
+            step_sizes: vec![None],
+            seeds: vec![vec![1, 2, 3, 4]],
+            episodes: 1000,
+            n0: DEFAULT_N0,
+            discount: 1.0,
+            rules: Rules::default(),
+        }
+    }
+}
+
+pub struct SweepJob {
+    pub alg: SweepAlg,
+    pub lambda: Option<f32>,
+    pub n: Option<usize>,
+    pub config: Config,
+}
+
+pub struct SweepResult {
+    pub job: SweepJob,
+    pub stats: ErrorStats,
+    pub mean_return: Reward,
+    pub seconds: f64,
+}
+
+impl SweepGrid {
+    // Returns the combinations of lambda, n and trace type that apply to
+    // the given algorithm.
+    fn alg_settings(&self, alg: SweepAlg) -> Vec<(Option<f32>, Option<usize>,
+                                                  TraceType)> {
+        if alg.uses_n() {
+            self.ns.iter().map(|&n| {
+                (None, Some(n), TraceType::default())
+            }).collect()
+        } else if alg == SweepAlg::TrueOnlineLfa {
+            self.lambdas.iter().map(|&lambda| {
+                (Some(lambda), None, TraceType::Dutch)
+            }).collect()
+        } else if alg.uses_lambda() {
+            self.lambdas.iter().flat_map(|&lambda| {
+                self.trace_types.iter().map(move |&trace_type| {
+                    (Some(lambda), None, trace_type)
+                })
+            }).collect()
+        } else {
+            vec![(None, None, TraceType::default())]
+        }
+    }
+
+    pub fn jobs(&self) -> Vec<SweepJob> {
+        let mut jobs = Vec::new();
+
+        for &alg in self.algs.iter() {
+            for (lambda, n, trace_type) in self.alg_settings(alg) {
+                for &epsilon in self.epsilons.iter() {
+                    for &step_size in self.step_sizes.iter() {
+                        for seed in self.seeds.iter() {
+                            jobs.push(SweepJob {
+                                alg,
+                                lambda,
+                                n,
+                                config: Config {
+                                    seed: seed.clone(),
+                                    epsilon,
+                                    n0: self.n0,
+                                    step_size,
+                                    trace_type,
+                                    discount: self.discount,
+                                    rules: self.rules,
+                                    ..Config::default()
+                                },
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        jobs
+    }
+}
+
+// Plays the given number of episodes, returning how far the learned
+// values are from the reference, along with the mean return.
+fn train<T: Alg>(mut gpi: Gpi<Easy21, EpsilonGreedyPolicy<StdRng, T>>,
+                 episodes: i32,
+                 reference: &ValueIteration) -> (ErrorStats, Reward) {
+    let mut total_reward = 0.0;
+    for _ in 0..episodes {
+        total_reward += gpi.play_episode();
+    }
+    let stats = compare(&gpi.policy.alg, reference);
+    (stats, total_reward / episodes as Reward)
+}
+
+fn run_job(job: SweepJob, episodes: i32,
+           reference: &ValueIteration) -> SweepResult {
+    let start = Instant::now();
+    let lambda = job.lambda.unwrap_or(0.0);
+    let n = job.n.unwrap_or(DEFAULT_N);
+    let config = &job.config;
+    let (stats, mean_return) = match job.alg {
+        SweepAlg::MonteCarlo => train(
+            shortcuts::run_monte_carlo(0, config), episodes, reference
+        ),
+        SweepAlg::OffPolicyMonteCarlo => train(
+            shortcuts::run_off_policy_monte_carlo(0, Sampling::Weighted,
+                                                  config),
+            episodes, reference
+        ),
+        SweepAlg::OrdinaryOffPolicyMonteCarlo => train(
+            shortcuts::run_off_policy_monte_carlo(0, Sampling::Ordinary,
+                                                  config),
+            episodes, reference
+        ),
+        SweepAlg::Sarsa => train(
+            shortcuts::run_sarsa(0, lambda, config), episodes, reference
+        ),
+        SweepAlg::QLearning => train(
+            shortcuts::run_q_learning(0, lambda, config), episodes, reference
+        ),
+        SweepAlg::NaiveQLearning => train(
+            shortcuts::run_naive_q_learning(0, lambda, config), episodes,
+            reference
+        ),
+        SweepAlg::ExpectedSarsa => train(
+            shortcuts::run_expected_sarsa(0, lambda, config), episodes,
+            reference
+        ),
+        SweepAlg::DoubleQLearning => train(
+            shortcuts::run_double_q_learning(0, config), episodes, reference
+        ),
+        SweepAlg::NStepSarsa => train(
+            shortcuts::run_n_step_sarsa(0, n, config), episodes, reference
+        ),
+        SweepAlg::NStepTreeBackup => train(
+            shortcuts::run_n_step_tree_backup(0, n, config), episodes,
+            reference
+        ),
+        SweepAlg::Lfa => train(
+            shortcuts::run_lfa(0, lambda, config), episodes, reference
+        ),
+        SweepAlg::TrueOnlineLfa => train(
+            shortcuts::run_true_online_lfa(0, lambda, config), episodes,
+            reference
+        ),
+    };
+    let elapsed = start.elapsed();
+
+    SweepResult {
+        job,
+        stats,
+        mean_return,
+        seconds: elapsed.as_secs() as f64 +
+                 elapsed.subsec_nanos() as f64 * 1e-9,
+    }
+}
+
+// Runs every job in the grid on a pool of the given number of threads,
+// comparing the learned values against the optimal ones. Results are
+// returned in the same order as SweepGrid::jobs().
+pub fn run_sweep(grid: &SweepGrid, threads: usize) -> Vec<SweepResult> {
+    let jobs: VecDeque<_> = grid.jobs().into_iter().enumerate().collect();
+    let num_jobs = jobs.len();
+    let queue = Arc::new(Mutex::new(jobs));
+    let reference = Arc::new(
+        ValueIteration::with_rules_and_discount(&grid.rules, grid.discount)
+    );
+    let episodes = grid.episodes;
+    let (sender, receiver) = mpsc::channel();
+    let mut workers = Vec::new();
+
+    for _ in 0..threads.max(1).min(num_jobs) {
+        let queue = queue.clone();
+        let reference = reference.clone();
+        let sender = sender.clone();
+        workers.push(thread::spawn(move || {
+            loop {
+                let next = queue.lock().unwrap().pop_front();
+                match next {
+                    None => break,
+                    Some((i, job)) => {
+                        let result = run_job(job, episodes, &reference);
+                        sender.send((i, result)).unwrap();
+                    }
+                }
+            }
+        }));
+    }
+    drop(sender);
+
+    let mut results: Vec<_> = receiver.iter().collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+fn format_option(value: Option<f32>, none: &str) -> String {
+    match value {
+        Some(value) => format!("{}", value),
+        None => String::from(none),
+    }
+}
+
+fn format_n(value: Option<usize>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::from("-"),
+    }
+}
+
+fn format_seed(seed: &[usize]) -> String {
+    let parts: Vec<String> = seed.iter().map(|n| n.to_string()).collect();
+    parts.join(",")
+}
+
+// Writes the results as a table with one row per job, with columns
+// separated by whitespace.
+pub fn write_table<W: Write>(results: &[SweepResult],
+                             output: &mut W) -> io::Result<()> {
+    writeln!(output,
+             "{:<22} {:>6} {:>3} {:>12} {:>8} {:>9} {:>10} {:>12} {:>11} \
+              {:>8}",
+             "alg", "lambda", "n", "traces", "epsilon", "step_size", "seed", "mse",
+             "mean_return", "seconds")?;
+    for result in results.iter() {
+        let job = &result.job;
+        writeln!(output,
+                 "{:<22} {:>6} {:>3} {:>12} {:>8} {:>9} {:>10} {:>12.6} \
+                  {:>11.4} {:>8.2}",
+                 job.alg.name(),
+                 format_option(job.lambda, "-"),
+                 format_n(job.n),
+                 if job.lambda.is_some() {
+                     job.config.trace_type.name()
+                 } else {
+                     "-"
+                 },
+                 format_option(job.config.epsilon, "varying"),
+                 format_option(job.config.step_size, "varying"),
+                 format_seed(&job.config.seed),
+                 result.stats.mean_squared_error,
+                 result.mean_return,
+                 result.seconds)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sweep::*;
+
+    fn grid() -> SweepGrid {
+        SweepGrid {
+            algs: vec![SweepAlg::MonteCarlo, SweepAlg::Sarsa],
+            lambdas: vec![0.0, 1.0],
+            trace_types: vec![TraceType::Replacing],
+            seeds: vec![vec![1], vec![2]],
+            episodes: 50,
+            ..SweepGrid::default()
+        }
+    }
+
+    #[test]
+    fn test_jobs_skip_lambda_for_monte_carlo() {
+        let jobs = grid().jobs();
+
+        assert_eq!(jobs.len(), 6);
+        assert_eq!(jobs[0].alg, SweepAlg::MonteCarlo);
+        assert_eq!(jobs[0].lambda, None);
+        assert_eq!(jobs[1].config.seed, vec![2]);
+        assert_eq!(jobs[2].lambda, Some(0.0));
+        assert_eq!(jobs[2].config.trace_type, TraceType::Replacing);
+        assert_eq!(jobs[5].lambda, Some(1.0));
+    }
+
+    #[test]
+    fn test_jobs_use_n_for_n_step_learners() {
+        let jobs = SweepGrid {
+            algs: vec![SweepAlg::NStepTreeBackup],
+            ns: vec![1, 8],
+            ..grid()
+        }.jobs();
+
+        assert_eq!(jobs.len(), 4);
+        assert_eq!(jobs[0].lambda, None);
+        assert_eq!(jobs[0].n, Some(1));
+        assert_eq!(jobs[3].n, Some(8));
+    }
+
+    #[test]
+    fn test_run_sweep_is_deterministic() {
+        let serial = run_sweep(&grid(), 1);
+        let parallel = run_sweep(&grid(), 4);
+
+        assert_eq!(serial.len(), 6);
+        for (a, b) in serial.iter().zip(parallel.iter()) {
+            assert_eq!(a.job.alg, b.job.alg);
+            assert_eq!(a.job.lambda, b.job.lambda);
+            assert_eq!(a.stats, b.stats);
+            assert_eq!(a.mean_return, b.mean_return);
+        }
+    }
+
+    #[test]
+    fn test_write_table_works() {
+        let mut output = Vec::new();
+        write_table(&run_sweep(&grid(), 2), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(output.lines().count(), 7);
+        assert!(output.lines().nth(1).unwrap().starts_with("mc "));
+    }
+}
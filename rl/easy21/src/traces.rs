@@ -0,0 +1,70 @@
+// This module defines the kinds of eligibility traces that the lambda
+// based learners support, as described in Sections 12.2 and 12.5 of
+// Sutton & Barto.
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum TraceType {
+    // Add 1 to a pair's trace every time it's visited.
+    #[default]
+    Accumulating,
+
+    // Reset a pair's trace to 1 every time it's visited. This keeps
+    // traces from growing beyond 1 when a pair is revisited within an
+    // episode, which can make learning unstable at high lambda.
+    Replacing,
+
+    // A mix of the two, which for tabular learners sets a pair's trace
+    // to (1 - step_size) * trace + 1 when it's visited.
+    Dutch,
+}
+
+pub const TRACE_TYPE_NAMES: &[&str] = &["accumulating", "replacing", "dutch"];
+
+impl TraceType {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TraceType::Accumulating => TRACE_TYPE_NAMES[0],
+            TraceType::Replacing => TRACE_TYPE_NAMES[1],
+            TraceType::Dutch => TRACE_TYPE_NAMES[2],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "accumulating" => Some(TraceType::Accumulating),
+            "replacing" => Some(TraceType::Replacing),
+            "dutch" => Some(TraceType::Dutch),
+            _ => None,
+        }
+    }
+
+    // Returns the new trace of a tabular state/action pair that was
+    // just visited, given its current trace.
+    pub fn visit(&self, trace: f32, step_size: f32) -> f32 {
+        match *self {
+            TraceType::Accumulating => trace + 1.0,
+            TraceType::Replacing => 1.0,
+            TraceType::Dutch => (1.0 - step_size) * trace + 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use traces::*;
+
+    #[test]
+    fn test_visit_works() {
+        assert_eq!(TraceType::Accumulating.visit(0.5, 0.1), 1.5);
+        assert_eq!(TraceType::Replacing.visit(0.5, 0.1), 1.0);
+        assert_eq!(TraceType::Dutch.visit(0.5, 0.5), 1.25);
+    }
+
+    #[test]
+    fn test_names_roundtrip() {
+        for &name in TRACE_TYPE_NAMES.iter() {
+            assert_eq!(TraceType::from_name(name).unwrap().name(), name);
+        }
+        assert_eq!(TraceType::from_name("boop"), None);
+    }
+}
@@ -1,7 +1,14 @@
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::marker::PhantomData;
 
-use game::{State, Action};
+use rand::Rng;
+
+use game::{State, Action, Reward, Easy21, ACTIONS, MIN_CARD, MAX_CARD,
+           MIN_SUM, MAX_SUM};
+use environment::Environment;
+use checkpoint::{Checkpoint, Checkpointable, CheckpointError};
+use traces::TraceType;
 
 
 pub fn increment<T: Eq + Hash + Copy>(map: &mut HashMap<T, f32>, key: T,
@@ -13,27 +20,340 @@ pub fn increment<T: Eq + Hash + Copy>(map: &mut HashMap<T, f32>, key: T,
 }
 
 
-pub struct VaryingStepSizer {
-    visits: HashMap<(State, Action), f32>,
+// Returns the action with the highest value, breaking ties in favor of
+// later actions.
+pub fn best_action<A: Copy, F: Fn(A) -> Reward>(actions: &[A], value: F) -> A {
+    let mut best = actions[0];
+    let mut best_value = value(best);
+
+    for &action in actions[1..].iter() {
+        let action_value = value(action);
+        if action_value >= best_value {
+            best = action;
+            best_value = action_value;
+        }
+    }
+
+    best
+}
+
+
+// Samples an index into the given action probabilities, which should sum
+// to 1. Any shortfall due to rounding goes to the last action.
+pub fn sample_action<T: Rng>(probabilities: &[f32], rng: &mut T) -> usize {
+    let mut remaining = rng.next_f32();
+
+    for (i, &probability) in probabilities.iter().enumerate() {
+        if remaining < probability {
+            return i;
+        }
+        remaining -= probability;
+    }
+
+    probabilities.len() - 1
+}
+
+
+fn table_index<E: Environment>(state: E::State,
+                                action: E::Action) -> Option<usize> {
+    E::state_index(state).map(|index| {
+        index * E::actions().len() + E::action_index(action)
+    })
+}
+
+fn expect_table_index<E: Environment>(state: E::State,
+                                       action: E::Action) -> usize {
+    match table_index::<E>(state, action) {
+        Some(index) => index,
+        None => panic!("{:?} has no index in a dense table", state),
+    }
+}
+
+
+// A dense table holding a value for every state/action pair of an
+// environment. States without an index (e.g. terminal ones) always
+// have a value of zero.
+pub struct ValueTable<E: Environment = Easy21> {
+    values: Vec<f32>,
+    env: PhantomData<E>,
+}
+
+impl<E: Environment> ValueTable<E> {
+    pub fn new() -> Self {
+        ValueTable {
+            values: vec![0.0; E::num_states() * E::actions().len()],
+            env: PhantomData,
+        }
+    }
+
+    pub fn get(&self, state: E::State, action: E::Action) -> f32 {
+        match table_index::<E>(state, action) {
+            Some(index) => self.values[index],
+            None => 0.0,
+        }
+    }
+
+    pub fn set(&mut self, state: E::State, action: E::Action, value: f32) {
+        self.values[expect_table_index::<E>(state, action)] = value;
+    }
+
+    // Adds the given amount to the pair's value, returning the new value.
+    pub fn increment(&mut self, state: E::State, action: E::Action,
+                     amount: f32) -> f32 {
+        let value = &mut self.values[expect_table_index::<E>(state, action)];
+        *value += amount;
+        *value
+    }
+
+    // Adds `scale` times each pair's eligibility trace to its value.
+    pub fn add_traces(&mut self, traces: &Traces<E>, scale: f32) {
+        for &index in traces.active.iter() {
+            self.values[index] += scale * traces.traces[index];
+        }
+    }
+}
+
+impl<E: Environment> Default for ValueTable<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ValueTable {
+    // Returns every pair with a non-zero value, e.g. for checkpointing.
+    pub fn to_hash_map(&self) -> HashMap<(State, Action), f32> {
+        let mut map = HashMap::new();
+        for dealer in MIN_CARD..MAX_CARD + 1 {
+            for player in MIN_SUM..MAX_SUM + 1 {
+                let state = State { dealer, player };
+                for &action in ACTIONS.iter() {
+                    let value = self.get(state, action);
+                    if value != 0.0 {
+                        map.insert((state, action), value);
+                    }
+                }
+            }
+        }
+        map
+    }
+
+    pub fn from_hash_map(map: &HashMap<(State, Action), f32>) -> Result<Self, CheckpointError> {
+        let mut table = Self::new();
+        for (&(state, action), &value) in map.iter() {
+            match table_index::<Easy21>(state, action) {
+                Some(index) => table.values[index] = value,
+                None => return Err(CheckpointError::Parse(
+                    format!("{:?} is out of range", state)
+                )),
+            }
+        }
+        Ok(table)
+    }
+}
+
+
+// A dense table holding a value for every state of an environment, e.g.
+// how often it's been visited. States without an index always have a
+// value of zero.
+pub struct StateTable<E: Environment = Easy21> {
+    values: Vec<f32>,
+    env: PhantomData<E>,
+}
+
+impl<E: Environment> StateTable<E> {
+    pub fn new() -> Self {
+        StateTable {
+            values: vec![0.0; E::num_states()],
+            env: PhantomData,
+        }
+    }
+
+    pub fn get(&self, state: E::State) -> f32 {
+        match E::state_index(state) {
+            Some(index) => self.values[index],
+            None => 0.0,
+        }
+    }
+
+    // Adds the given amount to the state's value, returning the new
+    // value.
+    pub fn increment(&mut self, state: E::State, amount: f32) -> f32 {
+        let index = match E::state_index(state) {
+            Some(index) => index,
+            None => panic!("{:?} has no index in a dense table", state),
+        };
+        self.values[index] += amount;
+        self.values[index]
+    }
+}
+
+impl<E: Environment> Default for StateTable<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateTable {
+    // Returns every state with a non-zero value, e.g. for checkpointing.
+    pub fn to_hash_map(&self) -> HashMap<State, f32> {
+        let mut map = HashMap::new();
+        for (index, &value) in self.values.iter().enumerate() {
+            if value != 0.0 {
+                map.insert(Easy21::state_at(index), value);
+            }
+        }
+        map
+    }
+
+    pub fn from_hash_map(map: &HashMap<State, f32>) -> Result<Self, CheckpointError> {
+        let mut table = Self::new();
+        for (&state, &value) in map.iter() {
+            match Easy21::state_index(state) {
+                Some(index) => table.values[index] = value,
+                None => return Err(CheckpointError::Parse(
+                    format!("{:?} is out of range", state)
+                )),
+            }
+        }
+        Ok(table)
+    }
+}
+
+
+// Eligibility traces for every state/action pair of an environment.
+// Only the pairs visited since the last clear() are kept track of, so
+// that updates don't need to iterate over the whole table.
+pub struct Traces<E: Environment = Easy21> {
+    traces: Vec<f32>,
+    is_active: Vec<bool>,
+    active: Vec<usize>,
+    env: PhantomData<E>,
 }
 
-impl VaryingStepSizer {
+impl<E: Environment> Traces<E> {
     pub fn new() -> Self {
-        VaryingStepSizer { visits: HashMap::new() }
+        let size = E::num_states() * E::actions().len();
+        Traces {
+            traces: vec![0.0; size],
+            is_active: vec![false; size],
+            active: Vec::new(),
+            env: PhantomData,
+        }
+    }
+
+    // Updates the trace of a pair that was just visited.
+    pub fn visit(&mut self, state: E::State, action: E::Action,
+                 trace_type: TraceType, step_size: f32) {
+        let index = expect_table_index::<E>(state, action);
+        if !self.is_active[index] {
+            self.is_active[index] = true;
+            self.active.push(index);
+        }
+        self.traces[index] = trace_type.visit(self.traces[index], step_size);
     }
 
-    pub fn update(&mut self, state: State, action: Action) -> f32 {
-        let visits = increment(&mut self.visits, (state, action), 1.0);
+    // Multiplies every trace by the given factor.
+    pub fn decay(&mut self, factor: f32) {
+        for &index in self.active.iter() {
+            self.traces[index] *= factor;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for &index in self.active.iter() {
+            self.traces[index] = 0.0;
+            self.is_active[index] = false;
+        }
+        self.active.clear();
+    }
+}
+
+impl<E: Environment> Default for Traces<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+pub struct VaryingStepSizer<E: Environment = Easy21> {
+    visits: ValueTable<E>,
+}
+
+impl<E: Environment> VaryingStepSizer<E> {
+    pub fn new() -> Self {
+        VaryingStepSizer { visits: ValueTable::new() }
+    }
+
+    pub fn update(&mut self, state: E::State, action: E::Action) -> f32 {
+        let visits = self.visits.increment(state, action, 1.0);
         1.0 / visits
     }
 }
 
 
+// Determines the step size to use when updating the value of a
+// state/action pair: either 1/N(s, a), where N(s, a) is the number of
+// times the pair has been visited, or a constant.
+pub enum StepSizer<E: Environment = Easy21> {
+    Varying(VaryingStepSizer<E>),
+    Constant(f32),
+}
+
+impl<E: Environment> StepSizer<E> {
+    pub fn new(step_size: Option<f32>) -> Self {
+        match step_size {
+            None => StepSizer::Varying(VaryingStepSizer::new()),
+            Some(value) => StepSizer::Constant(value),
+        }
+    }
+
+    pub fn update(&mut self, state: E::State, action: E::Action) -> f32 {
+        match *self {
+            StepSizer::Varying(ref mut sizer) => sizer.update(state, action),
+            StepSizer::Constant(value) => value,
+        }
+    }
+}
+
+impl StepSizer {
+    // Saves the visit counts, if any, under the given name, so that an
+    // algorithm can save more than one step sizer.
+    pub fn save_as(&self, checkpoint: &mut Checkpoint, name: &str) {
+        if let StepSizer::Varying(ref sizer) = *self {
+            checkpoint.set_value_table(name, &sizer.visits.to_hash_map());
+        }
+    }
+
+    pub fn load_as(&mut self, checkpoint: &Checkpoint,
+                   name: &str) -> Result<(), CheckpointError> {
+        if let StepSizer::Varying(ref mut sizer) = *self {
+            sizer.visits = ValueTable::from_hash_map(
+                &checkpoint.get_value_table(name)?
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Checkpointable for StepSizer {
+    fn save(&self, checkpoint: &mut Checkpoint) {
+        self.save_as(checkpoint, "step_sizer_visits");
+    }
+
+    fn load(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        self.load_as(checkpoint, "step_sizer_visits")
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
+    use rand::{SeedableRng, StdRng};
+
     use game::{State, Action};
+    use game::Action::*;
     use util::*;
 
     #[test]
@@ -50,9 +370,90 @@ mod tests {
         assert_eq!(*h.get(&600).unwrap(), 5.0);
     }
 
+    #[test]
+    fn test_best_action_works() {
+        assert_eq!(best_action(&[Hit, Stick], |a| {
+            if a == Hit { 1.0 } else { 0.0 }
+        }), Hit);
+        assert_eq!(best_action(&[Hit, Stick], |a| {
+            if a == Hit { 0.0 } else { 1.0 }
+        }), Stick);
+        assert_eq!(best_action(&[Hit, Stick], |_| 0.0), Stick);
+    }
+
+    #[test]
+    fn test_value_table_works() {
+        let mut table: ValueTable = ValueTable::new();
+        let state = State { dealer: 3, player: 12 };
+
+        assert_eq!(table.get(state, Hit), 0.0);
+        table.set(state, Hit, 0.5);
+        assert_eq!(table.increment(state, Hit, 0.25), 0.75);
+        assert_eq!(table.get(state, Stick), 0.0);
+
+        // Terminal states always have a value of zero.
+        assert_eq!(table.get(State { dealer: 3, player: 25 }, Hit), 0.0);
+
+        let map = table.to_hash_map();
+        assert_eq!(map.len(), 1);
+        assert_eq!(ValueTable::from_hash_map(&map).unwrap().get(state, Hit),
+                   0.75);
+    }
+
+    #[test]
+    fn test_sample_action_works() {
+        let mut rng: StdRng = SeedableRng::from_seed(&[1, 2, 3, 4][..]);
+
+        for _ in 0..10 {
+            assert_eq!(sample_action(&[0.0, 1.0], &mut rng), 1);
+            assert_eq!(sample_action(&[1.0, 0.0], &mut rng), 0);
+            assert_eq!(sample_action(&[0.0, 0.0], &mut rng), 1);
+        }
+    }
+
+    #[test]
+    fn test_state_table_works() {
+        let mut table: StateTable = StateTable::new();
+        let state = State { dealer: 3, player: 12 };
+
+        assert_eq!(table.get(state), 0.0);
+        assert_eq!(table.increment(state, 2.0), 2.0);
+        assert_eq!(table.get(State { dealer: 3, player: 25 }), 0.0);
+
+        let map = table.to_hash_map();
+        assert_eq!(map.len(), 1);
+        assert_eq!(StateTable::from_hash_map(&map).unwrap().get(state), 2.0);
+
+        let mut map = HashMap::new();
+        map.insert(State { dealer: 3, player: 25 }, 1.0);
+        assert!(StateTable::from_hash_map(&map).is_err());
+    }
+
+    #[test]
+    fn test_traces_work() {
+        let mut table: ValueTable = ValueTable::new();
+        let mut traces: Traces = Traces::new();
+        let a = State { dealer: 3, player: 12 };
+        let b = State { dealer: 4, player: 15 };
+
+        traces.visit(a, Hit, TraceType::Accumulating, 1.0);
+        traces.decay(0.5);
+        traces.visit(b, Stick, TraceType::Replacing, 1.0);
+        traces.visit(b, Stick, TraceType::Replacing, 1.0);
+        table.add_traces(&traces, 2.0);
+
+        assert_eq!(table.get(a, Hit), 1.0);
+        assert_eq!(table.get(b, Stick), 2.0);
+
+        traces.clear();
+        table.add_traces(&traces, 2.0);
+
+        assert_eq!(table.get(a, Hit), 1.0);
+    }
+
     #[test]
     fn test_varying_step_sizer_works() {
-        let mut s = VaryingStepSizer::new();
+        let mut s: VaryingStepSizer = VaryingStepSizer::new();
         let state = State { dealer: 1, player: 1 };
 
         assert_eq!(s.update(state, Action::Hit), 1.0);
@@ -60,4 +461,16 @@ mod tests {
         assert_eq!(s.update(state, Action::Hit), 1.0 / 3.0);
         assert_eq!(s.update(state, Action::Stick), 1.0);
     }
+
+    #[test]
+    fn test_step_sizer_works() {
+        let state = State { dealer: 1, player: 1 };
+        let mut varying: StepSizer = StepSizer::new(None);
+        let mut constant: StepSizer = StepSizer::new(Some(0.1));
+
+        assert_eq!(varying.update(state, Action::Hit), 1.0);
+        assert_eq!(varying.update(state, Action::Hit), 0.5);
+        assert_eq!(constant.update(state, Action::Hit), 0.1);
+        assert_eq!(constant.update(state, Action::Hit), 0.1);
+    }
 }
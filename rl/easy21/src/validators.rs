@@ -1,19 +1,92 @@
+use game::{MAX_CARD, MAX_SUM};
+use mlp::MAX_HIDDEN_LAYERS;
+
 pub fn episodes(v: i32) -> bool {
     v > 0
 }
 
 pub fn lambda(v: f32) -> bool {
-    v >= 0.0 && v <= 1.0
+    (0.0..=1.0).contains(&v)
+}
+
+pub fn n(v: i32) -> bool {
+    v > 0
+}
+
+pub fn discount(v: f32) -> bool {
+    (0.0..=1.0).contains(&v)
 }
 
 pub fn epsilon(v: f32) -> bool {
-    v >= 0.0 && v <= 1.0
+    (0.0..=1.0).contains(&v)
 }
 
 pub fn step_size(v: f32) -> bool {
     v > 0.0
 }
 
+pub fn temperature(v: f32) -> bool {
+    v > 0.0 && v.is_finite()
+}
+
+pub fn temperature_decay(v: f32) -> bool {
+    v > 0.0 && v <= 1.0
+}
+
+pub fn ucb_c(v: f32) -> bool {
+    v >= 0.0 && v.is_finite()
+}
+
+pub fn tilings(v: i32) -> bool {
+    v > 0
+}
+
+pub fn tile_width(v: i32) -> bool {
+    v > 0
+}
+
+pub fn degree(v: i32) -> bool {
+    v > 0
+}
+
+pub fn hidden_layers(v: i32) -> bool {
+    v > 0 && v as usize <= MAX_HIDDEN_LAYERS
+}
+
+pub fn width(v: i32) -> bool {
+    v > 0
+}
+
+pub fn buffer_size(v: i32) -> bool {
+    v > 0
+}
+
+pub fn batch_size(v: i32) -> bool {
+    v > 0
+}
+
+pub fn sync_interval(v: i32) -> bool {
+    v > 0
+}
+
+pub fn n0(v: f32) -> bool {
+    v > 0.0
+}
+
+pub fn red_probability(v: f32) -> bool {
+    (0.0..=1.0).contains(&v)
+}
+
+// The dealer's first card must always be below the stick minimum, since
+// a state whose dealer sum has reached it is terminal.
+pub fn dealer_stick_min(v: i32) -> bool {
+    (MAX_CARD + 1..=MAX_SUM).contains(&v)
+}
+
+pub fn seed(v: &[usize]) -> bool {
+    !v.is_empty()
+}
+
 #[cfg(test)]
 mod tests {
     use validators::*;
@@ -34,6 +107,22 @@ mod tests {
         assert!(!lambda(1.1));
     }
 
+    #[test]
+    fn test_n() {
+        assert!(n(1));
+        assert!(!n(0));
+        assert!(!n(-1));
+    }
+
+    #[test]
+    fn test_discount() {
+        assert!(discount(0.0));
+        assert!(discount(0.9));
+        assert!(discount(1.0));
+        assert!(!discount(-0.1));
+        assert!(!discount(1.1));
+    }
+
     #[test]
     fn test_epsilon() {
         assert!(epsilon(0.0));
@@ -49,4 +138,117 @@ mod tests {
         assert!(!step_size(-1.0));
         assert!(step_size(1.1));
     }
+
+    #[test]
+    fn test_temperature() {
+        assert!(temperature(0.5));
+        assert!(temperature(10.0));
+        assert!(!temperature(0.0));
+        assert!(!temperature(f32::INFINITY));
+    }
+
+    #[test]
+    fn test_temperature_decay() {
+        assert!(temperature_decay(0.999));
+        assert!(temperature_decay(1.0));
+        assert!(!temperature_decay(0.0));
+        assert!(!temperature_decay(1.1));
+    }
+
+    #[test]
+    fn test_ucb_c() {
+        assert!(ucb_c(0.0));
+        assert!(ucb_c(2.0));
+        assert!(!ucb_c(-1.0));
+    }
+
+    #[test]
+    fn test_tilings() {
+        assert!(tilings(1));
+        assert!(tilings(8));
+        assert!(!tilings(0));
+    }
+
+    #[test]
+    fn test_tile_width() {
+        assert!(tile_width(1));
+        assert!(!tile_width(0));
+        assert!(!tile_width(-3));
+    }
+
+    #[test]
+    fn test_degree() {
+        assert!(degree(1));
+        assert!(degree(3));
+        assert!(!degree(0));
+    }
+
+    #[test]
+    fn test_hidden_layers() {
+        assert!(hidden_layers(1));
+        assert!(hidden_layers(2));
+        assert!(!hidden_layers(0));
+        assert!(!hidden_layers(3));
+    }
+
+    #[test]
+    fn test_width() {
+        assert!(width(1));
+        assert!(width(64));
+        assert!(!width(0));
+    }
+
+    #[test]
+    fn test_buffer_size() {
+        assert!(buffer_size(1));
+        assert!(buffer_size(10000));
+        assert!(!buffer_size(0));
+    }
+
+    #[test]
+    fn test_batch_size() {
+        assert!(batch_size(32));
+        assert!(!batch_size(0));
+        assert!(!batch_size(-1));
+    }
+
+    #[test]
+    fn test_sync_interval() {
+        assert!(sync_interval(1));
+        assert!(!sync_interval(0));
+    }
+
+    #[test]
+    fn test_n0() {
+        assert!(n0(100.0));
+        assert!(n0(0.5));
+        assert!(!n0(0.0));
+        assert!(!n0(-1.0));
+    }
+
+    #[test]
+    fn test_red_probability() {
+        assert!(red_probability(0.0));
+        assert!(red_probability(1.0));
+        assert!(!red_probability(-0.1));
+        assert!(!red_probability(1.1));
+    }
+
+    #[test]
+    fn test_dealer_stick_min() {
+        assert!(dealer_stick_min(17));
+        assert!(dealer_stick_min(21));
+        assert!(dealer_stick_min(11));
+        assert!(!dealer_stick_min(1));
+        assert!(!dealer_stick_min(8));
+        assert!(!dealer_stick_min(10));
+        assert!(!dealer_stick_min(22));
+    }
+
+    #[test]
+    fn test_seed() {
+        assert!(seed(&[1, 2, 3, 4]));
+        assert!(seed(&[0]));
+        assert!(!seed(&[]));
+    }
 }